@@ -0,0 +1,128 @@
+use super::*;
+
+#[derive(geng::asset::Load)]
+pub struct Assets {
+    pub sprites: Sprites,
+    #[load(serde, path = "surface_styles.json")]
+    pub surface_styles: HashMap<String, SurfaceStyle>,
+    #[load(serde, path = "character.json")]
+    pub character: CharacterSkin,
+    pub sounds: Sounds,
+}
+
+#[derive(geng::asset::Load)]
+pub struct Sounds {
+    pub footstep: geng::Sound,
+    pub thud: geng::Sound,
+    pub grab: geng::Sound,
+    pub release: geng::Sound,
+    #[load(options(looped = "true"))]
+    pub wind: geng::Sound,
+    #[load(serde, path = "config.json")]
+    pub config: SoundConfig,
+}
+
+/// Volumes of the sounds and the speeds they react to, in world units per second.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoundConfig {
+    pub footstep_volume: f32,
+    /// Running slower than this makes no footsteps.
+    pub footstep_min_speed: f32,
+    /// Running speed at which the footsteps are the loudest.
+    pub footstep_full_speed: f32,
+    pub thud_volume: f32,
+    pub thud_min_impact: f32,
+    pub thud_full_impact: f32,
+    pub grab_volume: f32,
+    pub release_volume: f32,
+    /// Volume of the release sound when the hold is broken.
+    pub hold_broke_volume: f32,
+    pub wind_volume: f32,
+    pub wind_min_speed: f32,
+    pub wind_full_speed: f32,
+}
+
+/// Textures of the level decorations, referenced by the names listed in `list.json`.
+#[derive(geng::asset::Load)]
+pub struct Sprites {
+    #[load(serde, path = "list.json")]
+    names: Vec<String>,
+    #[load(listed_in = "list.json", path = "*.png")]
+    textures: Vec<ugli::Texture>,
+}
+
+/// Look of the surfaces referencing the style by name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SurfaceStyle {
+    pub thickness: f32,
+    pub color: Rgba<f32>,
+    /// Sprite repeated along the surface, scaled to the thickness.
+    pub texture: Option<String>,
+    pub fill: Option<SurfaceFill>,
+}
+
+/// A solid area between the surface and its copy moved by the offset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SurfaceFill {
+    pub offset: vec2<f32>,
+    pub color: Rgba<f32>,
+}
+
+/// Look of the player character, drawn as bones between the joints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterSkin {
+    pub torso: SkinBone,
+    pub head: SkinBone,
+    /// Drawn from the shoulder to the elbow.
+    pub upper_arm: SkinPart,
+    /// Drawn from the elbow to the hand.
+    pub forearm: SkinPart,
+    /// Drawn around the hand, oriented along the forearm.
+    pub hand: SkinPart,
+}
+
+/// A bone fixed to the body center.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkinBone {
+    /// Ends of the bone relative to the body center, in body radii, for the character facing right.
+    pub from: vec2<f32>,
+    pub to: vec2<f32>,
+    #[serde(flatten)]
+    pub part: SkinPart,
+}
+
+/// How a bone is drawn: a capsule of the color, or the sprite stretched along the bone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkinPart {
+    pub width: f32,
+    /// Fill of the capsule, or the tint of the sprite.
+    pub color: Rgba<f32>,
+    /// The sprite points along the bone from left to right and is mirrored when facing left.
+    #[serde(default)]
+    pub sprite: Option<String>,
+}
+
+impl Sprites {
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ugli::Texture> {
+        self.names
+            .iter()
+            .position(|sprite| sprite == name)
+            .map(|index| &self.textures[index])
+    }
+}
+
+impl Default for SurfaceStyle {
+    fn default() -> Self {
+        Self {
+            thickness: 0.1,
+            color: Rgba::GRAY,
+            texture: None,
+            fill: None,
+        }
+    }
+}
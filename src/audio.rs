@@ -0,0 +1,107 @@
+use super::*;
+
+/// Plays the sounds of the game in response to the gameplay.
+pub struct Audio {
+    geng: Geng,
+    assets: Rc<Assets>,
+    wind: geng::SoundEffect,
+}
+
+impl Audio {
+    pub fn new(geng: &Geng, assets: &Rc<Assets>, volume: f32) -> Self {
+        let mut wind = assets.sounds.wind.effect();
+        wind.set_volume(0.0);
+        wind.play();
+        let audio = Self {
+            geng: geng.clone(),
+            assets: assets.clone(),
+            wind,
+        };
+        audio.set_volume(volume);
+        audio
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.geng.audio().set_volume(volume.clamp(0.0, 1.0) as f64);
+    }
+
+    /// Silences the continuous sounds, until the next update.
+    pub fn pause(&mut self) {
+        self.wind.set_volume(0.0);
+    }
+
+    pub fn handle_events(&self, events: &[Event]) {
+        let sounds = &self.assets.sounds;
+        let config = &sounds.config;
+        for event in events {
+            match *event {
+                Event::Landed { impact, .. } => {
+                    let t = fraction(
+                        impact.as_f32(),
+                        config.thud_min_impact,
+                        config.thud_full_impact,
+                    );
+                    if t > 0.0 {
+                        play(&sounds.thud, config.thud_volume * t);
+                    }
+                }
+                Event::HoldStarted { .. } => play(&sounds.grab, config.grab_volume),
+                Event::HoldEnded { reason, .. } => {
+                    let volume = match reason {
+                        HoldEndReason::Released => config.release_volume,
+                        HoldEndReason::Broken => config.hold_broke_volume,
+                    };
+                    play(&sounds.release, volume);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn update(&mut self, model: &Model) {
+        let sounds = &self.assets.sounds;
+        let config = &sounds.config;
+        let player = &model.player;
+        let velocity = player.center.velocity.map(Coord::as_f32);
+        if model.death.is_some() {
+            self.wind.set_volume(0.0);
+            return;
+        }
+
+        // Footsteps follow the arm swing, like the running itself
+        if player.ground_normal.is_some() && player.history.swung_past_center() {
+            let t = fraction(
+                velocity.len(),
+                config.footstep_min_speed,
+                config.footstep_full_speed,
+            );
+            if t > 0.0 {
+                play(&sounds.footstep, config.footstep_volume * t);
+            }
+        }
+
+        let t = fraction(
+            velocity.len(),
+            config.wind_min_speed,
+            config.wind_full_speed,
+        );
+        self.wind.set_volume((config.wind_volume * t) as f64);
+    }
+}
+
+impl Drop for Audio {
+    fn drop(&mut self) {
+        self.wind.stop();
+    }
+}
+
+fn play(sound: &geng::Sound, volume: f32) {
+    let mut effect = sound.effect();
+    effect.set_volume(volume as f64);
+    effect.play();
+}
+
+/// Returns where the value is between the bounds, clamped to `0..=1`.
+fn fraction(value: f32, min: f32, max: f32) -> f32 {
+    ((value - min) / (max - min).max(1e-3)).clamp(0.0, 1.0)
+}
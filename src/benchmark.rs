@@ -0,0 +1,143 @@
+use super::*;
+
+use crate::env::BatchEnv;
+
+const QUERIES: usize = 10_000;
+const FRAMES: usize = 1_000;
+const ENVS: usize = 16;
+/// Width of the generated level per surface.
+const SPACING: f32 = 1.0;
+
+/// Checks whether the surface is found by the query around the point with the radius.
+type SurfaceTest = fn(&Surface, Position, f32) -> bool;
+
+/// Measures the surface queries on a generated level with the given number of surfaces,
+/// comparing the linear scans with the spatial index, and prints the results.
+pub fn run(surfaces: usize) {
+    let mut rng = Lcg(surfaces as u64 + 1);
+    let level = generate_level(surfaces, &mut rng);
+    let width = surfaces as f32 * SPACING;
+    let points: Vec<Position> = (0..QUERIES)
+        .map(|_| vec2(rng.next() * width, rng.next() * 10.0 - 5.0).map(Coord::new))
+        .collect();
+
+    let timer = Timer::new();
+    let grid = SurfaceGrid::new(&level.surfaces);
+    println!(
+        "{} surfaces, index built in {:.2} ms",
+        surfaces,
+        timer.elapsed().as_secs_f64() * 1000.0
+    );
+
+    let queries: [(&str, f32, SurfaceTest); 3] = [
+        ("collisions", 1.0, |surface, point, radius| {
+            surface.delta_to(point).len().as_f32() < radius
+        }),
+        ("hover", 0.5, |surface, point, radius| {
+            surface.delta_to(point).len().as_f32() <= radius
+        }),
+        ("snap", 0.5, |surface, point, radius| {
+            [surface.p1, surface.p2]
+                .iter()
+                .any(|&p| (p - point).len().as_f32() < radius)
+        }),
+    ];
+    for (name, radius, test) in queries {
+        let timer = Timer::new();
+        let linear: usize = points
+            .iter()
+            .map(|&point| {
+                level
+                    .surfaces
+                    .iter()
+                    .filter(|surface| test(surface, point, radius))
+                    .count()
+            })
+            .sum();
+        let linear_time = timer.elapsed().as_secs_f64();
+
+        let timer = Timer::new();
+        let indexed: usize = points
+            .iter()
+            .map(|&point| {
+                let area = Aabb2::point(point).extend_uniform(Coord::new(radius));
+                grid.surfaces_in(&level.surfaces, area)
+                    .filter(|(_, surface)| test(surface, point, radius))
+                    .count()
+            })
+            .sum();
+        let indexed_time = timer.elapsed().as_secs_f64();
+
+        assert_eq!(linear, indexed, "the index missed some surfaces");
+        println!(
+            "{name:>10}: linear {:8.3} us, indexed {:8.3} us per query ({:.0}x)",
+            linear_time / QUERIES as f64 * 1e6,
+            indexed_time / QUERIES as f64 * 1e6,
+            linear_time / indexed_time.max(1e-9),
+        );
+    }
+
+    let mut batch = BatchEnv::new(&level, ENVS, surfaces as u64);
+    let mut model = Model::new(level);
+    let mut control = BodyControl {
+        move_speed: Coord::ONE,
+        hold: true,
+        ..default()
+    };
+    let timer = Timer::new();
+    for _ in 0..FRAMES {
+        model.update(&mut control, Time::new(1.0 / 60.0));
+    }
+    println!(
+        "{:>10}: {:.3} ms per frame",
+        "update",
+        timer.elapsed().as_secs_f64() / FRAMES as f64 * 1000.0
+    );
+
+    // Random controls, as an untrained controller would give
+    let timer = Timer::new();
+    let mut episodes = 0;
+    for _ in 0..FRAMES / ENVS {
+        let controls: Vec<BodyControl> = (0..ENVS)
+            .map(|_| BodyControl {
+                hand_target: vec2(rng.next() * 4.0 - 2.0, rng.next() * 4.0 - 2.0).map(Coord::new),
+                move_speed: Coord::new(rng.next() * 2.0 - 1.0),
+                target_height: Coord::ONE,
+                hold: rng.next() < 0.5,
+                jump: rng.next() < 0.1,
+            })
+            .collect();
+        episodes += batch
+            .step(&controls)
+            .iter()
+            .filter(|&&(_, _, done)| done)
+            .count();
+    }
+    println!(
+        "{:>10}: {:.0} steps per second over {ENVS} environments, {episodes} episodes ended",
+        "env",
+        (FRAMES / ENVS * ENVS) as f64 / timer.elapsed().as_secs_f64()
+    );
+}
+
+/// Generates bumpy ground with floating platforms above it.
+fn generate_level(surfaces: usize, rng: &mut Lcg) -> Level {
+    let mut level = Level::new();
+    let mut last = vec2(0.0, 0.0);
+    for i in 0..surfaces {
+        let p1 = if i % 4 == 3 {
+            vec2(last.x, 2.0 + rng.next() * 3.0)
+        } else {
+            last
+        };
+        let p2 = p1 + vec2(SPACING, rng.next() - 0.5);
+        if i % 4 != 3 {
+            last = p2;
+        }
+        level
+            .surfaces
+            .push(Surface::new(p1.map(Coord::new), p2.map(Coord::new)));
+    }
+    level.spawn_point = vec2(Coord::ONE, Coord::new(2.0));
+    level
+}
@@ -0,0 +1,252 @@
+use super::*;
+
+mod nav;
+
+pub use nav::*;
+
+/// Distance along the path over which the arm is swung to run faster.
+const SWING_DISTANCE: f32 = 3.0;
+/// Swings of the arm per second while running.
+const SWING_FREQUENCY: f32 = 1.0;
+/// Horizontal distance to the node at which it counts as reached.
+const ARRIVE_DISTANCE: f32 = 0.5;
+/// Drops lower than this are taken by walking off the edge unless there is a gap.
+const STEP_DOWN_HEIGHT: f32 = 0.5;
+/// Moves taking longer than this, plus a second per two units of distance, have failed.
+const MOVE_TIMEOUT: f32 = 3.0;
+/// Extra cost of a failed move, so that another way is tried next time.
+const FAILURE_PENALTY: f32 = 50.0;
+/// How far in front of the wall the body stands to jump for a ledge.
+const CLIMB_RUNUP: f32 = 0.8;
+const HEADLESS_FPS: f32 = 60.0;
+/// Longest run without the window before the bot is considered stuck, in seconds.
+const HEADLESS_TIME_LIMIT: f32 = 120.0;
+
+/// Plays the level by itself, producing the same controls as the player would.
+pub struct Bot {
+    graph: NavGraph,
+    /// Remaining moves to the finish, each with the node it leads to.
+    path: Vec<(usize, NavMove)>,
+    /// Node the current move starts from.
+    from: usize,
+    penalties: HashMap<(usize, usize), R32>,
+    time: Time,
+    move_time: Time,
+    jumped: bool,
+}
+
+impl Bot {
+    pub fn new(level: &Level) -> Self {
+        Self {
+            graph: NavGraph::new(level),
+            path: Vec::new(),
+            from: 0,
+            penalties: HashMap::new(),
+            time: Time::ZERO,
+            move_time: Time::ZERO,
+            jumped: false,
+        }
+    }
+
+    /// Decides what to do in the next step of the simulation.
+    pub fn control(&mut self, model: &Model, delta_time: Time) -> BodyControl {
+        self.time += delta_time;
+        self.move_time += delta_time;
+        if model.death.is_some() || model.run.is_finished() {
+            self.path.clear();
+            return BodyControl::default();
+        }
+
+        let body = &model.player;
+        let feet = body.center.position - vec2(Coord::ZERO, body.center.radius);
+        let grounded = body.ground_normal.is_some();
+        if self.path.is_empty() && grounded {
+            self.plan(model, feet);
+        }
+        let Some(&(to, kind)) = self.path.first() else {
+            // The last node may be just outside of the finish area
+            let Some(finish) = model.level.finish.filter(|_| grounded) else {
+                return BodyControl::default();
+            };
+            let dx = finish.center().x - body.center.position.x;
+            return BodyControl {
+                move_speed: r32(dx.as_f32().signum()),
+                target_height: Coord::ONE,
+                ..default()
+            };
+        };
+
+        // Progress along the path
+        let target = self.graph.nodes[to].position;
+        let delta = (target - feet).map(Coord::as_f32);
+        let arrived = grounded
+            && delta.x.abs() < ARRIVE_DISTANCE
+            && delta.y.abs() < 1.0
+            && body.holding_to.is_none();
+        if arrived {
+            self.path.remove(0);
+            self.from = to;
+            self.move_time = Time::ZERO;
+            self.jumped = false;
+            return self.control(model, Time::ZERO);
+        }
+        let distance = (self.graph.nodes[self.from].position - target).len();
+        if self.move_time > Coord::new(MOVE_TIMEOUT) + distance / Coord::new(2.0) {
+            let penalty = self.penalties.entry((self.from, to)).or_insert(R32::ZERO);
+            *penalty += R32::new(FAILURE_PENALTY);
+            self.path.clear();
+            self.move_time = Time::ZERO;
+            return BodyControl::default();
+        }
+
+        let direction = r32(delta.x.signum());
+        let reach = body.arm.max_reach();
+        let mut control = BodyControl {
+            hand_target: vec2(Coord::ZERO, -reach),
+            move_speed: if delta.x.abs() > ARRIVE_DISTANCE / 2.0 {
+                direction
+            } else {
+                Coord::ZERO
+            },
+            target_height: Coord::ONE,
+            hold: false,
+            jump: false,
+        };
+        let start = self.graph.nodes[self.from].position;
+        // How far past the start of the move the body is
+        let past_start = (feet.x - start.x).as_f32() * direction.as_f32();
+        match kind {
+            NavMove::Walk => {
+                if grounded && self.straight_distance(feet) > SWING_DISTANCE {
+                    // Swinging the arm back and forth makes the running faster
+                    let phase = self.time.as_f32() * SWING_FREQUENCY * 2.0 * f32::PI;
+                    control.hand_target = vec2(r32(phase.sin()) * reach, -reach / r32(3.0));
+                }
+            }
+            NavMove::Jump => {
+                let drop = delta.y < -STEP_DOWN_HEIGHT && delta.x.abs() < 1.5;
+                if grounded && !self.jumped && !drop && past_start > -ARRIVE_DISTANCE {
+                    self.jumped = true;
+                    control.jump = true;
+                    control.hand_target = vec2(direction * r32(0.3), reach);
+                }
+            }
+            NavMove::Climb => {
+                let ledge = target - body.center.position;
+                match body.holding_to {
+                    Some(_) => {
+                        // Pull up, then let go once above the ledge
+                        control.hand_target = vec2(-direction * r32(0.3), -reach);
+                        control.hold = ledge.y > -body.center.radius * r32(0.8);
+                    }
+                    None => {
+                        let runup = (target.x - feet.x).as_f32() * direction.as_f32();
+                        if grounded {
+                            control.move_speed = if runup > CLIMB_RUNUP {
+                                direction
+                            } else {
+                                -direction
+                            };
+                            control.jump = (runup - CLIMB_RUNUP).abs() < 0.4;
+                        }
+                        control.hand_target = ledge;
+                        control.hold = ledge.len() < reach + r32(0.3);
+                    }
+                }
+            }
+        }
+        control
+    }
+
+    /// Finds the path from the position to the finish.
+    fn plan(&mut self, model: &Model, feet: vec2<Coord>) {
+        let Some(finish) = model.level.finish else {
+            return;
+        };
+        let Some(from) = self.graph.nearest(feet) else {
+            return;
+        };
+        let goal = self
+            .graph
+            .nodes
+            .iter()
+            .position(|node| finish.contains(node.position + vec2(Coord::ZERO, Coord::ONE)))
+            .or_else(|| self.graph.nearest(finish.center()));
+        let Some(goal) = goal else {
+            return;
+        };
+        self.from = from;
+        self.move_time = Time::ZERO;
+        self.jumped = false;
+        self.path = self
+            .graph
+            .path(from, goal, &self.penalties)
+            .unwrap_or_default();
+    }
+
+    /// Returns the horizontal distance the body can keep walking in the current direction.
+    fn straight_distance(&self, feet: vec2<Coord>) -> f32 {
+        let mut distance = 0.0;
+        let mut position = feet;
+        let mut direction = None;
+        for &(node, kind) in &self.path {
+            let next = self.graph.nodes[node].position;
+            let dx = (next.x - position.x).as_f32();
+            if kind != NavMove::Walk || direction.is_some_and(|d: f32| d != dx.signum()) {
+                break;
+            }
+            direction = Some(dx.signum());
+            distance += dx.abs();
+            position = next;
+        }
+        distance
+    }
+}
+
+/// Lets the bot play the level without the window for up to the time limit,
+/// returns the time of the run if it reaches the finish.
+pub fn play(level: Level, time_limit: Time) -> Option<Time> {
+    let mut bot = Bot::new(&level);
+    let mut model = Model::new(level);
+    let delta_time = Time::new(1.0 / HEADLESS_FPS);
+    let frames = (time_limit.as_f32() * HEADLESS_FPS) as usize;
+    for _ in 0..frames {
+        if model.run.is_finished() {
+            break;
+        }
+        let mut control = bot.control(&model, delta_time);
+        model.update(&mut control, delta_time);
+    }
+    model.run.recording.finish_time
+}
+
+/// Plays the level file with the bot and prints the result, returns whether it has finished.
+pub fn run(path: &std::path::Path) -> bool {
+    let Some(level) = Level::load_full(path) else {
+        println!("Failed to load the level");
+        return false;
+    };
+    match play(level, Time::new(HEADLESS_TIME_LIMIT)) {
+        Some(time) => {
+            println!("The bot has finished in {:.2} s", time.as_f32());
+            true
+        }
+        None => {
+            println!("The bot has not finished in {HEADLESS_TIME_LIMIT} s");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bot_finishes_the_level() {
+        let path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/levels/reachable.json");
+        let level = Level::load_full(path).unwrap();
+        assert!(play(level, Time::new(60.0)).is_some());
+    }
+}
@@ -0,0 +1,351 @@
+use super::*;
+
+/// Longer walkable surfaces are split into nodes this far apart, so that jumps can start
+/// and end in the middle of them.
+const NODE_SPACING: f32 = 2.0;
+/// Nodes closer than this are considered connected by walking.
+const JOIN_DISTANCE: f32 = 0.3;
+/// Fraction of the ideal jumps and climbs relied upon, since the body rarely gets them perfect.
+const MOVEMENT_MARGIN: f32 = 0.85;
+/// Highest drop that is taken without a second thought.
+const MAX_FALL_HEIGHT: f32 = 20.0;
+/// Extra cost of the moves, in world units of walking, so that walking is preferred.
+const JUMP_COST: f32 = 2.0;
+const CLIMB_COST: f32 = 6.0;
+/// Cost of a unit of distance covered in the air, so that the shorter jumps are preferred.
+const AIR_DISTANCE_COST: f32 = 2.0;
+
+/// How far the body can get with each of the moves, derived from the movement parameters.
+#[derive(Debug, Clone, Copy)]
+pub struct MovementLimits {
+    /// Steepest walkable surface, in radians from the horizontal.
+    pub walkable_angle: f32,
+    /// Highest the feet can rise in a jump with the arm at rest.
+    pub jump_height: f32,
+    /// Highest the feet can rise in a jump with the arm swung up as fast as it goes,
+    /// which the moves leave out since it is hard to get right.
+    pub swing_jump_height: f32,
+    /// Furthest horizontal distance covered by a running jump.
+    pub jump_distance: f32,
+    /// Highest ledge above the feet that can be grabbed from a jump and climbed onto.
+    pub climb_height: f32,
+    /// Furthest horizontal distance to a ledge that can be grabbed from a jump.
+    pub climb_distance: f32,
+}
+
+/// A place where the body can stand, with the feet at the position.
+#[derive(Debug, Clone, Copy)]
+pub struct NavNode {
+    pub position: vec2<Coord>,
+    pub surface: usize,
+    /// Whether the node is an end of its surface that the hand can grab.
+    pub ledge: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NavMove {
+    Walk,
+    /// Jump over a gap, onto a higher place, or drop down.
+    Jump,
+    /// Jump, grab the ledge and pull up onto it.
+    Climb,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NavEdge {
+    pub to: usize,
+    pub kind: NavMove,
+    pub cost: R32,
+}
+
+/// Graph of the places of the level the body can stand on, connected by the moves between them.
+#[derive(Debug, Clone)]
+pub struct NavGraph {
+    pub limits: MovementLimits,
+    pub nodes: Vec<NavNode>,
+    pub edges: Vec<Vec<NavEdge>>,
+}
+
+impl MovementLimits {
+    pub fn new(body: &Body) -> Self {
+        let gravity = -logic::GRAVITY.y;
+        let up = vec2(Coord::ZERO, Coord::ONE);
+        let jump_speed = |arm_impulse: Coord| {
+            logic::jump_velocity(up, up * arm_impulse, body.center.mass)
+                .y
+                .as_f32()
+        };
+        let height = |speed: f32| speed.sqr() / (2.0 * gravity);
+        let rest_speed = jump_speed(Coord::ZERO);
+        let air_time = 2.0 * rest_speed / gravity;
+        let jump_height = height(rest_speed);
+        let radius = body.center.radius.as_f32();
+        let reach = body.arm.max_reach().as_f32();
+        Self {
+            walkable_angle: logic::GROUND_ANGLE,
+            jump_height: jump_height * MOVEMENT_MARGIN,
+            swing_jump_height: height(jump_speed(body.arm.max_impulse())) * MOVEMENT_MARGIN,
+            jump_distance: MAX_RUNNING_SPEED * air_time * MOVEMENT_MARGIN,
+            // The hand reaches above the body center at the top of the jump
+            climb_height: (jump_height + radius + reach) * MOVEMENT_MARGIN,
+            climb_distance: radius + reach * MOVEMENT_MARGIN,
+        }
+    }
+}
+
+impl NavGraph {
+    pub fn new(level: &Level) -> Self {
+        let body = Body::new(level.spawn_point);
+        let radius = body.center.radius;
+        let mut graph = Self {
+            limits: MovementLimits::new(&body),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        };
+        let limits = graph.limits;
+
+        // Nodes along the walkable surfaces, connected by walking
+        let mut walks = Vec::new();
+        for (index, surface) in level.surfaces.iter().enumerate() {
+            let walkable = is_walkable(surface, limits.walkable_angle);
+            if surface.flags.spikes || surface.flags.disabled || !walkable {
+                continue;
+            }
+            let length = (surface.p2 - surface.p1).len().as_f32();
+            let steps = (length / NODE_SPACING).ceil().max(1.0) as usize;
+            for step in 0..=steps {
+                let t = Coord::new(step as f32 / steps as f32);
+                graph.nodes.push(NavNode {
+                    position: surface.p1 + (surface.p2 - surface.p1) * t,
+                    surface: index,
+                    ledge: step == 0 || step == steps,
+                });
+                graph.edges.push(Vec::new());
+                if step > 0 {
+                    walks.push(graph.nodes.len() - 1);
+                }
+            }
+        }
+        // The nodes covered by hazards are left out of the graph
+        let deadly: Vec<bool> = graph
+            .nodes
+            .iter()
+            .map(|node| {
+                let center = node.position + vec2(Coord::ZERO, radius);
+                level
+                    .hazards
+                    .iter()
+                    .any(|hazard| hazard.touches(center, radius, Time::ZERO))
+            })
+            .collect();
+        for node in walks {
+            if !deadly[node] && !deadly[node - 1] {
+                graph.connect(node - 1, node, NavMove::Walk);
+                graph.connect(node, node - 1, NavMove::Walk);
+            }
+        }
+
+        // Moves between the surfaces, only to the nodes on the surfaces within the range of the moves
+        let grid = SurfaceGrid::enabled(&level.surfaces);
+        let mut surface_nodes = vec![Vec::new(); level.surfaces.len()];
+        for (index, node) in graph.nodes.iter().enumerate() {
+            surface_nodes[node.surface].push(index);
+        }
+        let reach = vec2(
+            (limits.jump_distance + MAX_FALL_HEIGHT / 2.0).max(limits.climb_distance),
+            limits.jump_height.max(limits.climb_height),
+        );
+        for from in 0..graph.nodes.len() {
+            if deadly[from] {
+                continue;
+            }
+            let position = graph.nodes[from].position.map(Coord::as_f32);
+            let range =
+                Aabb2::from_corners(position - vec2(reach.x, MAX_FALL_HEIGHT), position + reach);
+            let candidates: Vec<usize> = grid
+                .query(range.map(Coord::new))
+                .into_iter()
+                .flat_map(|surface| surface_nodes[surface].iter().copied())
+                .collect();
+            for to in candidates {
+                if from == to || deadly[to] {
+                    continue;
+                }
+                if graph.edges[from].iter().any(|edge| edge.to == to) {
+                    continue;
+                }
+                let (a, b) = (graph.nodes[from], graph.nodes[to]);
+                let delta = (b.position - a.position).map(Coord::as_f32);
+                let kind = if delta.len() < JOIN_DISTANCE {
+                    NavMove::Walk
+                } else if delta.y <= limits.jump_height
+                    && delta.x.abs() <= limits.jump_distance + (-delta.y).max(0.0) / 2.0
+                    && -delta.y <= MAX_FALL_HEIGHT
+                {
+                    NavMove::Jump
+                } else if b.ledge
+                    && delta.y <= limits.climb_height
+                    && delta.x.abs() <= limits.climb_distance
+                {
+                    NavMove::Climb
+                } else {
+                    continue;
+                };
+                let height = delta.y.max(0.0) + 1.0;
+                if kind == NavMove::Walk || clear_path(level, &grid, a.position, b.position, height)
+                {
+                    graph.connect(from, to, kind);
+                }
+            }
+        }
+        graph
+    }
+
+    fn connect(&mut self, from: usize, to: usize, kind: NavMove) {
+        let distance = (self.nodes[to].position - self.nodes[from].position).len();
+        let cost = match kind {
+            NavMove::Walk => distance,
+            NavMove::Jump => distance * R32::new(AIR_DISTANCE_COST) + R32::new(JUMP_COST),
+            NavMove::Climb => distance * R32::new(AIR_DISTANCE_COST) + R32::new(CLIMB_COST),
+        };
+        self.edges[from].push(NavEdge { to, kind, cost });
+    }
+
+    /// Returns the node closest to the point, preferring the ones below it.
+    pub fn nearest(&self, position: vec2<Coord>) -> Option<usize> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, node)| {
+                let delta = node.position - position;
+                let above = delta.y.max(Coord::ZERO);
+                delta.len() + above * Coord::new(2.0)
+            })
+            .map(|(index, _)| index)
+    }
+
+    /// Returns whether each node can be reached from the given one.
+    pub fn reachable(&self, from: usize) -> Vec<bool> {
+        let mut reachable = vec![false; self.nodes.len()];
+        reachable[from] = true;
+        let mut stack = vec![from];
+        while let Some(node) = stack.pop() {
+            for edge in &self.edges[node] {
+                if !std::mem::replace(&mut reachable[edge.to], true) {
+                    stack.push(edge.to);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Finds the cheapest sequence of moves between the nodes,
+    /// with the extra costs of the moves that have failed before.
+    pub fn path(
+        &self,
+        from: usize,
+        to: usize,
+        penalties: &HashMap<(usize, usize), R32>,
+    ) -> Option<Vec<(usize, NavMove)>> {
+        if from == to {
+            return Some(Vec::new());
+        }
+        let costs = self.costs_from(from, penalties);
+        costs[to].1?;
+        let mut path = Vec::new();
+        let mut node = to;
+        while node != from {
+            let (_, Some((previous, kind))) = costs[node] else {
+                return None;
+            };
+            path.push((node, kind));
+            node = previous;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Dijkstra's algorithm, returns the cost of every node and the move that leads there.
+    #[allow(clippy::type_complexity)]
+    fn costs_from(
+        &self,
+        from: usize,
+        penalties: &HashMap<(usize, usize), R32>,
+    ) -> Vec<(R32, Option<(usize, NavMove)>)> {
+        let infinity = R32::new(f32::MAX);
+        let mut costs = vec![(infinity, None); self.nodes.len()];
+        let mut done = vec![false; self.nodes.len()];
+        costs[from].0 = R32::ZERO;
+        let mut queue = std::collections::BinaryHeap::new();
+        queue.push(std::cmp::Reverse((R32::ZERO, from)));
+        while let Some(std::cmp::Reverse((cost, node))) = queue.pop() {
+            if std::mem::replace(&mut done[node], true) {
+                continue;
+            }
+            for edge in &self.edges[node] {
+                let penalty = penalties
+                    .get(&(node, edge.to))
+                    .copied()
+                    .unwrap_or(R32::ZERO);
+                let next = cost + edge.cost + penalty;
+                if next < costs[edge.to].0 {
+                    costs[edge.to] = (next, Some((node, edge.kind)));
+                    queue.push(std::cmp::Reverse((next, edge.to)));
+                }
+            }
+        }
+        costs
+    }
+}
+
+fn is_walkable(surface: &Surface, angle: f32) -> bool {
+    let direction = (surface.p2 - surface.p1).map(Coord::as_f32);
+    direction.x != 0.0 && (direction.y / direction.x).atan().abs() < angle
+}
+
+/// Checks that the body can move between the feet positions over an arc of the given height,
+/// approximated by two straight lines through the top of the arc.
+fn clear_path(
+    level: &Level,
+    grid: &SurfaceGrid,
+    from: vec2<Coord>,
+    to: vec2<Coord>,
+    height: f32,
+) -> bool {
+    let lift = |position: vec2<Coord>| position.map(Coord::as_f32) + vec2(0.0, 1.0);
+    let (from, to) = (lift(from), lift(to));
+    let top = (from + to) / 2.0 + vec2(0.0, height);
+    // The body is thinner than its collision circle near the feet, so the lines go through
+    // its middle and only have to keep a little distance from the surfaces
+    let margin = 0.5;
+    [(from, top), (top, to)].into_iter().all(|(a, b)| {
+        let area = Aabb2::points_bounding_box([a, b])
+            .unwrap()
+            .extend_uniform(margin)
+            .map(Coord::new);
+        grid.surfaces_in(&level.surfaces, area)
+            .all(|(_, surface)| segment_distance(a, b, surface.segment_f32()) > margin)
+    })
+}
+
+fn segment_distance(a: vec2<f32>, b: vec2<f32>, Segment(c, d): Segment<f32>) -> f32 {
+    let cross = |o: vec2<f32>, p: vec2<f32>, q: vec2<f32>| vec2::skew(p - o, q - o);
+    let intersect = cross(a, b, c).signum() != cross(a, b, d).signum()
+        && cross(c, d, a).signum() != cross(c, d, b).signum();
+    if intersect {
+        return 0.0;
+    }
+    let point_distance = |p: vec2<f32>, Segment(s, e): Segment<f32>| {
+        let direction = e - s;
+        let t = if direction.len_sqr() > 0.0 {
+            (vec2::dot(p - s, direction) / direction.len_sqr()).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (s + direction * t - p).len()
+    };
+    point_distance(a, Segment(c, d))
+        .min(point_distance(b, Segment(c, d)))
+        .min(point_distance(c, Segment(a, b)))
+        .min(point_distance(d, Segment(a, b)))
+}
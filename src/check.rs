@@ -0,0 +1,251 @@
+use super::*;
+
+use crate::bot::{NavGraph, NavMove};
+
+/// What the body can get to from the spawn point, judging by the level surfaces and hazards.
+/// The doors, props, ropes and bars are not taken into account.
+pub struct LevelCheck {
+    pub graph: NavGraph,
+    /// Whether each node of the graph can be reached from the spawn point.
+    pub reachable: Vec<bool>,
+    /// Moves between the reachable surfaces.
+    pub links: Vec<SurfaceLink>,
+    pub checkpoints: Vec<bool>,
+    /// Whether the finish can be reached, `None` if the level has no finish.
+    pub finish: Option<bool>,
+}
+
+/// A move from one surface to another, an edge of the reachability graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SurfaceLink {
+    pub from: usize,
+    pub to: usize,
+    pub kind: NavMove,
+}
+
+impl LevelCheck {
+    pub fn new(level: &Level) -> Self {
+        let graph = NavGraph::new(level);
+        let reachable = match graph.nearest(level.spawn_point) {
+            Some(start) => graph.reachable(start),
+            None => vec![false; graph.nodes.len()],
+        };
+        let mut links: Vec<SurfaceLink> = Vec::new();
+        for (from, edges) in graph.edges.iter().enumerate() {
+            if !reachable[from] {
+                continue;
+            }
+            for edge in edges {
+                let link = SurfaceLink {
+                    from: graph.nodes[from].surface,
+                    to: graph.nodes[edge.to].surface,
+                    kind: edge.kind,
+                };
+                if link.from != link.to && !links.contains(&link) {
+                    links.push(link);
+                }
+            }
+        }
+        let mut check = Self {
+            graph,
+            reachable,
+            links,
+            checkpoints: Vec::new(),
+            finish: None,
+        };
+        check.checkpoints = level
+            .checkpoints
+            .iter()
+            .map(|checkpoint| {
+                check.touches(|column| {
+                    segment_point_distance(column, checkpoint.position.map(Coord::as_f32))
+                        < logic::CHECKPOINT_RADIUS
+                })
+            })
+            .collect();
+        check.finish = level.finish.map(|finish| {
+            let finish = finish.map(Coord::as_f32);
+            check.touches(|Segment(bottom, top)| {
+                finish.min.x <= bottom.x
+                    && bottom.x <= finish.max.x
+                    && finish.min.y <= top.y
+                    && bottom.y <= finish.max.y
+            })
+        });
+        check
+    }
+
+    /// Checks whether the body center passes the test somewhere it can get to,
+    /// given as the column from standing on a reachable node up to the top of a jump there.
+    fn touches(&self, test: impl Fn(Segment<f32>) -> bool) -> bool {
+        let lift = vec2(0.0, 1.0);
+        self.graph
+            .nodes
+            .iter()
+            .zip(&self.reachable)
+            .filter(|(_, &reachable)| reachable)
+            .any(|(node, _)| {
+                let bottom = node.position.map(Coord::as_f32) + lift;
+                test(Segment(
+                    bottom,
+                    bottom + vec2(0.0, self.graph.limits.jump_height),
+                ))
+            })
+    }
+
+    /// Describes what is worth a look but does not make the level unplayable.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.finish.is_none() {
+            warnings.push("The level has no finish".to_owned());
+        }
+        warnings
+    }
+
+    /// Describes the parts of the level that cannot be reached.
+    pub fn problems(&self, level: &Level) -> Vec<String> {
+        let mut problems = Vec::new();
+        match self.finish {
+            None => {}
+            Some(false) => {
+                let center = level.finish.unwrap().center().map(Coord::as_f32);
+                problems.push(format!(
+                    "The finish at ({:.1}, {:.1}) cannot be reached",
+                    center.x, center.y
+                ));
+            }
+            Some(true) => {}
+        }
+        for (index, (checkpoint, _)) in level
+            .checkpoints
+            .iter()
+            .zip(&self.checkpoints)
+            .enumerate()
+            .filter(|(_, (_, &reachable))| !reachable)
+        {
+            problems.push(format!(
+                "Checkpoint {index} at ({:.1}, {:.1}) cannot be reached",
+                checkpoint.position.x, checkpoint.position.y
+            ));
+        }
+        problems
+    }
+}
+
+fn segment_point_distance(Segment(start, end): Segment<f32>, point: vec2<f32>) -> f32 {
+    let direction = end - start;
+    let t = if direction.len_sqr() > 0.0 {
+        (vec2::dot(point - start, direction) / direction.len_sqr()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (start + direction * t - point).len()
+}
+
+/// Checks the level file and prints the report, returns whether everything can be reached.
+pub fn run(path: &std::path::Path) -> bool {
+    if !path.is_file() {
+        println!("There is no level at {}", path.display());
+        return false;
+    }
+    let Some(level) = Level::load_full(path) else {
+        println!("Failed to load the level {}", path.display());
+        return false;
+    };
+    let check = LevelCheck::new(&level);
+    let limits = check.graph.limits;
+    println!(
+        "Jumps up to {:.1} high and {:.1} far, climbs up to {:.1} high",
+        limits.jump_height, limits.jump_distance, limits.climb_height
+    );
+    println!(
+        "Swinging the arm up adds to the jumps, up to {:.1} high, which is not counted on",
+        limits.swing_jump_height
+    );
+    let surfaces: HashSet<usize> = check
+        .graph
+        .nodes
+        .iter()
+        .zip(&check.reachable)
+        .filter(|(_, &reachable)| reachable)
+        .map(|(node, _)| node.surface)
+        .collect();
+    let walkable: HashSet<usize> = check.graph.nodes.iter().map(|node| node.surface).collect();
+    println!(
+        "{} of {} walkable surfaces reachable, {} moves between them",
+        surfaces.len(),
+        walkable.len(),
+        check.links.len()
+    );
+    for link in &check.links {
+        println!("  surface {} -> {}: {:?}", link.from, link.to, link.kind);
+    }
+    for warning in check.warnings() {
+        println!("Warning: {warning}");
+    }
+    let problems = check.problems(&level);
+    for problem in &problems {
+        println!("{problem}");
+    }
+    if problems.is_empty() {
+        println!("Everything can be reached");
+    }
+    problems.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bot::MovementLimits;
+
+    fn fixture(name: &str) -> Level {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/levels")
+            .join(format!("{name}.json"));
+        Level::load_full(path).unwrap()
+    }
+
+    #[test]
+    fn reachable_level() {
+        let level = fixture("reachable");
+        let check = LevelCheck::new(&level);
+        assert_eq!(check.finish, Some(true));
+        assert_eq!(check.checkpoints, [true]);
+        assert!(check.problems(&level).is_empty());
+        assert!(check.warnings().is_empty());
+    }
+
+    #[test]
+    fn jump_limits() {
+        let limits = MovementLimits::new(&Body::new(vec2::ZERO));
+        // The arm can add to the jumps, but the check does not count on it
+        assert!(limits.swing_jump_height > limits.jump_height);
+        assert!(limits.climb_height > limits.jump_height);
+    }
+
+    #[test]
+    fn unreachable_finish_and_checkpoint() {
+        let level = fixture("unreachable");
+        let check = LevelCheck::new(&level);
+        assert_eq!(check.finish, Some(false));
+        assert_eq!(check.checkpoints, [true, false]);
+        assert_eq!(
+            check.problems(&level),
+            [
+                "The finish at (28.5, 11.5) cannot be reached",
+                "Checkpoint 1 at (25.0, 11.0) cannot be reached",
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_finish() {
+        let mut level = fixture("reachable");
+        level.finish = None;
+        let check = LevelCheck::new(&level);
+        assert_eq!(check.finish, None);
+        assert_eq!(check.warnings(), ["The level has no finish"]);
+        assert!(check.problems(&level).is_empty());
+    }
+}
@@ -1,57 +1,261 @@
 use super::*;
 
-use crate::physics::Surface;
+use crate::bot::NavMove;
+use crate::check::LevelCheck;
+use crate::physics::{Bar, PropShape, Surface, SurfaceGrid};
 
 const AUTOSAVE_PERIOD: f32 = 10.0;
 const SNAP_DISTANCE: f32 = 0.5;
 const HOVER_DISTANCE: f32 = 0.5;
+const FINISH_SIZE: vec2<f32> = vec2(1.0, 1.5);
+const KILL_ZONE_SIZE: vec2<f32> = vec2(1.0, 1.0);
+const CRUSHER_SIZE: vec2<f32> = vec2(1.5, 0.5);
+const CRUSHER_OFFSET: vec2<f32> = vec2(0.0, -4.0);
+const CRUSHER_PERIOD: f32 = 2.0;
+const BAR_WIDTH: f32 = 3.0;
+const ROPE_LENGTH: f32 = 4.0;
+const BOX_SIZE: vec2<f32> = vec2(0.6, 0.6);
+const BOX_MASS: f32 = 10.0;
+const BALL_RADIUS: f32 = 0.5;
+const BALL_MASS: f32 = 5.0;
+/// Height of the placed sprites, the width follows the texture proportions.
+const SPRITE_HEIGHT: f32 = 2.0;
+/// Width of one repetition of a new background layer.
+const BACKGROUND_WIDTH: f32 = 40.0;
+/// Parallax of the new background layers, cycling from the farthest to the nearest.
+const BACKGROUND_PARALLAX: [f32; 3] = [0.2, 0.4, 0.6];
+const POLYGON_COLOR: Rgba<f32> = Rgba {
+    r: 0.25,
+    g: 0.3,
+    b: 0.35,
+    a: 1.0,
+};
+/// Half size of the new trigger volumes.
+const TRIGGER_SIZE: vec2<f32> = vec2(1.0, 1.0);
+const TRIGGER_COLOR: Rgba<f32> = Rgba {
+    r: 1.0,
+    g: 0.8,
+    b: 0.0,
+    a: 0.15,
+};
+/// Disabled surfaces are not drawn by the game, so the editor shows their outline.
+const DISABLED_SURFACE_COLOR: Rgba<f32> = Rgba {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 0.4,
+};
+const LABEL_SIZE: f32 = 0.4;
+/// Half size of the new doors.
+const DOOR_SIZE: vec2<f32> = vec2(0.25, 1.5);
+/// New doors slide up by their height when opened.
+const DOOR_OFFSET: vec2<f32> = vec2(0.0, 3.0);
+const DOOR_SPEED: f32 = 3.0;
+const GATE_RADIUS: f32 = 0.3;
+const GATE_COLOR: Rgba<f32> = Rgba {
+    r: 0.3,
+    g: 0.3,
+    b: 0.6,
+    a: 1.0,
+};
+const WIRE_COLOR: Rgba<f32> = Rgba {
+    r: 0.9,
+    g: 0.6,
+    b: 0.1,
+    a: 0.8,
+};
+const DOOR_OPEN_COLOR: Rgba<f32> = Rgba {
+    r: 0.35,
+    g: 0.4,
+    b: 0.5,
+    a: 0.3,
+};
+const REACHABLE_COLOR: Rgba<f32> = Rgba {
+    r: 0.2,
+    g: 0.9,
+    b: 0.3,
+    a: 0.8,
+};
+const UNREACHABLE_COLOR: Rgba<f32> = Rgba {
+    r: 1.0,
+    g: 0.2,
+    b: 0.2,
+    a: 0.5,
+};
+const JUMP_MOVE_COLOR: Rgba<f32> = Rgba {
+    r: 0.2,
+    g: 0.6,
+    b: 1.0,
+    a: 0.4,
+};
+const CLIMB_MOVE_COLOR: Rgba<f32> = Rgba {
+    r: 0.8,
+    g: 0.4,
+    b: 1.0,
+    a: 0.6,
+};
+const EDITOR_TEXT_SIZE: f32 = 20.0;
 
 pub struct Editor {
     geng: Geng,
     assets: Rc<Assets>,
     render: Render,
+    controls: InputConfig,
     framebuffer_size: vec2<f32>,
     next_autosave: f32,
     cursor_pos: vec2<f32>,
     mouse_drag: Option<MouseDrag>,
     level: Level,
+    /// Spatial index over the level surfaces, kept in sync with the edits.
+    surface_grid: SurfaceGrid,
+    /// Streams the surfaces around the camera, `None` if the level stores them inline.
+    chunks: Option<ChunkStream>,
+    /// Index of the sprite placed by the decoration actions.
+    sprite: usize,
+    /// Whether new decorations go over the gameplay layer.
+    foreground: bool,
+    /// Vertices of the decoration polygon being placed.
+    polygon: Vec<vec2<Coord>>,
+    /// Script tag given to the surfaces, a new one is made up when it is not set.
+    tag: Option<String>,
+    /// Whether the level script parses, shown to the designer editing it.
+    script_status: String,
+    /// Object the wire being placed starts from.
+    wire_start: Option<WireNode>,
+    /// Reachability of the level as of the last explicit save, shown while it is set.
+    reachability: Option<LevelCheck>,
     play: bool,
 }
 
 struct MouseDrag {
     pub start_camera: vec2<f32>,
     pub start: vec2<Coord>,
-    pub button: geng::MouseButton,
+    pub input: Input,
 }
 
 impl Editor {
     pub fn new(geng: &Geng, assets: &Rc<Assets>) -> Self {
         geng.window().unlock_cursor();
+        let path = run_dir().join("assets").join("new_level.json");
+        let level = Level::load(&path).unwrap_or_default();
+        let chunks = level
+            .chunked
+            .then(|| ChunkStream::new(Level::chunks_dir(&path)));
         Self {
             geng: geng.clone(),
             assets: assets.clone(),
             render: Render::new(geng, assets),
+            controls: InputConfig::load(run_dir().join("assets").join("controls.json"))
+                .unwrap_or_default(),
             framebuffer_size: vec2(1.0, 1.0),
             next_autosave: 0.0,
             cursor_pos: vec2::ZERO,
             mouse_drag: None,
-            level: Level::load(run_dir().join("assets").join("new_level.json")).unwrap_or_default(),
+            surface_grid: SurfaceGrid::new(&level.surfaces),
+            level,
+            chunks,
+            sprite: 0,
+            foreground: false,
+            polygon: Vec::new(),
+            tag: None,
+            script_status: script_status(),
+            wire_start: None,
+            reachability: None,
             play: false,
         }
     }
 
     fn find_hovered_surface(&self, position: vec2<Coord>) -> Option<usize> {
+        let area = Aabb2::point(position).extend_uniform(Coord::new(HOVER_DISTANCE));
+        self.surface_grid
+            .surfaces_in(&self.level.surfaces, area)
+            .find(|(_, surface)| surface.delta_to(position).len().as_f32() <= HOVER_DISTANCE)
+            .map(|(index, _)| index)
+    }
+
+    /// Returns the decoration under the cursor, the topmost one first.
+    fn find_hovered_decoration(&self, position: vec2<Coord>) -> Option<usize> {
+        let order = |foreground| {
+            self.level
+                .decorations
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(move |(_, decoration)| decoration.foreground == foreground)
+        };
+        order(true)
+            .chain(order(false))
+            .find(|(_, decoration)| decoration.bounds().contains(position))
+            .map(|(index, _)| index)
+    }
+
+    /// Returns the sprite name and its proportions.
+    fn selected_sprite(&self) -> Option<(String, f32)> {
+        let sprites = &self.assets.sprites;
+        let name = sprites.names().get(self.sprite)?;
+        let size = sprites.get(name)?.size().map(|x| x as f32);
+        Some((name.clone(), size.x / size.y))
+    }
+
+    fn find_hovered_checkpoint(&self, position: vec2<Coord>) -> Option<usize> {
+        self.level.checkpoints.iter().position(|checkpoint| {
+            (checkpoint.position - position).len().as_f32() <= HOVER_DISTANCE
+        })
+    }
+
+    fn find_hovered_collectible(&self, position: vec2<Coord>) -> Option<usize> {
         self.level
-            .surfaces
+            .collectibles
             .iter()
-            .position(|surface| surface.delta_to(position).len().as_f32() <= HOVER_DISTANCE)
+            .position(|item| (item.position - position).len().as_f32() <= HOVER_DISTANCE)
     }
 
-    fn snap_position(&self, position: vec2<Coord>) -> vec2<Coord> {
+    fn find_hovered_bar(&self, position: vec2<Coord>) -> Option<usize> {
+        self.level.bars.iter().position(|bar| {
+            (bar.closest_point(position) - position).len().as_f32() <= HOVER_DISTANCE
+        })
+    }
+
+    fn find_hovered_rope(&self, position: vec2<Coord>) -> Option<usize> {
         self.level
-            .surfaces
+            .ropes
+            .iter()
+            .position(|rope| (rope.anchor - position).len().as_f32() <= HOVER_DISTANCE)
+    }
+
+    /// Returns the switch, the gate or the door under the cursor.
+    fn find_hovered_node(&self, position: vec2<Coord>) -> Option<WireNode> {
+        let near = |point: vec2<Coord>| (point - position).len().as_f32() <= HOVER_DISTANCE;
+        let level = &self.level;
+        if let Some(index) = level
+            .switches
             .iter()
-            .flat_map(|surface| [surface.p1, surface.p2])
+            .position(|switch| near(switch.position))
+        {
+            return Some(WireNode::Switch(index));
+        }
+        if let Some(index) = level.gates.iter().position(|gate| near(gate.position)) {
+            return Some(WireNode::Gate(index));
+        }
+        level
+            .doors
+            .iter()
+            .position(|door| door.area.contains(position))
+            .map(WireNode::Door)
+    }
+
+    fn cursor_world_pos(&self) -> vec2<Coord> {
+        self.render
+            .camera
+            .screen_to_world(self.framebuffer_size, self.cursor_pos)
+            .map(Coord::new)
+    }
+
+    fn snap_position(&self, position: vec2<Coord>) -> vec2<Coord> {
+        let area = Aabb2::point(position).extend_uniform(Coord::new(SNAP_DISTANCE));
+        self.surface_grid
+            .surfaces_in(&self.level.surfaces, area)
+            .flat_map(|(_, surface)| [surface.p1, surface.p2])
             .map(|p| (p, (p - position).len()))
             .filter(|(_, dist)| dist.as_f32() < SNAP_DISTANCE)
             .min_by_key(|(_, dist)| *dist)
@@ -59,9 +263,350 @@ impl Editor {
             .unwrap_or(position)
     }
 
-    pub fn save_level(&self) {
-        self.level
-            .save(run_dir().join("assets").join("new_level.json"));
+    /// Returns an unused name made of the prefix and a number.
+    fn new_name(prefix: &str, used: impl IntoIterator<Item = String>) -> String {
+        let used: HashSet<String> = used.into_iter().collect();
+        (1..)
+            .map(|number| format!("{prefix}{number}"))
+            .find(|name| !used.contains(name))
+            .unwrap()
+    }
+
+    pub fn save_level(&mut self) {
+        // The script is edited by hand next to the level, so it is reloaded to check it
+        self.script_status = script_status();
+        let path = run_dir().join("assets").join("new_level.json");
+        match &mut self.chunks {
+            Some(chunks) => {
+                chunks.save(&mut self.level.surfaces);
+                self.surface_grid = SurfaceGrid::new(&self.level.surfaces);
+                self.level.chunk_bounds = chunks.bounds();
+                let level = Level {
+                    surfaces: Vec::new(),
+                    ..self.level.clone()
+                };
+                level.save(path);
+            }
+            None => self.level.save(path),
+        }
+    }
+
+    /// Returns all the surfaces of the saved level, including the chunks that are not loaded.
+    fn saved_surfaces(&self) -> Vec<Surface> {
+        match &self.chunks {
+            Some(chunks) => chunks.read_all(),
+            None => self.level.surfaces.clone(),
+        }
+    }
+
+    /// Checks the whole level, including the chunks that are not loaded.
+    fn check_level(&self) -> LevelCheck {
+        LevelCheck::new(&Level {
+            surfaces: self.saved_surfaces(),
+            ..self.level.clone()
+        })
+    }
+
+    /// Moves the surfaces into the chunks next to the level, or back into the level file.
+    fn toggle_chunked(&mut self) {
+        let path = run_dir().join("assets").join("new_level.json");
+        match self.chunks.take() {
+            Some(mut chunks) => {
+                chunks.save(&mut self.level.surfaces);
+                self.level.surfaces = chunks.read_all();
+                chunks.clear();
+                self.level.chunked = false;
+                self.level.chunk_bounds = None;
+                log::info!("Level surfaces moved into the level file");
+            }
+            None => {
+                // Saving marks the chunks with the surfaces as loaded,
+                // the distant ones are unloaded on the next update
+                let mut chunks = ChunkStream::new(Level::chunks_dir(&path));
+                chunks.clear();
+                chunks.save(&mut self.level.surfaces);
+                self.chunks = Some(chunks);
+                self.level.chunked = true;
+                log::info!("Level surfaces moved into chunks");
+            }
+        }
+        self.save_level();
+    }
+
+    /// Adds the geometry from the SVG file next to the level.
+    fn import_svg(&mut self) {
+        let path = run_dir().join("assets").join("new_level.svg");
+        let geometry = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))
+            .and_then(|text| formats::import_svg(&text, &default()));
+        let geometry = match geometry {
+            Ok(geometry) => geometry,
+            Err(err) => {
+                log::error!("Failed to import the SVG: {err:#}");
+                return;
+            }
+        };
+        for surface in geometry.surfaces {
+            self.surface_grid
+                .insert(self.level.surfaces.len(), &surface);
+            self.level.surfaces.push(surface);
+        }
+        if let Some(spawn_point) = geometry.spawn_point {
+            self.level.spawn_point = spawn_point;
+        }
+        log::info!("SVG imported");
+    }
+
+    /// Replaces the level with the Tiled map next to it, either `.tmx` or `.tiled.json`.
+    fn import_tiled(&mut self) {
+        let tmx = run_dir().join("assets").join("new_level.tmx");
+        let path = if tmx.exists() {
+            tmx
+        } else {
+            run_dir().join("assets").join("new_level.tiled.json")
+        };
+        match formats::load_tiled(&path) {
+            Ok(level) => {
+                replace_level(
+                    &mut self.level,
+                    &mut self.surface_grid,
+                    &mut self.chunks,
+                    level,
+                );
+                self.save_level();
+                log::info!("Tiled map imported");
+            }
+            Err(err) => log::error!("Failed to import the Tiled map: {err:#}"),
+        }
+    }
+
+    /// Writes the whole level to the SVG file next to it.
+    fn export_svg(&mut self) {
+        self.save_level();
+        let level = Level {
+            surfaces: self.saved_surfaces(),
+            ..self.level.clone()
+        };
+        let path = run_dir().join("assets").join("new_level.svg");
+        match std::fs::write(&path, formats::export_svg(&level, &default())) {
+            Ok(()) => log::info!("SVG exported"),
+            Err(err) => log::error!("Failed to write {}: {err}", path.display()),
+        }
+    }
+
+    /// Draws what the level script refers to:
+    /// the trigger volumes, the tags and the disabled surfaces.
+    fn draw_script_overlay(&self, framebuffer: &mut ugli::Framebuffer) {
+        let draw2d = self.geng.draw2d();
+        let camera = &self.render.camera;
+        let label = |text: &str, position: vec2<f32>, framebuffer: &mut ugli::Framebuffer| {
+            self.geng.default_font().draw(
+                framebuffer,
+                camera,
+                text,
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(position) * mat3::scale_uniform(LABEL_SIZE),
+                Rgba::WHITE,
+            );
+        };
+        for volume in &self.level.triggers {
+            let area = volume.area.map(Coord::as_f32);
+            draw2d.draw2d(framebuffer, camera, &draw2d::Quad::new(area, TRIGGER_COLOR));
+            label(&volume.name, area.center(), framebuffer);
+        }
+        for surface in &self.level.surfaces {
+            if surface.flags.disabled {
+                draw2d.draw2d(
+                    framebuffer,
+                    camera,
+                    &draw2d::Segment::new(surface.segment_f32(), 0.1, DISABLED_SURFACE_COLOR),
+                );
+            }
+            if let Some(tag) = &surface.tag {
+                let middle = (surface.p1 + surface.p2).map(Coord::as_f32) / 2.0;
+                label(tag, middle, framebuffer);
+            }
+        }
+        // The other objects the scripts refer to by name
+        let level = &self.level;
+        let named = (level.checkpoints.iter())
+            .map(|checkpoint| (&checkpoint.name, checkpoint.position))
+            .chain(
+                level
+                    .collectibles
+                    .iter()
+                    .map(|item| (&item.name, item.position)),
+            )
+            .chain(level.ropes.iter().map(|rope| (&rope.name, rope.anchor)))
+            .chain(level.props.iter().map(|prop| (&prop.name, prop.position)));
+        for (name, position) in named {
+            let position = position.map(Coord::as_f32) + vec2(0.0, 1.0);
+            label(name, position, framebuffer);
+        }
+    }
+
+    /// Draws the doors and the switches along with the gates and the wires,
+    /// which are not visible in the game.
+    fn draw_wiring_overlay(&self, framebuffer: &mut ugli::Framebuffer) {
+        let draw2d = self.geng.draw2d();
+        let camera = &self.render.camera;
+        let level = &self.level;
+        for door in &level.doors {
+            let open = door.area_at(Coord::ONE).map(Coord::as_f32);
+            draw2d.draw2d(
+                framebuffer,
+                camera,
+                &draw2d::Quad::new(open, DOOR_OPEN_COLOR),
+            );
+        }
+        self.render
+            .draw_wiring(level, &WiringState::new(level), framebuffer);
+
+        let position = |node| level.node_position(node).map(|p| p.map(Coord::as_f32));
+        for wire in &level.wires {
+            if let (Some(from), Some(to)) = (position(wire.from), position(wire.to)) {
+                draw2d.draw2d(
+                    framebuffer,
+                    camera,
+                    &draw2d::Segment::new(Segment(from, to), 0.05, WIRE_COLOR),
+                );
+                // The arrow head shows the direction of the signal
+                let direction = (to - from).normalize_or_zero();
+                let tip = to - direction * GATE_RADIUS;
+                draw2d.draw2d(
+                    framebuffer,
+                    camera,
+                    &draw2d::Polygon::new(
+                        vec![
+                            tip,
+                            tip - direction * 0.3 + direction.rotate_90() * 0.15,
+                            tip - direction * 0.3 - direction.rotate_90() * 0.15,
+                        ],
+                        WIRE_COLOR,
+                    ),
+                );
+            }
+        }
+        if let Some(from) = self.wire_start.and_then(position) {
+            let cursor = camera.screen_to_world(self.framebuffer_size, self.cursor_pos);
+            draw2d.draw2d(
+                framebuffer,
+                camera,
+                &draw2d::Segment::new(Segment(from, cursor), 0.05, WIRE_COLOR),
+            );
+        }
+
+        for gate in &level.gates {
+            let center = gate.position.map(Coord::as_f32);
+            draw2d.draw2d(
+                framebuffer,
+                camera,
+                &draw2d::Ellipse::circle(center, GATE_RADIUS, GATE_COLOR),
+            );
+            let name = match gate.kind {
+                GateKind::And => "AND",
+                GateKind::Or => "OR",
+                GateKind::Not => "NOT",
+            };
+            self.geng.default_font().draw(
+                framebuffer,
+                camera,
+                name,
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(center) * mat3::scale_uniform(LABEL_SIZE * 0.5),
+                Rgba::WHITE,
+            );
+        }
+    }
+
+    /// Draws the places the body can stand on and the moves between them,
+    /// marking what cannot be reached from the spawn point.
+    fn draw_reachability_overlay(&self, framebuffer: &mut ugli::Framebuffer) {
+        let Some(check) = &self.reachability else {
+            return;
+        };
+        let draw2d = self.geng.draw2d();
+        let camera = &self.render.camera;
+        let graph = &check.graph;
+        for (from, edges) in graph.edges.iter().enumerate() {
+            if !check.reachable[from] {
+                continue;
+            }
+            for edge in edges {
+                let color = match edge.kind {
+                    NavMove::Walk => continue,
+                    NavMove::Jump => JUMP_MOVE_COLOR,
+                    NavMove::Climb => CLIMB_MOVE_COLOR,
+                };
+                let segment = Segment(
+                    graph.nodes[from].position.map(Coord::as_f32),
+                    graph.nodes[edge.to].position.map(Coord::as_f32),
+                );
+                draw2d.draw2d(
+                    framebuffer,
+                    camera,
+                    &draw2d::Segment::new(segment, 0.03, color),
+                );
+            }
+        }
+        for (node, &reachable) in graph.nodes.iter().zip(&check.reachable) {
+            let color = if reachable {
+                REACHABLE_COLOR
+            } else {
+                UNREACHABLE_COLOR
+            };
+            draw2d.draw2d(
+                framebuffer,
+                camera,
+                &draw2d::Ellipse::circle(node.position.map(Coord::as_f32), 0.15, color),
+            );
+        }
+        for (checkpoint, &reachable) in self.level.checkpoints.iter().zip(&check.checkpoints) {
+            if !reachable {
+                draw2d.draw2d(
+                    framebuffer,
+                    camera,
+                    &draw2d::Ellipse::circle(
+                        checkpoint.position.map(Coord::as_f32),
+                        logic::CHECKPOINT_RADIUS,
+                        UNREACHABLE_COLOR,
+                    ),
+                );
+            }
+        }
+        if let (Some(finish), Some(false)) = (self.level.finish, check.finish) {
+            draw2d.draw2d(
+                framebuffer,
+                camera,
+                &draw2d::Quad::new(finish.map(Coord::as_f32), UNREACHABLE_COLOR),
+            );
+        }
+    }
+}
+
+/// Replaces the whole level along with the index over its surfaces, removing the old chunks.
+fn replace_level(
+    level: &mut Level,
+    surface_grid: &mut SurfaceGrid,
+    chunks: &mut Option<ChunkStream>,
+    new: Level,
+) {
+    *surface_grid = SurfaceGrid::new(&new.surfaces);
+    *level = new;
+    if let Some(mut chunks) = chunks.take() {
+        chunks.clear();
+    }
+}
+
+/// Checks the script next to the level.
+fn script_status() -> String {
+    let path = run_dir().join("assets").join("new_level.rhai");
+    match std::fs::read_to_string(&path) {
+        Ok(source) => match Script::parse(&source) {
+            Ok(script) => format!("Script: {} handlers", script.handlers().count()),
+            Err(err) => format!("Script error: {err:#}"),
+        },
+        Err(_) => "Script: none".to_owned(),
     }
 }
 
@@ -73,44 +618,292 @@ impl geng::State for Editor {
             self.next_autosave = AUTOSAVE_PERIOD;
             self.save_level();
         }
+
+        if let Some(chunks) = &mut self.chunks {
+            let focus = self.render.camera.center.map(Coord::new);
+            if chunks.update(&mut self.level.surfaces, focus, true) {
+                self.surface_grid = SurfaceGrid::new(&self.level.surfaces);
+            }
+        }
     }
 
     fn handle_event(&mut self, event: geng::Event) {
         let window = self.geng.window();
-        match event {
-            geng::Event::KeyPress { key } => match key {
-                geng::Key::P => self.play = true,
-                geng::Key::S if window.is_key_pressed(geng::Key::ControlLeft) => {
-                    self.next_autosave = AUTOSAVE_PERIOD;
-                    self.save_level()
+        let triggered = |action| self.controls.is_triggered(window, &event, action);
+        if triggered(Action::EditorPlay) {
+            self.play = true;
+        } else if triggered(Action::EditorSave) {
+            self.next_autosave = AUTOSAVE_PERIOD;
+            self.save_level();
+            // Checking the level takes a while, so the autosaves leave the overlay as it is
+            if self.reachability.is_some() {
+                self.reachability = Some(self.check_level());
+            }
+        } else if triggered(Action::EditorImportSvg) {
+            self.import_svg();
+        } else if triggered(Action::EditorExportSvg) {
+            self.export_svg();
+        } else if triggered(Action::EditorImportTiled) {
+            self.import_tiled();
+        } else if triggered(Action::EditorChunked) {
+            self.toggle_chunked();
+        } else if triggered(Action::EditorClear) {
+            replace_level(
+                &mut self.level,
+                &mut self.surface_grid,
+                &mut self.chunks,
+                Level::new(),
+            );
+            self.save_level();
+        } else if triggered(Action::EditorNextSprite) {
+            let count = self.assets.sprites.names().len();
+            self.sprite = (self.sprite + 1) % count.max(1);
+        } else if triggered(Action::EditorForeground) {
+            self.foreground = !self.foreground;
+        } else if triggered(Action::EditorSprite) {
+            let world_pos = self.cursor_world_pos();
+            if let Some((sprite, aspect)) = self.selected_sprite() {
+                let size = vec2(SPRITE_HEIGHT * aspect, SPRITE_HEIGHT);
+                self.level.decorations.push(Decoration {
+                    shape: DecorationShape::Sprite {
+                        sprite,
+                        area: Aabb2::point(world_pos)
+                            .extend_symmetric(size.map(Coord::new) / r32(2.0)),
+                    },
+                    foreground: self.foreground,
+                });
+            }
+        } else if triggered(Action::EditorPolygon) {
+            let world_pos = self.cursor_world_pos();
+            let vertex = self.snap_position(world_pos);
+            let closed = self.polygon.len() >= 3
+                && (self.polygon[0] - vertex).len().as_f32() <= SNAP_DISTANCE;
+            if closed {
+                self.level.decorations.push(Decoration {
+                    shape: DecorationShape::Polygon {
+                        vertices: std::mem::take(&mut self.polygon),
+                        color: POLYGON_COLOR,
+                    },
+                    foreground: self.foreground,
+                });
+            } else {
+                self.polygon.push(vertex);
+            }
+        } else if triggered(Action::EditorBackground) {
+            let position = self.cursor_pos;
+            let world_pos = self
+                .render
+                .camera
+                .screen_to_world(self.framebuffer_size, position);
+            if let Some((sprite, aspect)) = self.selected_sprite() {
+                let parallax =
+                    BACKGROUND_PARALLAX[self.level.backgrounds.len() % BACKGROUND_PARALLAX.len()];
+                let size = vec2(BACKGROUND_WIDTH, BACKGROUND_WIDTH / aspect);
+                // Placed under the cursor for the current camera position
+                let bottom = world_pos - self.render.camera.center * (1.0 - parallax);
+                self.level.backgrounds.push(BackgroundLayer {
+                    sprite,
+                    parallax,
+                    area: Aabb2::point(bottom)
+                        .extend_symmetric(vec2(size.x / 2.0, 0.0))
+                        .extend_up(size.y),
+                });
+            }
+        } else if triggered(Action::EditorSetSpawn) {
+            let world_pos = self.cursor_world_pos();
+            self.level.spawn_point = world_pos;
+        } else if triggered(Action::EditorCheckpoint) {
+            let world_pos = self.cursor_world_pos();
+            let used = self.level.checkpoints.iter().map(|item| item.name.clone());
+            self.level.checkpoints.push(Checkpoint {
+                position: world_pos,
+                name: Self::new_name("checkpoint_", used),
+            });
+        } else if triggered(Action::EditorFinish) {
+            let world_pos = self.cursor_world_pos();
+            self.level.finish =
+                Some(Aabb2::point(world_pos).extend_symmetric(FINISH_SIZE.map(Coord::new)));
+        } else if triggered(Action::EditorSpikes) {
+            let world_pos = self.cursor_world_pos();
+            if let Some(index) = self.find_hovered_surface(world_pos) {
+                let flags = &mut self.level.surfaces[index].flags;
+                flags.spikes = !flags.spikes;
+            }
+        } else if triggered(Action::EditorSurfaceStyle) {
+            let world_pos = self.cursor_world_pos();
+            if let Some(index) = self.find_hovered_surface(world_pos) {
+                let mut styles: Vec<&String> = self.assets.surface_styles.keys().collect();
+                styles.sort();
+                // Cycles through the styles and back to the default look
+                let style = &mut self.level.surfaces[index].style;
+                let next = match style {
+                    Some(current) => styles
+                        .iter()
+                        .position(|&name| name == current)
+                        .and_then(|index| styles.get(index + 1)),
+                    None => styles.first(),
+                };
+                *style = next.map(|&name| name.clone());
+            }
+        } else if triggered(Action::EditorSurfaceTag) {
+            let world_pos = self.cursor_world_pos();
+            if let Some(index) = self.find_hovered_surface(world_pos) {
+                if self.level.surfaces[index].tag.is_some() {
+                    self.level.surfaces[index].tag = None;
+                } else {
+                    let tag = match &self.tag {
+                        Some(tag) => tag.clone(),
+                        None => {
+                            // Surfaces in the unloaded chunks might use the tags as well
+                            let unloaded = match &self.chunks {
+                                Some(chunks) => chunks.read_all(),
+                                None => Vec::new(),
+                            };
+                            let used = self
+                                .level
+                                .surfaces
+                                .iter()
+                                .chain(&unloaded)
+                                .filter_map(|surface| surface.tag.clone())
+                                .collect::<Vec<_>>();
+                            Self::new_name("surface_", used)
+                        }
+                    };
+                    self.tag = Some(tag.clone());
+                    self.level.surfaces[index].tag = Some(tag);
                 }
-                geng::Key::R => {
-                    if window.is_key_pressed(geng::Key::ControlLeft) {
-                        self.level = Level::new();
-                        self.save_level();
+            }
+        } else if triggered(Action::EditorNewTag) {
+            self.tag = None;
+        } else if triggered(Action::EditorSurfaceDisabled) {
+            let world_pos = self.cursor_world_pos();
+            if let Some(index) = self.find_hovered_surface(world_pos) {
+                let flags = &mut self.level.surfaces[index].flags;
+                flags.disabled = !flags.disabled;
+            }
+        } else if triggered(Action::EditorTrigger) {
+            let world_pos = self.cursor_world_pos();
+            let used = self.level.triggers.iter().map(|volume| volume.name.clone());
+            let name = Self::new_name("trigger_", used);
+            self.level.triggers.push(TriggerVolume {
+                name,
+                area: Aabb2::point(world_pos).extend_symmetric(TRIGGER_SIZE.map(Coord::new)),
+            });
+        } else if triggered(Action::EditorPlate) || triggered(Action::EditorLever) {
+            let world_pos = self.cursor_world_pos();
+            let kind = if triggered(Action::EditorLever) {
+                SwitchKind::Lever
+            } else {
+                SwitchKind::Plate
+            };
+            self.level.switches.push(Switch {
+                position: self.snap_position(world_pos),
+                kind,
+            });
+        } else if triggered(Action::EditorDoor) {
+            let world_pos = self.cursor_world_pos();
+            self.level.doors.push(Door {
+                area: Aabb2::point(world_pos).extend_symmetric(DOOR_SIZE.map(Coord::new)),
+                offset: DOOR_OFFSET.map(Coord::new),
+                speed: Coord::new(DOOR_SPEED),
+            });
+        } else if triggered(Action::EditorGate) {
+            let world_pos = self.cursor_world_pos();
+            if let Some(WireNode::Gate(index)) = self.find_hovered_node(world_pos) {
+                let gate = &mut self.level.gates[index];
+                gate.kind = gate.kind.next();
+            } else {
+                self.level.gates.push(Gate {
+                    position: world_pos,
+                    kind: GateKind::And,
+                });
+            }
+        } else if triggered(Action::EditorWire) {
+            let world_pos = self.cursor_world_pos();
+            let node = self.find_hovered_node(world_pos);
+            match (self.wire_start.take(), node) {
+                (None, Some(node)) if node.is_source() => self.wire_start = Some(node),
+                (Some(from), Some(to)) if from != to && to.is_sink() => {
+                    let wire = Wire { from, to };
+                    if let Some(index) = self.level.wires.iter().position(|&other| other == wire) {
+                        self.level.wires.remove(index);
                     } else {
-                        let position = self.cursor_pos;
-                        let world_pos = self
-                            .render
-                            .camera
-                            .screen_to_world(self.framebuffer_size, position)
-                            .map(Coord::new);
-                        self.level.spawn_point = world_pos;
+                        self.level.wires.push(wire);
                     }
                 }
                 _ => {}
-            },
+            }
+        } else if triggered(Action::EditorReachability) {
+            if self.reachability.take().is_none() {
+                self.save_level();
+                self.reachability = Some(self.check_level());
+            }
+        } else if triggered(Action::EditorKillZone) {
+            let world_pos = self.cursor_world_pos();
+            self.level.hazards.push(Hazard::KillZone(
+                Aabb2::point(world_pos).extend_symmetric(KILL_ZONE_SIZE.map(Coord::new)),
+            ));
+        } else if triggered(Action::EditorCrusher) {
+            let world_pos = self.cursor_world_pos();
+            self.level.hazards.push(Hazard::Crusher(Crusher {
+                area: Aabb2::point(world_pos).extend_symmetric(CRUSHER_SIZE.map(Coord::new)),
+                offset: CRUSHER_OFFSET.map(Coord::new),
+                period: Time::new(CRUSHER_PERIOD),
+            }));
+        } else if triggered(Action::EditorCollectible) || triggered(Action::EditorHandCollectible) {
+            let world_pos = self.cursor_world_pos();
+            let used = self.level.collectibles.iter().map(|item| item.name.clone());
+            self.level.collectibles.push(Collectible {
+                position: world_pos,
+                name: Self::new_name("collectible_", used),
+                hand_only: triggered(Action::EditorHandCollectible),
+            });
+        } else if triggered(Action::EditorBar) {
+            let world_pos = self.cursor_world_pos();
+            let offset = vec2(Coord::new(BAR_WIDTH / 2.0), Coord::ZERO);
+            self.level.bars.push(Bar {
+                p1: world_pos - offset,
+                p2: world_pos + offset,
+            });
+        } else if triggered(Action::EditorRope) {
+            let world_pos = self.cursor_world_pos();
+            let used = self.level.ropes.iter().map(|rope| rope.name.clone());
+            self.level.ropes.push(Rope {
+                anchor: world_pos,
+                length: Coord::new(ROPE_LENGTH),
+                name: Self::new_name("rope_", used),
+            });
+        } else if triggered(Action::EditorBox) {
+            let world_pos = self.cursor_world_pos();
+            let used = self.level.props.iter().map(|prop| prop.name.clone());
+            self.level.props.push(Prop {
+                position: world_pos,
+                name: Self::new_name("box_", used),
+                shape: PropShape::Box {
+                    half_size: BOX_SIZE.map(Coord::new),
+                },
+                mass: Mass::new(BOX_MASS),
+            });
+        } else if triggered(Action::EditorBall) {
+            let world_pos = self.cursor_world_pos();
+            let used = self.level.props.iter().map(|prop| prop.name.clone());
+            self.level.props.push(Prop {
+                position: world_pos,
+                name: Self::new_name("ball_", used),
+                shape: PropShape::Circle {
+                    radius: Coord::new(BALL_RADIUS),
+                },
+                mass: Mass::new(BALL_MASS),
+            });
+        }
+
+        match event {
             geng::Event::MousePress { button } => {
-                let position = self.cursor_pos;
-                let world_pos = self
-                    .render
-                    .camera
-                    .screen_to_world(self.framebuffer_size, position)
-                    .map(Coord::new);
+                let world_pos = self.cursor_world_pos();
                 self.mouse_drag = Some(MouseDrag {
                     start_camera: self.render.camera.center,
                     start: self.snap_position(world_pos),
-                    button,
+                    input: Input::Mouse(button),
                 });
             }
             geng::Event::CursorMove { position, .. } => {
@@ -121,41 +914,80 @@ impl geng::State for Editor {
                     .camera
                     .screen_to_world(self.framebuffer_size, position);
                 if let Some(drag) = &self.mouse_drag {
-                    if let geng::MouseButton::Right = drag.button {
+                    if self.controls.is_bound(Action::EditorPan, drag.input) {
                         self.render.camera.center =
                             drag.start_camera + drag.start.map(Coord::as_f32) - world_pos;
                     }
                 }
             }
             geng::Event::MouseRelease { button } => {
-                let position = self.cursor_pos;
-                let world_pos = self
-                    .render
-                    .camera
-                    .screen_to_world(self.framebuffer_size, position)
-                    .map(Coord::new);
-                match button {
-                    geng::MouseButton::Left => {
-                        if let Some(drag) = self.mouse_drag.take() {
-                            let p1 = drag.start;
-                            let p2 = self.snap_position(world_pos);
-                            if (p2 - p1).len().as_f32() > SNAP_DISTANCE {
-                                self.level.surfaces.push(Surface { p1, p2 });
-                            }
+                let world_pos = self.cursor_world_pos();
+                let input = Input::Mouse(button);
+                if let Some(drag) = self.mouse_drag.take() {
+                    if self.controls.is_bound(Action::EditorDraw, input) {
+                        let p1 = drag.start;
+                        let p2 = self.snap_position(world_pos);
+                        if (p2 - p1).len().as_f32() > SNAP_DISTANCE {
+                            let surface = Surface::new(p1, p2);
+                            self.surface_grid
+                                .insert(self.level.surfaces.len(), &surface);
+                            self.level.surfaces.push(surface);
                         }
-                    }
-                    geng::MouseButton::Right => {
-                        if let Some(drag) = self.mouse_drag.take() {
-                            if world_pos == drag.start {
-                                if let Some(index) = self.find_hovered_surface(world_pos) {
-                                    self.level.surfaces.remove(index);
-                                }
-                            }
+                    } else if self.controls.is_bound(Action::EditorPan, input)
+                        && world_pos == drag.start
+                    {
+                        if let Some(index) = self.find_hovered_checkpoint(world_pos) {
+                            self.level.checkpoints.remove(index);
+                        } else if let Some(index) = self.find_hovered_collectible(world_pos) {
+                            self.level.collectibles.remove(index);
+                        } else if let Some(index) = self
+                            .level
+                            .props
+                            .iter()
+                            .position(|prop| prop.body().aabb().contains(world_pos))
+                        {
+                            self.level.props.remove(index);
+                        } else if let Some(index) = self.find_hovered_rope(world_pos) {
+                            self.level.ropes.remove(index);
+                        } else if let Some(index) = self.find_hovered_bar(world_pos) {
+                            self.level.bars.remove(index);
+                        } else if let Some(node) = self.find_hovered_node(world_pos) {
+                            self.level.remove_node(node);
+                            self.wire_start = None;
+                        } else if let Some(index) = self
+                            .level
+                            .hazards
+                            .iter()
+                            .position(|hazard| hazard.area(Time::ZERO).contains(world_pos))
+                        {
+                            self.level.hazards.remove(index);
+                        } else if self
+                            .level
+                            .finish
+                            .is_some_and(|finish| finish.contains(world_pos))
+                        {
+                            self.level.finish = None;
+                        } else if let Some(index) = self
+                            .level
+                            .triggers
+                            .iter()
+                            .position(|volume| volume.area.contains(world_pos))
+                        {
+                            self.level.triggers.remove(index);
+                        } else if let Some(index) = self.find_hovered_surface(world_pos) {
+                            self.level.surfaces.remove(index);
+                            self.surface_grid = SurfaceGrid::new(&self.level.surfaces);
+                        } else if let Some(index) = self.find_hovered_decoration(world_pos) {
+                            self.level.decorations.remove(index);
+                        } else if let Some(index) =
+                            self.level.backgrounds.iter().rposition(|layer| {
+                                let area = layer.area_at(self.render.camera.center);
+                                (area.min.y..=area.max.y).contains(&world_pos.y.as_f32())
+                            })
+                        {
+                            self.level.backgrounds.remove(index);
                         }
                     }
-                    geng::MouseButton::Middle => {
-                        self.mouse_drag.take();
-                    }
                 }
             }
             _ => {}
@@ -165,7 +997,14 @@ impl geng::State for Editor {
     fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
         ugli::clear(framebuffer, Some(Rgba::BLACK), None, None);
         self.framebuffer_size = framebuffer.size().map(|x| x as f32);
-        self.render.draw_level(&self.level, framebuffer);
+        self.render.draw_background(&self.level, framebuffer);
+        self.render
+            .draw_level(&self.level, &self.surface_grid, Time::ZERO, framebuffer);
+        self.render.draw_collectibles(&self.level, &[], framebuffer);
+        self.render.draw_foreground(&self.level, framebuffer);
+        self.draw_script_overlay(framebuffer);
+        self.draw_wiring_overlay(framebuffer);
+        self.draw_reachability_overlay(framebuffer);
 
         let position = self.cursor_pos;
         let world_pos = self
@@ -191,8 +1030,22 @@ impl geng::State for Editor {
             &draw2d::Quad::new(Aabb2::point(snapped).extend_uniform(0.1), Rgba::RED),
         );
 
+        if !self.polygon.is_empty() {
+            let vertices = self
+                .polygon
+                .iter()
+                .map(|v| v.map(Coord::as_f32))
+                .chain([snapped])
+                .collect();
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.render.camera,
+                &draw2d::Chain::new(Chain { vertices }, 0.05, POLYGON_COLOR, 1),
+            );
+        }
+
         if let Some(drag) = &self.mouse_drag {
-            if let geng::MouseButton::Left = drag.button {
+            if self.controls.is_bound(Action::EditorDraw, drag.input) {
                 self.geng.draw2d().draw2d(
                     framebuffer,
                     &self.render.camera,
@@ -210,6 +1063,59 @@ impl geng::State for Editor {
             &self.render.camera,
             &draw2d::Ellipse::circle(self.level.spawn_point.map(Coord::as_f32), 1.0, Rgba::BLUE),
         );
+
+        for zone in &self.level.camera_zones {
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.render.camera,
+                &draw2d::Quad::new(zone.area.map(Coord::as_f32), Rgba::new(0.0, 0.5, 1.0, 0.1)),
+            );
+        }
+
+        let sprite = self
+            .assets
+            .sprites
+            .names()
+            .get(self.sprite)
+            .map_or("none", |name| name.as_str());
+        let layer = if self.foreground {
+            "foreground"
+        } else {
+            "background"
+        };
+        let tag = self.tag.as_deref().unwrap_or("new");
+        let storage = if self.level.chunked {
+            "in chunks"
+        } else {
+            "in the level file"
+        };
+        let mut lines = vec![
+            format!("Sprite: {sprite}, {layer}"),
+            format!("Tag: {tag}"),
+            format!("Surfaces: {storage}"),
+            self.script_status.clone(),
+        ];
+        if let Some(check) = &self.reachability {
+            let problems = check.problems(&self.level);
+            if problems.is_empty() {
+                lines.push("Reachability: everything can be reached".to_owned());
+            }
+            lines.extend(check.warnings());
+            lines.extend(problems);
+        }
+        for (row, line) in lines.iter().enumerate() {
+            self.geng.default_font().draw(
+                framebuffer,
+                &geng::PixelPerfectCamera,
+                line,
+                vec2::splat(geng::TextAlign::LEFT),
+                mat3::translate(vec2(
+                    EDITOR_TEXT_SIZE,
+                    EDITOR_TEXT_SIZE * (1.0 + row as f32),
+                )) * mat3::scale_uniform(EDITOR_TEXT_SIZE),
+                Rgba::WHITE,
+            );
+        }
     }
 
     fn transition(&mut self) -> Option<geng::state::Transition> {
@@ -225,3 +1131,36 @@ impl Drop for Editor {
         self.save_level();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replacing_the_level_reindexes_the_surfaces() {
+        let mut level = Level::new();
+        level.surfaces = vec![Surface::new(
+            vec2(-30.0, 20.0).map(r32),
+            vec2(-25.0, 20.0).map(r32),
+        )];
+        let mut surface_grid = SurfaceGrid::new(&level.surfaces);
+        let dir = std::env::temp_dir().join(format!("parkour-editor-{}", std::process::id()));
+        let mut chunks = ChunkStream::new(&dir);
+        chunks.save(&mut level.surfaces.clone());
+        let mut chunks = Some(chunks);
+
+        let mut imported = Level::new();
+        imported.surfaces = vec![
+            Surface::new(vec2(50.0, 10.0).map(r32), vec2(60.0, 10.0).map(r32)),
+            Surface::new(vec2(1.0, -1.0).map(r32), vec2(1.0, 1.0).map(r32)),
+        ];
+        replace_level(&mut level, &mut surface_grid, &mut chunks, imported);
+
+        let area = |x: f32, y: f32| Aabb2::point(vec2(x, y)).extend_uniform(1.0).map(r32);
+        assert_eq!(surface_grid.query(area(55.0, 10.0)), [0]);
+        assert_eq!(surface_grid.query(area(1.0, 0.0)), [1]);
+        assert!(surface_grid.query(area(-28.0, 20.0)).is_empty());
+        assert!(chunks.is_none());
+        assert!(!dir.exists());
+    }
+}
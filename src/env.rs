@@ -0,0 +1,226 @@
+use super::*;
+
+pub const STEP_TIME: f32 = 1.0 / 60.0;
+/// Episodes are cut off after this many steps.
+const MAX_STEPS: usize = 60 * 120;
+/// Furthest horizontal distance the seed moves the spawn point by.
+const SPAWN_JITTER: f32 = 0.5;
+
+const FINISH_REWARD: f32 = 100.0;
+const CHECKPOINT_REWARD: f32 = 10.0;
+const DEATH_PENALTY: f32 = 20.0;
+/// Penalty for every step, so that faster runs are rewarded.
+const STEP_PENALTY: f32 = 0.01;
+
+/// Values describing the body: position, velocity, whether it is on the ground,
+/// the ground normal and whether the hand is holding onto something.
+const BODY_VALUES: usize = 8;
+/// Values describing the arm: the elbow angle and angular velocity, the hand angle and
+/// angular velocity, and the hand position relative to the body.
+const ARM_VALUES: usize = 6;
+/// Offset from the body to the center of the finish.
+const GOAL_VALUES: usize = 2;
+/// Values describing a surface: whether it is present, its end points relative to the body
+/// and whether it kills.
+const SURFACE_VALUES: usize = 6;
+/// Number of the closest surfaces included in the observation.
+const NEARBY_SURFACES: usize = 8;
+/// Surfaces further than this from the body are not observed.
+const OBSERVATION_RADIUS: f32 = 10.0;
+
+/// Length of the observations, which are laid out as the body, the arm, the goal and then the
+/// nearby surfaces from the closest one, with the absent surfaces filled with zeros.
+pub const OBSERVATION_SIZE: usize =
+    BODY_VALUES + ARM_VALUES + GOAL_VALUES + SURFACE_VALUES * NEARBY_SURFACES;
+
+pub type Observation = Vec<f32>;
+
+/// The simulation of a single run through a level, stepped by an external controller.
+pub struct Env {
+    pub model: Model,
+    /// The level the episodes start from.
+    level: Level,
+    steps: usize,
+    /// Distance to the finish after the last step.
+    distance: f32,
+    /// Seed of the next episode when the environment is reset by the batch.
+    seed: u64,
+}
+
+pub struct BatchEnv {
+    pub envs: Vec<Env>,
+}
+
+impl Env {
+    pub fn new(level: &Level, seed: u64) -> Self {
+        let mut env = Self {
+            model: Model::new(level.clone()),
+            level: level.clone(),
+            steps: 0,
+            distance: 0.0,
+            seed,
+        };
+        env.reset(level, seed);
+        env
+    }
+
+    /// Starts a new episode in the level, the seed shifts the spawn point a little.
+    pub fn reset(&mut self, level: &Level, seed: u64) -> Observation {
+        self.level = level.clone();
+        let mut level = level.clone();
+        let offset = (Lcg(seed).next() * 2.0 - 1.0) * SPAWN_JITTER;
+        level.spawn_point.x += Coord::new(offset);
+        self.model = Model::new(level);
+        self.steps = 0;
+        self.seed = seed.wrapping_add(1);
+        self.distance = self.goal_distance();
+        self.observe()
+    }
+
+    /// Advances the simulation by one step, returns the observation after it,
+    /// the reward for the step and whether the episode is over.
+    pub fn step(&mut self, mut control: BodyControl) -> (Observation, f32, bool) {
+        let events = self.model.update(&mut control, Time::new(STEP_TIME));
+        self.steps += 1;
+
+        // Getting closer to the finish is rewarded along the way
+        let distance = self.goal_distance();
+        let mut reward = self.distance - distance - STEP_PENALTY;
+        self.distance = distance;
+        let mut done = self.steps >= MAX_STEPS;
+        for event in &events {
+            match event {
+                Event::Finished { .. } => {
+                    reward += FINISH_REWARD;
+                    done = true;
+                }
+                Event::Died { .. } => {
+                    reward -= DEATH_PENALTY;
+                    done = true;
+                }
+                Event::CheckpointReached { .. } => reward += CHECKPOINT_REWARD,
+                _ => {}
+            }
+        }
+        (self.observe(), reward, done)
+    }
+
+    /// Distance from the body to the finish, or the distance travelled right without one.
+    fn goal_distance(&self) -> f32 {
+        let position = self.model.player.center.position;
+        match self.model.level.finish {
+            Some(finish) => (finish.center() - position).len().as_f32(),
+            None => -position.x.as_f32(),
+        }
+    }
+
+    /// Describes the current state in the layout given by [`OBSERVATION_SIZE`].
+    pub fn observe(&self) -> Observation {
+        let model = &self.model;
+        let body = &model.player;
+        let center = body.center.position;
+        let mut values = Vec::with_capacity(OBSERVATION_SIZE);
+
+        let normal = body.ground_normal.unwrap_or(vec2::ZERO);
+        values.extend([
+            center.x,
+            center.y,
+            body.center.velocity.x,
+            body.center.velocity.y,
+            flag(body.ground_normal.is_some()),
+            normal.x,
+            normal.y,
+            flag(body.holding_to.is_some()),
+        ]);
+
+        let [(elbow, elbow_velocity), (hand, hand_velocity)] = body.arm.joints();
+        let [_, _, hand_point] = body.arm.get_skeleton(&body.center);
+        let hand_offset = hand_point.position - center;
+        values.extend([
+            elbow.as_radians(),
+            elbow_velocity,
+            hand.as_radians(),
+            hand_velocity,
+            hand_offset.x,
+            hand_offset.y,
+        ]);
+
+        let goal = model
+            .level
+            .finish
+            .map_or(vec2::ZERO, |finish| finish.center() - center);
+        values.extend([goal.x, goal.y]);
+
+        let radius = Coord::new(OBSERVATION_RADIUS);
+        let area = Aabb2::point(center).extend_uniform(radius);
+        let mut surfaces: Vec<(Coord, &Surface)> = model
+            .surface_grid
+            .surfaces_in(&model.level.surfaces, area)
+            .map(|(_, surface)| (surface.delta_to(center).len(), surface))
+            .filter(|&(distance, _)| distance < radius)
+            .collect();
+        surfaces.sort_by_key(|&(distance, _)| distance);
+        for index in 0..NEARBY_SURFACES {
+            match surfaces.get(index) {
+                Some((_, surface)) => {
+                    let (p1, p2) = (surface.p1 - center, surface.p2 - center);
+                    let spikes = flag(surface.flags.spikes);
+                    values.extend([Coord::ONE, p1.x, p1.y, p2.x, p2.y, spikes]);
+                }
+                None => values.extend([Coord::ZERO; SURFACE_VALUES]),
+            }
+        }
+
+        debug_assert_eq!(values.len(), OBSERVATION_SIZE);
+        values.into_iter().map(Coord::as_f32).collect()
+    }
+}
+
+impl BatchEnv {
+    pub fn new(level: &Level, count: usize, seed: u64) -> Self {
+        let mut batch = Self {
+            envs: (0..count).map(|_| Env::new(level, seed)).collect(),
+        };
+        batch.reset(level, seed);
+        batch
+    }
+
+    /// Starts new episodes in all the environments, each with its own seed.
+    pub fn reset(&mut self, level: &Level, seed: u64) -> Vec<Observation> {
+        self.envs
+            .iter_mut()
+            .enumerate()
+            .map(|(index, env)| env.reset(level, seed.wrapping_add((index as u64) << 32)))
+            .collect()
+    }
+
+    /// Steps every environment with its control. The finished episodes start over,
+    /// in which case the observation is the first one of the new episode.
+    pub fn step(&mut self, controls: &[BodyControl]) -> Vec<(Observation, f32, bool)> {
+        assert_eq!(
+            controls.len(),
+            self.envs.len(),
+            "one control per environment"
+        );
+        self.envs
+            .iter_mut()
+            .zip(controls)
+            .map(|(env, &control)| {
+                let (mut observation, reward, done) = env.step(control);
+                if done {
+                    let level = env.level.clone();
+                    observation = env.reset(&level, env.seed);
+                }
+                (observation, reward, done)
+            })
+            .collect()
+    }
+}
+
+fn flag(value: bool) -> Coord {
+    if value {
+        Coord::ONE
+    } else {
+        Coord::ZERO
+    }
+}
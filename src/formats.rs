@@ -0,0 +1,74 @@
+use super::*;
+
+mod svg;
+mod tiled;
+
+pub use svg::*;
+pub use tiled::*;
+
+use quick_xml::events::BytesStart;
+
+/// Imports the SVG file into the level, adding to the existing surfaces.
+pub fn import_svg_file(path: &std::path::Path, options: &SvgOptions) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let geometry = import_svg(&text, options)?;
+    log::info!("Imported {} surfaces", geometry.surfaces.len());
+
+    let level_path = run_dir().join("assets").join("new_level.json");
+    let mut level = Level::load_full(&level_path).unwrap_or_default();
+    level.surfaces.extend(geometry.surfaces);
+    if let Some(spawn_point) = geometry.spawn_point {
+        level.spawn_point = spawn_point;
+    }
+    level.save_full(level_path);
+    Ok(())
+}
+
+/// Exports the whole level, including the surfaces stored in chunks, to the SVG file.
+pub fn export_svg_file(path: &std::path::Path, options: &SvgOptions) -> anyhow::Result<()> {
+    let level =
+        Level::load_full(run_dir().join("assets").join("new_level.json")).unwrap_or_default();
+    std::fs::write(path, export_svg(&level, options))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    log::info!("Exported {} surfaces", level.surfaces.len());
+    Ok(())
+}
+
+/// Replaces the level with the one imported from the Tiled map.
+pub fn import_tiled_file(path: &std::path::Path) -> anyhow::Result<()> {
+    let level = load_tiled(path)?;
+    level.save_full(run_dir().join("assets").join("new_level.json"));
+    Ok(())
+}
+
+/// Reads the Tiled map in the TMX format, or in the JSON format if the extension is not `tmx`.
+pub fn load_tiled(path: &std::path::Path) -> anyhow::Result<Level> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let level = if path.extension().is_some_and(|ext| ext == "tmx") {
+        import_tmx(&text)?
+    } else {
+        import_tiled_json(&text)?
+    };
+    log::info!(
+        "Imported {} surfaces from the Tiled map",
+        level.surfaces.len()
+    );
+    Ok(level)
+}
+
+/// Returns the unescaped value of the XML attribute.
+fn attribute(element: &BytesStart, name: &str) -> anyhow::Result<Option<String>> {
+    for attribute in element.attributes() {
+        let attribute = attribute?;
+        if attribute.key.local_name().as_ref() == name.as_bytes() {
+            return Ok(Some(
+                attribute
+                    .normalized_value(quick_xml::XmlVersion::Implicit1_0)?
+                    .into_owned(),
+            ));
+        }
+    }
+    Ok(None)
+}
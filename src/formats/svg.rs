@@ -0,0 +1,986 @@
+use super::*;
+
+use quick_xml::events::{BytesStart, Event};
+
+/// Default number of world units per SVG unit.
+const SVG_SCALE: f32 = 0.05;
+/// Default maximum distance in world units between a curve and its flattened segments.
+const SVG_TOLERANCE: f32 = 0.05;
+/// Upper limit on the number of segments a single curve is flattened into.
+const MAX_CURVE_SEGMENTS: usize = 1000;
+/// Radius of the spawn point marker in the exported SVG, in world units.
+const SPAWN_MARKER_RADIUS: f32 = 0.5;
+
+/// Elements whose contents are not drawn directly.
+const HIDDEN_ELEMENTS: [&[u8]; 7] = [
+    b"defs",
+    b"clipPath",
+    b"mask",
+    b"marker",
+    b"pattern",
+    b"symbol",
+    b"metadata",
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct SvgOptions {
+    /// Number of world units per SVG unit.
+    pub scale: f32,
+    /// Maximum distance in world units between a curve and its flattened segments.
+    pub tolerance: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SvgGeometry {
+    pub surfaces: Vec<Surface>,
+    /// Position of the circle with the id `spawn`, if there is one.
+    pub spawn_point: Option<Position>,
+}
+
+/// 2d affine transformation in the SVG notation:
+/// `x' = a * x + c * y + e`, `y' = b * x + d * y + f`.
+#[derive(Debug, Clone, Copy)]
+struct Affine {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+    f: f32,
+}
+
+/// Collects the polylines in world coordinates.
+struct Builder {
+    options: SvgOptions,
+    transform: Affine,
+    spikes: bool,
+    geometry: SvgGeometry,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            scale: SVG_SCALE,
+            tolerance: SVG_TOLERANCE,
+        }
+    }
+}
+
+/// Converts the lines, polylines, polygons, rectangles, circles, ellipses and paths
+/// of the SVG document into surfaces. Curves are flattened with the tolerance from the options,
+/// and the y axis is flipped, so that it points up like in the game.
+/// Lines with the `spikes` class become spiky surfaces.
+pub fn import_svg(text: &str, options: &SvgOptions) -> anyhow::Result<SvgGeometry> {
+    let mut reader = quick_xml::Reader::from_str(text);
+    let mut builder = Builder {
+        options: *options,
+        transform: Affine::IDENTITY,
+        spikes: false,
+        geometry: default(),
+    };
+    // The transform and whether the contents are hidden for every open element
+    let mut stack: Vec<(Affine, bool)> = vec![(Affine::IDENTITY, false)];
+    loop {
+        let event = reader.read_event().context("Failed to parse the SVG")?;
+        let empty = matches!(event, Event::Empty(_));
+        match event {
+            Event::Start(element) | Event::Empty(element) => {
+                let &(parent, hidden) = stack.last().unwrap();
+                let transform = match attribute(&element, "transform")? {
+                    Some(value) => parent.then(&parse_transform(&value)?),
+                    None => parent,
+                };
+                let hidden = hidden || HIDDEN_ELEMENTS.contains(&element.local_name().as_ref());
+                if !hidden {
+                    builder.transform = transform;
+                    builder.spikes = attribute(&element, "class")?
+                        .is_some_and(|class| class.split_whitespace().any(|c| c == "spikes"));
+                    builder.element(&element).with_context(|| {
+                        format!(
+                            "Failed to import the <{}> element",
+                            String::from_utf8_lossy(element.local_name().as_ref())
+                        )
+                    })?;
+                }
+                if !empty {
+                    stack.push((transform, hidden));
+                }
+            }
+            Event::End(_) if stack.len() > 1 => {
+                stack.pop();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(builder.geometry)
+}
+
+/// Writes the surfaces and the spawn point of the level as an SVG document.
+/// Uses the same mapping as [import_svg], so the result can be imported back.
+pub fn export_svg(level: &Level, options: &SvgOptions) -> String {
+    let to_svg = |p: Position| vec2(p.x.as_f32(), -p.y.as_f32()) / options.scale;
+    let bounds = level.bounds().map(Coord::as_f32).extend_uniform(1.0);
+    let min = to_svg(vec2(bounds.min.x, bounds.max.y).map(Coord::new));
+    let size = bounds.size() / options.scale;
+    let stroke = 0.1 / options.scale;
+
+    let mut svg = String::new();
+    svg += &format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+        min.x, min.y, size.x, size.y
+    );
+    svg += &format!("  <g fill=\"none\" stroke=\"black\" stroke-width=\"{stroke}\">\n");
+    for surface in &level.surfaces {
+        let (p1, p2) = (to_svg(surface.p1), to_svg(surface.p2));
+        let class = if surface.flags.spikes {
+            " class=\"spikes\" stroke=\"red\""
+        } else {
+            ""
+        };
+        svg += &format!(
+            "    <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"{class}/>\n",
+            p1.x, p1.y, p2.x, p2.y
+        );
+    }
+    svg += "  </g>\n";
+    let spawn = to_svg(level.spawn_point);
+    svg += &format!(
+        "  <circle id=\"spawn\" cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"blue\"/>\n",
+        spawn.x,
+        spawn.y,
+        SPAWN_MARKER_RADIUS / options.scale
+    );
+    svg += "</svg>\n";
+    svg
+}
+
+impl Builder {
+    fn element(&mut self, element: &BytesStart) -> anyhow::Result<()> {
+        let number = |name: &str| -> anyhow::Result<f32> {
+            match attribute(element, name)? {
+                Some(value) => parse_length(&value),
+                None => Ok(0.0),
+            }
+        };
+        match element.local_name().as_ref() {
+            b"line" => {
+                let p1 = vec2(number("x1")?, number("y1")?);
+                let p2 = vec2(number("x2")?, number("y2")?);
+                self.polyline(&[p1, p2])?;
+            }
+            b"polyline" | b"polygon" => {
+                let values = parse_numbers(&attribute(element, "points")?.unwrap_or_default())?;
+                let mut points: Vec<vec2<f32>> =
+                    values.chunks_exact(2).map(|p| vec2(p[0], p[1])).collect();
+                if element.local_name().as_ref() == b"polygon" {
+                    if let Some(&first) = points.first() {
+                        points.push(first);
+                    }
+                }
+                self.polyline(&points)?;
+            }
+            b"rect" => {
+                let (x, y) = (number("x")?, number("y")?);
+                let (width, height) = (number("width")?, number("height")?);
+                self.polyline(&[
+                    vec2(x, y),
+                    vec2(x + width, y),
+                    vec2(x + width, y + height),
+                    vec2(x, y + height),
+                    vec2(x, y),
+                ])?;
+            }
+            b"circle" | b"ellipse" => {
+                let center = vec2(number("cx")?, number("cy")?);
+                if attribute(element, "id")?.as_deref() == Some("spawn") {
+                    self.geometry.spawn_point = Some(self.to_world(center)?);
+                    return Ok(());
+                }
+                let radius = if element.local_name().as_ref() == b"circle" {
+                    vec2::splat(number("r")?)
+                } else {
+                    vec2(number("rx")?, number("ry")?)
+                };
+                let mut points = vec![center + vec2(radius.x, 0.0)];
+                self.arc(&mut points, center, radius, 0.0, 0.0, f32::PI * 2.0);
+                self.polyline(&points)?;
+            }
+            b"path" => {
+                let data = attribute(element, "d")?.unwrap_or_default();
+                for points in self.path(&data)? {
+                    self.polyline(&points)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Maps a point from the element coordinates to the world coordinates.
+    fn to_world(&self, point: vec2<f32>) -> anyhow::Result<Position> {
+        let point = self.transform.apply(point) * self.options.scale;
+        anyhow::ensure!(
+            point.x.is_finite() && point.y.is_finite(),
+            "The point is too far away"
+        );
+        Ok(vec2(point.x, -point.y).map(Coord::new))
+    }
+
+    /// Adds the segments of the polyline given in the element coordinates.
+    fn polyline(&mut self, points: &[vec2<f32>]) -> anyhow::Result<()> {
+        for pair in points.windows(2) {
+            let (p1, p2) = (self.to_world(pair[0])?, self.to_world(pair[1])?);
+            if p1 != p2 {
+                let mut surface = Surface::new(p1, p2);
+                surface.flags.spikes = self.spikes;
+                self.geometry.surfaces.push(surface);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of segments needed to flatten a curve with the given bound
+    /// on its second derivative in element coordinates.
+    fn segments_for(&self, second_derivative: f32) -> usize {
+        let world = second_derivative * self.transform.max_scale() * self.options.scale;
+        let segments = (world / (8.0 * self.options.tolerance)).sqrt().ceil();
+        (segments as usize).clamp(1, MAX_CURVE_SEGMENTS)
+    }
+
+    fn cubic(&self, points: &mut Vec<vec2<f32>>, p: [vec2<f32>; 4]) {
+        let bound = 6.0
+            * (p[0] - p[1] * 2.0 + p[2])
+                .len()
+                .max((p[1] - p[2] * 2.0 + p[3]).len());
+        let segments = self.segments_for(bound);
+        for i in 1..=segments {
+            let t = i as f32 / segments as f32;
+            let s = 1.0 - t;
+            points.push(
+                p[0] * (s * s * s)
+                    + p[1] * (3.0 * s * s * t)
+                    + p[2] * (3.0 * s * t * t)
+                    + p[3] * (t * t * t),
+            );
+        }
+    }
+
+    fn quadratic(&self, points: &mut Vec<vec2<f32>>, p: [vec2<f32>; 3]) {
+        let segments = self.segments_for(2.0 * (p[0] - p[1] * 2.0 + p[2]).len());
+        for i in 1..=segments {
+            let t = i as f32 / segments as f32;
+            let s = 1.0 - t;
+            points.push(p[0] * (s * s) + p[1] * (2.0 * s * t) + p[2] * (t * t));
+        }
+    }
+
+    /// Appends the points of an elliptical arc, not including the starting point.
+    fn arc(
+        &self,
+        points: &mut Vec<vec2<f32>>,
+        center: vec2<f32>,
+        radius: vec2<f32>,
+        rotation: f32,
+        start_angle: f32,
+        sweep: f32,
+    ) {
+        let world_radius = radius.x.max(radius.y) * self.transform.max_scale() * self.options.scale;
+        let step = if self.options.tolerance < world_radius {
+            2.0 * (1.0 - self.options.tolerance / world_radius).acos()
+        } else {
+            f32::PI / 2.0
+        };
+        let segments = ((sweep.abs() / step).ceil() as usize).clamp(1, MAX_CURVE_SEGMENTS);
+        let (sin, cos) = rotation.sin_cos();
+        for i in 1..=segments {
+            let angle = start_angle + sweep * i as f32 / segments as f32;
+            let local = vec2(radius.x * angle.cos(), radius.y * angle.sin());
+            points
+                .push(center + vec2(cos * local.x - sin * local.y, sin * local.x + cos * local.y));
+        }
+    }
+
+    /// Converts the path data into polylines in element coordinates.
+    fn path(&self, data: &str) -> anyhow::Result<Vec<Vec<vec2<f32>>>> {
+        let mut tokens = PathTokens::new(data);
+        let mut paths = Vec::new();
+        let mut points: Vec<vec2<f32>> = Vec::new();
+        let mut current = vec2::ZERO;
+        let mut subpath_start = vec2::ZERO;
+        // The last control point of the previous curve, used to reflect for `S` and `T`
+        let mut last_control: Option<(u8, vec2<f32>)> = None;
+        let mut command = None;
+
+        while let Some(next) = tokens.command_or_repeat(command)? {
+            command = Some(next);
+            let relative = next.is_ascii_lowercase();
+            let origin = if relative { current } else { vec2::ZERO };
+            let point = |tokens: &mut PathTokens| -> anyhow::Result<vec2<f32>> {
+                Ok(origin + vec2(tokens.number()?, tokens.number()?))
+            };
+            let mut control = None;
+            match next.to_ascii_uppercase() {
+                b'M' => {
+                    if points.len() > 1 {
+                        paths.push(std::mem::take(&mut points));
+                    }
+                    current = point(&mut tokens)?;
+                    subpath_start = current;
+                    points = vec![current];
+                    // The following coordinate pairs are implicit line commands
+                    command = Some(if relative { b'l' } else { b'L' });
+                }
+                b'L' => {
+                    current = point(&mut tokens)?;
+                    points.push(current);
+                }
+                b'H' => {
+                    let x = tokens.number()?;
+                    current.x = if relative { current.x + x } else { x };
+                    points.push(current);
+                }
+                b'V' => {
+                    let y = tokens.number()?;
+                    current.y = if relative { current.y + y } else { y };
+                    points.push(current);
+                }
+                b'C' | b'S' => {
+                    let c1 = if next.eq_ignore_ascii_case(&b'C') {
+                        point(&mut tokens)?
+                    } else {
+                        match last_control {
+                            Some((b'C', c)) => current * 2.0 - c,
+                            _ => current,
+                        }
+                    };
+                    let c2 = point(&mut tokens)?;
+                    let end = point(&mut tokens)?;
+                    self.cubic(&mut points, [current, c1, c2, end]);
+                    control = Some((b'C', c2));
+                    current = end;
+                }
+                b'Q' | b'T' => {
+                    let c = if next.eq_ignore_ascii_case(&b'Q') {
+                        point(&mut tokens)?
+                    } else {
+                        match last_control {
+                            Some((b'Q', c)) => current * 2.0 - c,
+                            _ => current,
+                        }
+                    };
+                    let end = point(&mut tokens)?;
+                    self.quadratic(&mut points, [current, c, end]);
+                    control = Some((b'Q', c));
+                    current = end;
+                }
+                b'A' => {
+                    let radius = vec2(tokens.number()?.abs(), tokens.number()?.abs());
+                    let rotation = tokens.number()?.to_radians();
+                    let large_arc = tokens.flag()?;
+                    let sweep = tokens.flag()?;
+                    let end = point(&mut tokens)?;
+                    self.endpoint_arc(
+                        &mut points,
+                        current,
+                        end,
+                        radius,
+                        rotation,
+                        large_arc,
+                        sweep,
+                    );
+                    current = end;
+                }
+                b'Z' => {
+                    if current != subpath_start {
+                        points.push(subpath_start);
+                    }
+                    current = subpath_start;
+                    if points.len() > 1 {
+                        paths.push(std::mem::take(&mut points));
+                    }
+                    points = vec![current];
+                    command = None;
+                }
+                _ => anyhow::bail!("Unknown path command {:?}", next as char),
+            }
+            if points.is_empty() {
+                points.push(current);
+            }
+            last_control = control;
+        }
+        if points.len() > 1 {
+            paths.push(points);
+        }
+        Ok(paths)
+    }
+
+    /// Appends an arc given in the SVG endpoint parameterization.
+    #[allow(clippy::too_many_arguments)]
+    fn endpoint_arc(
+        &self,
+        points: &mut Vec<vec2<f32>>,
+        start: vec2<f32>,
+        end: vec2<f32>,
+        mut radius: vec2<f32>,
+        rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+    ) {
+        if start == end {
+            return;
+        }
+        if radius.x == 0.0 || radius.y == 0.0 {
+            points.push(end);
+            return;
+        }
+
+        // Conversion to the center parameterization from the SVG specification
+        let (sin, cos) = rotation.sin_cos();
+        let half = (start - end) / 2.0;
+        let p = vec2(cos * half.x + sin * half.y, -sin * half.x + cos * half.y);
+        let lambda = (p.x / radius.x).sqr() + (p.y / radius.y).sqr();
+        if lambda > 1.0 {
+            radius *= lambda.sqrt();
+        }
+        let (rx2, ry2) = (radius.x.sqr(), radius.y.sqr());
+        let numerator = rx2 * ry2 - rx2 * p.y.sqr() - ry2 * p.x.sqr();
+        let denominator = rx2 * p.y.sqr() + ry2 * p.x.sqr();
+        let sign = if large_arc == sweep { -1.0 } else { 1.0 };
+        let coef = sign * (numerator / denominator).max(0.0).sqrt();
+        let center_p = vec2(
+            coef * radius.x * p.y / radius.y,
+            -coef * radius.y * p.x / radius.x,
+        );
+        let center = vec2(
+            cos * center_p.x - sin * center_p.y,
+            sin * center_p.x + cos * center_p.y,
+        ) + (start + end) / 2.0;
+
+        let angle = |v: vec2<f32>| v.y.atan2(v.x);
+        let u = vec2((p.x - center_p.x) / radius.x, (p.y - center_p.y) / radius.y);
+        let v = vec2(
+            (-p.x - center_p.x) / radius.x,
+            (-p.y - center_p.y) / radius.y,
+        );
+        let start_angle = angle(u);
+        let mut delta = angle(v) - start_angle;
+        if sweep && delta < 0.0 {
+            delta += f32::PI * 2.0;
+        } else if !sweep && delta > 0.0 {
+            delta -= f32::PI * 2.0;
+        }
+        self.arc(points, center, radius, rotation, start_angle, delta);
+        // Avoid the accumulated error at the end point
+        if let Some(last) = points.last_mut() {
+            *last = end;
+        }
+    }
+}
+
+impl Affine {
+    const IDENTITY: Self = Self {
+        a: 1.0,
+        b: 0.0,
+        c: 0.0,
+        d: 1.0,
+        e: 0.0,
+        f: 0.0,
+    };
+
+    fn apply(&self, p: vec2<f32>) -> vec2<f32> {
+        vec2(
+            self.a * p.x + self.c * p.y + self.e,
+            self.b * p.x + self.d * p.y + self.f,
+        )
+    }
+
+    /// Returns the transformation that applies `other` first and then `self`.
+    fn then(&self, other: &Self) -> Self {
+        Self {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            e: self.a * other.e + self.c * other.f + self.e,
+            f: self.b * other.e + self.d * other.f + self.f,
+        }
+    }
+
+    /// Returns an upper bound on how much the transformation stretches lengths.
+    fn max_scale(&self) -> f32 {
+        vec2(self.a, self.b).len().max(vec2(self.c, self.d).len())
+    }
+}
+
+/// Splits the path data into commands and numbers.
+struct PathTokens<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PathTokens<'a> {
+    fn new(data: &'a str) -> Self {
+        Self {
+            data: data.as_bytes(),
+            position: 0,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while self
+            .data
+            .get(self.position)
+            .is_some_and(|c| c.is_ascii_whitespace() || *c == b',')
+        {
+            self.position += 1;
+        }
+    }
+
+    /// Returns the next command, or repeats the previous one if the next token is a number.
+    fn command_or_repeat(&mut self, previous: Option<u8>) -> anyhow::Result<Option<u8>> {
+        self.skip_separators();
+        match self.data.get(self.position) {
+            None => Ok(None),
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.position += 1;
+                Ok(Some(*c))
+            }
+            Some(_) => match previous {
+                Some(command) => Ok(Some(command)),
+                None => anyhow::bail!("Path data has to start with a command"),
+            },
+        }
+    }
+
+    fn number(&mut self) -> anyhow::Result<f32> {
+        self.skip_separators();
+        let start = self.position;
+        let mut end = start;
+        let bytes = self.data;
+        if matches!(bytes.get(end), Some(b'+' | b'-')) {
+            end += 1;
+        }
+        let mut seen_dot = false;
+        while let Some(&c) = bytes.get(end) {
+            if c.is_ascii_digit() {
+                end += 1;
+            } else if c == b'.' && !seen_dot {
+                seen_dot = true;
+                end += 1;
+            } else if (c == b'e' || c == b'E')
+                && bytes
+                    .get(end + 1)
+                    .is_some_and(|n| n.is_ascii_digit() || *n == b'-' || *n == b'+')
+            {
+                end += 2;
+                while bytes.get(end).is_some_and(u8::is_ascii_digit) {
+                    end += 1;
+                }
+                break;
+            } else {
+                break;
+            }
+        }
+        let text = std::str::from_utf8(&bytes[start..end])?;
+        let value: f32 = text
+            .parse()
+            .with_context(|| format!("Expected a number at {start} in the path data"))?;
+        anyhow::ensure!(value.is_finite(), "The number {text} is out of range");
+        self.position = end;
+        Ok(value)
+    }
+
+    /// Arc flags are single digits that do not need separators.
+    fn flag(&mut self) -> anyhow::Result<bool> {
+        self.skip_separators();
+        let flag = match self.data.get(self.position) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => anyhow::bail!("Expected an arc flag at {} in the path data", self.position),
+        };
+        self.position += 1;
+        Ok(flag)
+    }
+}
+
+/// Parses a length ignoring the `px` unit.
+fn parse_length(value: &str) -> anyhow::Result<f32> {
+    let value = value.trim().trim_end_matches("px");
+    let length: f32 = value
+        .parse()
+        .with_context(|| format!("Invalid length {value:?}"))?;
+    anyhow::ensure!(length.is_finite(), "Invalid length {value:?}");
+    Ok(length)
+}
+
+fn parse_numbers(value: &str) -> anyhow::Result<Vec<f32>> {
+    let mut tokens = PathTokens::new(value);
+    let mut numbers = Vec::new();
+    loop {
+        tokens.skip_separators();
+        if tokens.position >= tokens.data.len() {
+            return Ok(numbers);
+        }
+        numbers.push(tokens.number()?);
+    }
+}
+
+/// Parses a list of transform functions like `translate(10 20) rotate(45)`.
+fn parse_transform(value: &str) -> anyhow::Result<Affine> {
+    let mut result = Affine::IDENTITY;
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let open = rest
+            .find('(')
+            .with_context(|| format!("Invalid transform {value:?}"))?;
+        let close = rest
+            .find(')')
+            .with_context(|| format!("Invalid transform {value:?}"))?;
+        let name = rest[..open].trim().trim_start_matches(',').trim();
+        let args = parse_numbers(&rest[open + 1..close])?;
+        let arg = |i: usize| args.get(i).copied();
+        let transform = match (name, args.len()) {
+            ("matrix", 6) => Affine {
+                a: args[0],
+                b: args[1],
+                c: args[2],
+                d: args[3],
+                e: args[4],
+                f: args[5],
+            },
+            ("translate", 1 | 2) => Affine {
+                e: args[0],
+                f: arg(1).unwrap_or(0.0),
+                ..Affine::IDENTITY
+            },
+            ("scale", 1 | 2) => Affine {
+                a: args[0],
+                d: arg(1).unwrap_or(args[0]),
+                ..Affine::IDENTITY
+            },
+            ("rotate", 1 | 3) => {
+                let (sin, cos) = args[0].to_radians().sin_cos();
+                let rotation = Affine {
+                    a: cos,
+                    b: sin,
+                    c: -sin,
+                    d: cos,
+                    ..Affine::IDENTITY
+                };
+                let (cx, cy) = (arg(1).unwrap_or(0.0), arg(2).unwrap_or(0.0));
+                let to_center = Affine {
+                    e: cx,
+                    f: cy,
+                    ..Affine::IDENTITY
+                };
+                let from_center = Affine {
+                    e: -cx,
+                    f: -cy,
+                    ..Affine::IDENTITY
+                };
+                to_center.then(&rotation).then(&from_center)
+            }
+            ("skewX", 1) => Affine {
+                c: args[0].to_radians().tan(),
+                ..Affine::IDENTITY
+            },
+            ("skewY", 1) => Affine {
+                b: args[0].to_radians().tan(),
+                ..Affine::IDENTITY
+            },
+            _ => anyhow::bail!("Unsupported transform {name}({args:?})"),
+        };
+        result = result.then(&transform);
+        rest = rest[close + 1..].trim();
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Maps one SVG unit to one world unit.
+    const UNIT: SvgOptions = SvgOptions {
+        scale: 1.0,
+        tolerance: 0.1,
+    };
+
+    fn builder(options: SvgOptions) -> Builder {
+        Builder {
+            options,
+            transform: Affine::IDENTITY,
+            spikes: false,
+            geometry: default(),
+        }
+    }
+
+    fn close(a: vec2<f32>, b: vec2<f32>) -> bool {
+        (a - b).len() < 1e-3
+    }
+
+    fn assert_points(actual: &[vec2<f32>], expected: &[(f32, f32)]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?}");
+        for (&a, &(x, y)) in actual.iter().zip(expected) {
+            assert!(close(a, vec2(x, y)), "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn relative_commands() {
+        let paths = builder(UNIT)
+            .path("m 10 10 20 0 l 0 5 h -5 v 5 H 0 V 0 z")
+            .unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_points(
+            &paths[0],
+            &[
+                (10.0, 10.0),
+                (30.0, 10.0),
+                (30.0, 15.0),
+                (25.0, 15.0),
+                (25.0, 20.0),
+                (0.0, 20.0),
+                (0.0, 0.0),
+                (10.0, 10.0),
+            ],
+        );
+
+        // The next subpath starts relative to where the closed one started
+        let paths = builder(UNIT).path("M 1 1 h 2 z m 1 1 h 2").unwrap();
+        assert_eq!(paths.len(), 2);
+        assert_points(&paths[1], &[(2.0, 2.0), (4.0, 2.0)]);
+
+        // Numbers need no separators where the signs and dots tell them apart
+        let paths = builder(UNIT).path("M0-1.5.5.5l-1e1,2").unwrap();
+        assert_points(&paths[0], &[(0.0, -1.5), (0.5, 0.5), (-9.5, 2.5)]);
+    }
+
+    #[test]
+    fn smooth_curves_reflect_the_control_point() {
+        let builder = builder(UNIT);
+        let explicit = builder
+            .path("M 0 0 C 0 10 10 10 10 0 C 10 -10 20 -10 20 0")
+            .unwrap();
+        let smooth = builder
+            .path("M 0 0 C 0 10 10 10 10 0 s 10 -10 10 0")
+            .unwrap();
+        assert_eq!(explicit[0].len(), smooth[0].len());
+        for (&a, &b) in explicit[0].iter().zip(&smooth[0]) {
+            assert!(close(a, b));
+        }
+    }
+
+    #[test]
+    fn arcs() {
+        let builder = builder(UNIT);
+        let on_circle = |points: &[vec2<f32>], center: vec2<f32>, radius: f32| {
+            points
+                .iter()
+                .all(|&p| ((p - center).len() - radius).abs() < 1e-3)
+        };
+
+        // Half of a circle, swept in the positive angle direction, which is up in the SVG
+        let paths = builder.path("M 0 0 A 10 10 0 0 1 20 0").unwrap();
+        let points = &paths[0];
+        assert!(on_circle(points, vec2(10.0, 0.0), 10.0));
+        assert!(points.iter().all(|p| p.y <= 1e-3));
+        assert!(close(*points.last().unwrap(), vec2(20.0, 0.0)));
+
+        // The other way around
+        let paths = builder.path("M 0 0 A 10 10 0 0 0 20 0").unwrap();
+        assert!(paths[0].iter().all(|p| p.y >= -1e-3));
+
+        // The large arc of the circle through both points
+        let paths = builder.path("M 0 0 a 10 10 0 1 1 10 10").unwrap();
+        let points = &paths[0];
+        assert!(on_circle(points, vec2(10.0, 0.0), 10.0));
+        assert!(points.iter().any(|p| p.x > 19.0));
+        assert!(close(*points.last().unwrap(), vec2(10.0, 10.0)));
+
+        // Too small radii are scaled up to reach the end point
+        let paths = builder.path("M 0 0 A 1 1 0 0 1 20 0").unwrap();
+        assert!(on_circle(&paths[0], vec2(10.0, 0.0), 10.0));
+
+        // Zero radius is a straight line
+        let paths = builder.path("M 0 0 A 0 5 0 0 1 20 0").unwrap();
+        assert_points(&paths[0], &[(0.0, 0.0), (20.0, 0.0)]);
+    }
+
+    #[test]
+    fn invalid_paths() {
+        let builder = builder(UNIT);
+        assert!(builder.path("10 10").is_err());
+        assert!(builder.path("M 0 0 L 10").is_err());
+        assert!(builder.path("M 0 0 A 1 1 0 2 0 5 5").is_err());
+        assert!(builder.path("M 0 0 X 1 1").is_err());
+    }
+
+    #[test]
+    fn transforms() {
+        let apply = |transform: &str, point: (f32, f32)| {
+            parse_transform(transform)
+                .unwrap()
+                .apply(vec2(point.0, point.1))
+        };
+        assert!(close(
+            apply("translate(10 20)", (1.0, 1.0)),
+            vec2(11.0, 21.0)
+        ));
+        assert!(close(apply("translate(10)", (1.0, 1.0)), vec2(11.0, 1.0)));
+        assert!(close(apply("scale(2, 3)", (1.0, 1.0)), vec2(2.0, 3.0)));
+        assert!(close(apply("rotate(90)", (1.0, 0.0)), vec2(0.0, 1.0)));
+        assert!(close(
+            apply("rotate(180 5 5)", (0.0, 0.0)),
+            vec2(10.0, 10.0)
+        ));
+        assert!(close(apply("skewX(45)", (0.0, 1.0)), vec2(1.0, 1.0)));
+        assert!(close(
+            apply("matrix(1 0 0 1 5 6)", (1.0, 1.0)),
+            vec2(6.0, 7.0)
+        ));
+        // The rightmost transform is applied first
+        assert!(close(
+            apply("translate(10 0) scale(2)", (1.0, 1.0)),
+            vec2(12.0, 2.0)
+        ));
+        assert!(close(
+            apply("scale(2) translate(10 0)", (1.0, 1.0)),
+            vec2(22.0, 2.0)
+        ));
+        assert!(parse_transform("rotate(1 2)").is_err());
+        assert!(parse_transform("translate(1 2").is_err());
+    }
+
+    #[test]
+    fn nested_transforms_and_flipped_y() {
+        let svg = r#"<svg>
+            <g transform="translate(10 0)">
+                <line x1="0" y1="5" x2="10" y2="5" transform="scale(2)" class="floor spikes"/>
+            </g>
+            <defs><line x1="0" y1="0" x2="1" y2="1"/></defs>
+            <circle id="spawn" cx="3" cy="4" r="1"/>
+        </svg>"#;
+        let geometry = import_svg(svg, &UNIT).unwrap();
+        assert_eq!(geometry.surfaces.len(), 1);
+        let surface = &geometry.surfaces[0];
+        assert_eq!(surface.p1, vec2(10.0, -10.0).map(r32));
+        assert_eq!(surface.p2, vec2(30.0, -10.0).map(r32));
+        assert!(surface.flags.spikes);
+        assert_eq!(geometry.spawn_point, Some(vec2(3.0, -4.0).map(r32)));
+    }
+
+    #[test]
+    fn curve_segments_follow_the_tolerance() {
+        let count = |options: SvgOptions, curve: &dyn Fn(&Builder, &mut Vec<vec2<f32>>)| {
+            let mut points = Vec::new();
+            curve(&builder(options), &mut points);
+            points.len()
+        };
+        let quadratic = |builder: &Builder, points: &mut Vec<vec2<f32>>| {
+            builder.quadratic(
+                points,
+                [vec2(0.0, 0.0), vec2(50.0, 100.0), vec2(100.0, 0.0)],
+            )
+        };
+        let straight = |builder: &Builder, points: &mut Vec<vec2<f32>>| {
+            builder.cubic(
+                points,
+                [
+                    vec2(0.0, 0.0),
+                    vec2(1.0, 0.0),
+                    vec2(2.0, 0.0),
+                    vec2(3.0, 0.0),
+                ],
+            )
+        };
+        let circle = |builder: &Builder, points: &mut Vec<vec2<f32>>| {
+            builder.arc(
+                points,
+                vec2::ZERO,
+                vec2::splat(10.0),
+                0.0,
+                0.0,
+                f32::PI * 2.0,
+            )
+        };
+        let options = |scale: f32, tolerance: f32| SvgOptions { scale, tolerance };
+
+        // The second derivative is 400, so sqrt(400 / (8 * tolerance)) segments are needed
+        assert_eq!(count(options(1.0, 1.0), &quadratic), 8);
+        assert_eq!(count(options(1.0, 0.25), &quadratic), 15);
+        // Scaling the world down is the same as a bigger tolerance
+        assert_eq!(count(options(0.25, 1.0), &quadratic), 4);
+        assert_eq!(count(options(1.0, 1e-9), &quadratic), MAX_CURVE_SEGMENTS);
+        assert_eq!(count(options(1.0, 1.0), &straight), 1);
+        // Each segment covers the angle 2 * acos(1 - tolerance / radius)
+        assert_eq!(count(options(1.0, 1.0), &circle), 7);
+        assert_eq!(count(options(1.0, 100.0), &circle), 4);
+    }
+
+    #[test]
+    fn flattened_curves_stay_within_the_tolerance() {
+        let options = SvgOptions {
+            scale: 1.0,
+            tolerance: 0.05,
+        };
+        let mut points = vec![vec2(10.0, 0.0)];
+        builder(options).arc(
+            &mut points,
+            vec2::ZERO,
+            vec2::splat(10.0),
+            0.0,
+            0.0,
+            f32::PI * 2.0,
+        );
+        for pair in points.windows(2) {
+            let middle = (pair[0] + pair[1]) / 2.0;
+            assert!(10.0 - middle.len() <= options.tolerance + 1e-4);
+        }
+    }
+
+    #[test]
+    fn non_finite_numbers_are_errors() {
+        for svg in [
+            r#"<svg><line x1="nan" y1="0" x2="1" y2="1"/></svg>"#,
+            r#"<svg><rect x="0" y="0" width="inf" height="1"/></svg>"#,
+            r#"<svg><path d="M 0 0 L 1e999 0"/></svg>"#,
+            r#"<svg><polyline points="0,0 1e40,1"/></svg>"#,
+            r#"<svg><circle id="spawn" cx="-infinity" cy="0" r="1"/></svg>"#,
+            r#"<svg><line x1="1e30" y1="0" x2="1" y2="1" transform="scale(1e30)"/></svg>"#,
+        ] {
+            assert!(import_svg(svg, &default()).is_err(), "{svg}");
+        }
+    }
+
+    #[test]
+    fn export_import_round_trip() {
+        let mut level = Level::new();
+        level.spawn_point = vec2(1.5, 2.0).map(r32);
+        level.surfaces = vec![
+            Surface::new(vec2(-3.0, 0.0).map(r32), vec2(4.25, 0.5).map(r32)),
+            Surface::new(vec2(4.25, 0.5).map(r32), vec2(6.0, 3.0).map(r32)),
+            Surface::new(vec2(10.0, -2.0).map(r32), vec2(12.0, -2.0).map(r32)),
+        ];
+        level.surfaces[2].flags.spikes = true;
+
+        for options in [SvgOptions::default(), UNIT] {
+            let geometry = import_svg(&export_svg(&level, &options), &options).unwrap();
+            assert_eq!(geometry.surfaces.len(), level.surfaces.len());
+            for (imported, original) in geometry.surfaces.iter().zip(&level.surfaces) {
+                assert!(close(
+                    imported.p1.map(Coord::as_f32),
+                    original.p1.map(Coord::as_f32)
+                ));
+                assert!(close(
+                    imported.p2.map(Coord::as_f32),
+                    original.p2.map(Coord::as_f32)
+                ));
+                assert_eq!(imported.flags.spikes, original.flags.spikes);
+            }
+            let spawn = geometry.spawn_point.unwrap().map(Coord::as_f32);
+            assert!(close(spawn, level.spawn_point.map(Coord::as_f32)));
+        }
+    }
+}
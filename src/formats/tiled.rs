@@ -0,0 +1,556 @@
+use super::*;
+
+use quick_xml::events::Event;
+
+/// Half size in world units of the hazards placed as points.
+const POINT_HAZARD_SIZE: vec2<f32> = vec2(0.5, 0.5);
+/// Half size in world units of the finish placed as a point.
+const POINT_FINISH_SIZE: vec2<f32> = vec2(1.0, 1.5);
+/// Half size in world units of the trigger volumes placed as points.
+const POINT_TRIGGER_SIZE: vec2<f32> = vec2(1.0, 1.0);
+
+/// An object from an object layer of a Tiled map in pixels, with the layer offsets applied.
+#[derive(Debug, Clone)]
+struct TiledObject {
+    name: String,
+    class: String,
+    position: vec2<f32>,
+    size: vec2<f32>,
+    /// Clockwise rotation in degrees around the position.
+    rotation: f32,
+    point: bool,
+    ellipse: bool,
+    /// Points relative to the position, and whether the shape is closed.
+    shape: Option<(Vec<vec2<f32>>, bool)>,
+    properties: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct JsonMap {
+    #[serde(default)]
+    tileheight: f32,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+}
+
+#[derive(Deserialize)]
+struct JsonLayer {
+    #[serde(default)]
+    objects: Vec<JsonObject>,
+    /// Sublayers of a group layer.
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+}
+
+#[derive(Deserialize)]
+struct JsonObject {
+    #[serde(default)]
+    name: String,
+    /// Called `type` before Tiled 1.9.
+    #[serde(default, alias = "type")]
+    class: String,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default)]
+    point: bool,
+    #[serde(default)]
+    ellipse: bool,
+    polyline: Option<Vec<JsonPoint>>,
+    polygon: Option<Vec<JsonPoint>>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonPoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct JsonProperty {
+    name: String,
+    value: serde_json::Value,
+}
+
+/// Creates a level from a Tiled map in the TMX format.
+/// See [import_tiled_objects] for how the objects are converted.
+pub fn import_tmx(text: &str) -> anyhow::Result<Level> {
+    let mut reader = quick_xml::Reader::from_str(text);
+    let mut tile_height = 0.0;
+    let mut objects = Vec::new();
+    // Offsets of the open layers
+    let mut offsets = vec![vec2::ZERO];
+    let mut object: Option<TiledObject> = None;
+    loop {
+        let event = reader.read_event().context("Failed to parse the TMX")?;
+        let empty = matches!(event, Event::Empty(_));
+        match event {
+            Event::Start(element) | Event::Empty(element) => {
+                let number = |name| -> anyhow::Result<f32> {
+                    Ok(attribute(&element, name)?
+                        .map(|value| parse_number(&value))
+                        .transpose()
+                        .with_context(|| format!("Invalid {name}"))?
+                        .unwrap_or(0.0))
+                };
+                match element.local_name().as_ref() {
+                    b"map" => tile_height = number("tileheight")?,
+                    b"objectgroup" | b"group" if !empty => {
+                        let offset = vec2(number("offsetx")?, number("offsety")?);
+                        offsets.push(*offsets.last().unwrap() + offset);
+                    }
+                    b"object" => {
+                        let parsed = TiledObject {
+                            name: attribute(&element, "name")?.unwrap_or_default(),
+                            class: match attribute(&element, "class")? {
+                                Some(class) => class,
+                                None => attribute(&element, "type")?.unwrap_or_default(),
+                            },
+                            position: *offsets.last().unwrap() + vec2(number("x")?, number("y")?),
+                            size: vec2(number("width")?, number("height")?),
+                            rotation: number("rotation")?,
+                            point: false,
+                            ellipse: false,
+                            shape: None,
+                            properties: HashMap::new(),
+                        };
+                        if empty {
+                            objects.push(parsed);
+                        } else {
+                            object = Some(parsed);
+                        }
+                    }
+                    b"point" => {
+                        if let Some(object) = &mut object {
+                            object.point = true;
+                        }
+                    }
+                    b"ellipse" => {
+                        if let Some(object) = &mut object {
+                            object.ellipse = true;
+                        }
+                    }
+                    name @ (b"polyline" | b"polygon") => {
+                        if let Some(object) = &mut object {
+                            let points = attribute(&element, "points")?.unwrap_or_default();
+                            let points = points
+                                .split_whitespace()
+                                .map(|pair| -> anyhow::Result<vec2<f32>> {
+                                    let (x, y) = pair
+                                        .split_once(',')
+                                        .with_context(|| format!("Invalid point {pair:?}"))?;
+                                    Ok(vec2(parse_number(x)?, parse_number(y)?))
+                                })
+                                .collect::<anyhow::Result<_>>()?;
+                            object.shape = Some((points, name == b"polygon"));
+                        }
+                    }
+                    b"property" => {
+                        if let Some(object) = &mut object {
+                            if let Some(name) = attribute(&element, "name")? {
+                                let value = attribute(&element, "value")?.unwrap_or_default();
+                                object.properties.insert(name, value);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(element) => match element.local_name().as_ref() {
+                b"objectgroup" | b"group" if offsets.len() > 1 => {
+                    offsets.pop();
+                }
+                b"object" => objects.extend(object.take()),
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    import_tiled_objects(&objects, tile_height)
+}
+
+/// Creates a level from a Tiled map in the JSON format.
+/// See [import_tiled_objects] for how the objects are converted.
+pub fn import_tiled_json(text: &str) -> anyhow::Result<Level> {
+    let map: JsonMap = serde_json::from_str(text).context("Failed to parse the Tiled JSON")?;
+    let mut objects = Vec::new();
+    collect_json_objects(&map.layers, vec2::ZERO, &mut objects);
+    import_tiled_objects(&objects, map.tileheight)
+}
+
+fn collect_json_objects(layers: &[JsonLayer], offset: vec2<f32>, objects: &mut Vec<TiledObject>) {
+    for layer in layers {
+        let offset = offset + vec2(layer.offsetx, layer.offsety);
+        collect_json_objects(&layer.layers, offset, objects);
+        objects.extend(layer.objects.iter().map(|object| {
+            let points = |points: &[JsonPoint]| points.iter().map(|p| vec2(p.x, p.y)).collect();
+            TiledObject {
+                name: object.name.clone(),
+                class: object.class.clone(),
+                position: offset + vec2(object.x, object.y),
+                size: vec2(object.width, object.height),
+                rotation: object.rotation,
+                point: object.point,
+                ellipse: object.ellipse,
+                shape: match (&object.polyline, &object.polygon) {
+                    (Some(polyline), _) => Some((points(polyline), false)),
+                    (None, Some(polygon)) => Some((points(polygon), true)),
+                    (None, None) => None,
+                },
+                properties: object
+                    .properties
+                    .iter()
+                    .map(|property| {
+                        let value = match &property.value {
+                            serde_json::Value::String(value) => value.clone(),
+                            value => value.to_string(),
+                        };
+                        (property.name.clone(), value)
+                    })
+                    .collect(),
+            }
+        }));
+    }
+}
+
+/// Converts the objects into a level, one tile is one world unit.
+///
+/// The objects are recognized by their name, or by their class if the name is empty:
+/// - `spawn` sets the spawn point;
+/// - `checkpoint` adds a checkpoint;
+/// - `hazard` adds a kill zone covering the rectangle, or a crusher
+///   if the `period` property is set, moving by `offset_x` and `offset_y` world units;
+/// - `finish` sets the finish area;
+/// - `collectible` adds a collectible, picked up only by the hand if `hand_only` is set.
+///
+/// The `tag` property names the checkpoints and the collectibles for the scripts.
+///
+/// Other polylines, polygons and rectangles become surfaces,
+/// with the `spikes` property mapped to the surface flag and `style` naming the surface style.
+fn import_tiled_objects(objects: &[TiledObject], tile_height: f32) -> anyhow::Result<Level> {
+    anyhow::ensure!(tile_height > 0.0, "The map has no tile height");
+    let scale = 1.0 / tile_height;
+    let mut level = Level::new();
+    for object in objects {
+        let (sin, cos) = object.rotation.to_radians().sin_cos();
+        let to_world = |local: vec2<f32>| -> anyhow::Result<Position> {
+            let rotated = vec2(cos * local.x - sin * local.y, sin * local.x + cos * local.y);
+            let world = (object.position + rotated) * scale;
+            anyhow::ensure!(
+                world.x.is_finite() && world.y.is_finite(),
+                "The object {:?} is out of range",
+                object.name
+            );
+            Ok(vec2(world.x, -world.y).map(Coord::new))
+        };
+        let flag = |name: &str| object.properties.get(name).is_some_and(|v| v == "true");
+        let tag = || object.properties.get("tag").cloned().unwrap_or_default();
+        let number = |name: &str| -> anyhow::Result<Option<f32>> {
+            object
+                .properties
+                .get(name)
+                .map(|value| parse_number(value))
+                .transpose()
+                .with_context(|| format!("Invalid {name} property of {:?}", object.name))
+        };
+
+        let has_area = !object.point && object.size != vec2::ZERO;
+        let center = to_world(if has_area {
+            object.size / 2.0
+        } else {
+            vec2::ZERO
+        })?;
+        let area = |default_size: vec2<f32>| -> anyhow::Result<Aabb2<Coord>> {
+            Ok(if has_area {
+                let corners = [
+                    vec2(0.0, 0.0),
+                    vec2(object.size.x, 0.0),
+                    object.size,
+                    vec2(0.0, object.size.y),
+                ];
+                Aabb2::points_bounding_box(
+                    corners
+                        .into_iter()
+                        .map(to_world)
+                        .collect::<anyhow::Result<Vec<_>>>()?,
+                )
+                .unwrap()
+            } else {
+                Aabb2::point(center).extend_symmetric(default_size.map(Coord::new))
+            })
+        };
+
+        // Trigger volumes are named for the scripts, so they are recognized by the class
+        if object.class.eq_ignore_ascii_case("trigger") {
+            level.triggers.push(TriggerVolume {
+                name: object.name.clone(),
+                area: area(POINT_TRIGGER_SIZE)?,
+            });
+            continue;
+        }
+
+        let role = if object.name.is_empty() {
+            &object.class
+        } else {
+            &object.name
+        };
+        match role.to_lowercase().as_str() {
+            "spawn" => level.spawn_point = center,
+            "checkpoint" => level.checkpoints.push(Checkpoint {
+                position: center,
+                name: tag(),
+            }),
+            "finish" => level.finish = Some(area(POINT_FINISH_SIZE)?),
+            "collectible" => level.collectibles.push(Collectible {
+                position: center,
+                name: tag(),
+                hand_only: flag("hand_only"),
+            }),
+            "hazard" => {
+                let area = area(POINT_HAZARD_SIZE)?;
+                level.hazards.push(match number("period")? {
+                    Some(period) => Hazard::Crusher(Crusher {
+                        area,
+                        offset: vec2(
+                            number("offset_x")?.unwrap_or(0.0),
+                            number("offset_y")?.unwrap_or(0.0),
+                        )
+                        .map(Coord::new),
+                        period: Time::new(period),
+                    }),
+                    None => Hazard::KillZone(area),
+                });
+            }
+            _ => {
+                let (mut points, closed) = match &object.shape {
+                    Some(shape) => shape.clone(),
+                    None if has_area && !object.ellipse => (
+                        vec![
+                            vec2(0.0, 0.0),
+                            vec2(object.size.x, 0.0),
+                            object.size,
+                            vec2(0.0, object.size.y),
+                        ],
+                        true,
+                    ),
+                    None => {
+                        log::warn!("Skipping the Tiled object {:?}", object.name);
+                        continue;
+                    }
+                };
+                if closed {
+                    points.extend(points.first().copied());
+                }
+                let flags = SurfaceFlags {
+                    spikes: flag("spikes"),
+                    disabled: flag("disabled"),
+                };
+                for pair in points.windows(2) {
+                    let (p1, p2) = (to_world(pair[0])?, to_world(pair[1])?);
+                    if p1 != p2 {
+                        level.surfaces.push(Surface {
+                            p1,
+                            p2,
+                            flags,
+                            style: object.properties.get("style").cloned(),
+                            tag: object.properties.get("tag").cloned(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    Ok(level)
+}
+
+/// Parses a number, which has to be finite to be used in the level.
+fn parse_number(value: &str) -> anyhow::Result<f32> {
+    let number: f32 = value
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid number {value:?}"))?;
+    anyhow::ensure!(number.is_finite(), "Invalid number {value:?}");
+    Ok(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The same map in both formats: the objects of every kind,
+    /// with a nested group layer and a layer after it to check the offsets.
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" tilewidth="10" tileheight="10">
+ <objectgroup name="gameplay" offsetx="5">
+  <object id="1" name="spawn" x="10" y="20"><point/></object>
+  <object id="2" type="checkpoint" x="30" y="20"><point/></object>
+  <object id="3" name="hazard" x="0" y="40" width="20" height="10"/>
+  <object id="4" name="hazard" x="40" y="40" width="10" height="10">
+   <properties>
+    <property name="period" type="float" value="2"/>
+    <property name="offset_y" type="float" value="3"/>
+   </properties>
+  </object>
+ </objectgroup>
+ <group name="terrain" offsetx="100" offsety="10">
+  <objectgroup name="ground" offsety="10">
+   <object id="5" name="floor" x="0" y="0">
+    <properties>
+     <property name="spikes" type="bool" value="true"/>
+     <property name="style" value="grass"/>
+    </properties>
+    <polygon points="0,0 10,0 10,10"/>
+   </object>
+   <object id="6" x="0" y="0"><polyline points="0,0 20,0"/></object>
+  </objectgroup>
+ </group>
+ <objectgroup name="after">
+  <object id="7" x="0" y="0"><polyline points="0,0 0,10"/></object>
+ </objectgroup>
+</map>
+"#;
+
+    const JSON: &str = r#"{
+  "tileheight": 10,
+  "tilewidth": 10,
+  "layers": [
+    {"type": "objectgroup", "offsetx": 5, "objects": [
+      {"name": "spawn", "x": 10, "y": 20, "point": true},
+      {"type": "checkpoint", "x": 30, "y": 20, "point": true},
+      {"name": "hazard", "x": 0, "y": 40, "width": 20, "height": 10},
+      {"name": "hazard", "x": 40, "y": 40, "width": 10, "height": 10, "properties": [
+        {"name": "period", "type": "float", "value": 2},
+        {"name": "offset_y", "type": "float", "value": 3}
+      ]}
+    ]},
+    {"type": "group", "offsetx": 100, "offsety": 10, "layers": [
+      {"type": "objectgroup", "offsety": 10, "objects": [
+        {"name": "floor", "x": 0, "y": 0,
+         "polygon": [{"x": 0, "y": 0}, {"x": 10, "y": 0}, {"x": 10, "y": 10}],
+         "properties": [
+           {"name": "spikes", "type": "bool", "value": true},
+           {"name": "style", "type": "string", "value": "grass"}
+         ]},
+        {"x": 0, "y": 0, "polyline": [{"x": 0, "y": 0}, {"x": 20, "y": 0}]}
+      ]}
+    ]},
+    {"type": "objectgroup", "objects": [
+      {"x": 0, "y": 0, "polyline": [{"x": 0, "y": 0}, {"x": 0, "y": 10}]}
+    ]}
+  ]
+}"#;
+
+    fn point(x: f32, y: f32) -> vec2<Coord> {
+        vec2(x, y).map(r32)
+    }
+
+    fn assert_close(actual: vec2<Coord>, expected: vec2<Coord>) {
+        let delta = (actual - expected).len().as_f32();
+        assert!(delta < 1e-4, "{actual:?} != {expected:?}");
+    }
+
+    fn assert_area(actual: Aabb2<Coord>, expected: Aabb2<Coord>) {
+        assert_close(actual.min, expected.min);
+        assert_close(actual.max, expected.max);
+    }
+
+    fn check_map(level: &Level) {
+        assert_close(level.spawn_point, point(1.5, -2.0));
+        assert_eq!(level.checkpoints.len(), 1);
+        assert_close(level.checkpoints[0].position, point(3.5, -2.0));
+
+        assert_eq!(level.hazards.len(), 2);
+        match &level.hazards[0] {
+            Hazard::KillZone(area) => assert_area(
+                *area,
+                Aabb2::from_corners(point(0.5, -5.0), point(2.5, -4.0)),
+            ),
+            hazard => panic!("Expected a kill zone, got {hazard:?}"),
+        }
+        match &level.hazards[1] {
+            Hazard::Crusher(crusher) => {
+                let expected = Aabb2::from_corners(point(4.5, -5.0), point(5.5, -4.0));
+                assert_area(crusher.area, expected);
+                assert_eq!(crusher.offset, point(0.0, 3.0));
+                assert_eq!(crusher.period, Time::new(2.0));
+            }
+            hazard => panic!("Expected a crusher, got {hazard:?}"),
+        }
+
+        // The polygon is closed, the polylines are not
+        let expected = [
+            (point(10.0, -2.0), point(11.0, -2.0), true),
+            (point(11.0, -2.0), point(11.0, -3.0), true),
+            (point(11.0, -3.0), point(10.0, -2.0), true),
+            (point(10.0, -2.0), point(12.0, -2.0), false),
+            (point(0.0, 0.0), point(0.0, -1.0), false),
+        ];
+        assert_eq!(level.surfaces.len(), expected.len());
+        for (surface, (p1, p2, floor)) in level.surfaces.iter().zip(expected) {
+            assert_close(surface.p1, p1);
+            assert_close(surface.p2, p2);
+            assert_eq!(surface.flags.spikes, floor);
+            assert_eq!(surface.style.as_deref(), floor.then_some("grass"));
+        }
+    }
+
+    #[test]
+    fn tmx() {
+        check_map(&import_tmx(TMX).unwrap());
+    }
+
+    #[test]
+    fn json() {
+        check_map(&import_tiled_json(JSON).unwrap());
+    }
+
+    #[test]
+    fn rotated_rectangle() {
+        let tmx = r#"<map tileheight="10"><objectgroup>
+            <object x="0" y="0" width="10" height="20" rotation="90"/>
+        </objectgroup></map>"#;
+        let level = import_tmx(tmx).unwrap();
+        assert_eq!(level.surfaces.len(), 4);
+        // Rotated clockwise on the screen around the top left corner
+        assert_close(level.surfaces[0].p2, point(0.0, -1.0));
+        assert_close(level.surfaces[1].p2, point(-2.0, -1.0));
+    }
+
+    #[test]
+    fn invalid_numbers_are_errors() {
+        for tmx in [
+            r#"<map tileheight="10"><objectgroup><object name="spawn" x="nan" y="0"/></objectgroup></map>"#,
+            r#"<map tileheight="10"><objectgroup offsetx="inf"><object name="spawn"/></objectgroup></map>"#,
+            r#"<map tileheight="10"><objectgroup><object x="0" y="0"><polyline points="0,0 inf,0"/></object></objectgroup></map>"#,
+            r#"<map tileheight="1e-40"><objectgroup><object name="spawn" x="1" y="1"/></objectgroup></map>"#,
+            r#"<map><objectgroup><object name="spawn" x="1" y="1"/></objectgroup></map>"#,
+            r#"<map tileheight="10"><objectgroup><object name="hazard" x="0" y="0" width="1" height="1">
+                <properties><property name="period" value="nan"/></properties>
+            </object></objectgroup></map>"#,
+        ] {
+            assert!(import_tmx(tmx).is_err(), "{tmx}");
+        }
+        for json in [
+            r#"{"tileheight": 10, "layers": [{"objects": [{"name": "spawn", "x": 1e40}]}]}"#,
+            r#"{"tileheight": 10, "layers": [{"objects": [{"x": 0, "y": 0, "width": 1, "height": 1, "rotation": 1e40}]}]}"#,
+            r#"{"tileheight": 10, "layers": [{"objects": [{"name": "hazard", "width": 1, "height": 1,
+                "properties": [{"name": "period", "value": 1}, {"name": "offset_x", "value": "inf"}]}]}]}"#,
+        ] {
+            assert!(import_tiled_json(json).is_err(), "{json}");
+        }
+    }
+}
@@ -2,38 +2,137 @@ use super::*;
 
 use geng_utils::conversions::*;
 
-const CAMERA_INTERPOLATION: f32 = 0.5;
+mod camera;
+mod pause;
+mod rival;
 
-const MOUSE_SENSITIVITY: f32 = 0.005;
-
-const KEYS_MOVE_RIGHT: [geng::Key; 2] = [geng::Key::D, geng::Key::ArrowRight];
-const KEYS_MOVE_LEFT: [geng::Key; 2] = [geng::Key::A, geng::Key::ArrowLeft];
+use camera::CameraController;
+use pause::PauseMenu;
+use rival::Rival;
 
 pub struct Game {
     geng: Geng,
     assets: Rc<Assets>,
     pub render: Render,
+    audio: Audio,
     pub model: Model,
     pub player_control: BodyControl,
+    /// A bot racing the player through the level.
+    rival: Option<Rival>,
+    best_run: Option<GhostRun>,
+    results: LevelResults,
+    /// Streams the surfaces of the level, `None` if the level stores them inline.
+    chunks: Option<ChunkStream>,
+    controls: InputConfig,
+    settings: Settings,
+    gamepad: GamepadInput,
+    pause: Option<PauseMenu>,
+    framebuffer_size: vec2<f32>,
     cursor_pos: vec2<f32>,
     toggle_editor: bool,
-    camera_target: vec2<Coord>,
+    exit_to_menu: bool,
+    camera: CameraController,
 }
 
 impl Game {
     pub fn new(geng: &Geng, assets: &Rc<Assets>) -> Self {
-        geng.window().lock_cursor();
-        let level =
-            Level::load(run_dir().join("assets").join("new_level.json")).unwrap_or_default();
-        Self {
+        let path = run_dir().join("assets").join("new_level.json");
+        let mut level = Level::load(&path).unwrap_or_default();
+        let spawn_point = level.spawn_point.map(Coord::as_f32);
+        let mut chunks = level
+            .chunked
+            .then(|| ChunkStream::new(Level::chunks_dir(&path)));
+        if !level.script.is_empty() {
+            // The script refers to the surfaces and keeps their changes,
+            // so the whole level is loaded at once instead of being streamed
+            if let Some(chunks) = chunks.take() {
+                level.surfaces = chunks.read_all();
+            }
+        }
+        let settings =
+            Settings::load(run_dir().join("assets").join("settings.json")).unwrap_or_default();
+        let mut game = Self {
             geng: geng.clone(),
             assets: assets.clone(),
             render: Render::new(geng, assets),
+            audio: Audio::new(geng, assets, settings.volume),
             model: Model::new(level),
+            chunks,
             player_control: default(),
+            rival: None,
+            best_run: GhostRun::load(run_dir().join("assets").join("new_level.ghost.json")),
+            results: LevelResults::load(run_dir().join("assets").join("new_level.results.json"))
+                .unwrap_or_default(),
+            controls: InputConfig::load(run_dir().join("assets").join("controls.json"))
+                .unwrap_or_default(),
+            settings,
+            gamepad: GamepadInput::new(),
+            pause: None,
+            framebuffer_size: vec2(1.0, 1.0),
             cursor_pos: vec2::ZERO,
             toggle_editor: false,
-            camera_target: vec2::ZERO,
+            exit_to_menu: false,
+            camera: CameraController::new(spawn_point),
+        };
+        game.stream_chunks();
+        game.camera.snap(&mut game.render.camera, spawn_point);
+        game.lock_cursor();
+        game
+    }
+
+    /// Updates the records and keeps the finished run if it is the new personal best.
+    fn finish_run(&mut self) {
+        self.results.record(&self.model.run);
+        self.results
+            .save(run_dir().join("assets").join("new_level.results.json"));
+
+        let run = &self.model.run.recording;
+        if run.is_better_than(self.best_run.as_ref()) {
+            run.save(run_dir().join("assets").join("new_level.ghost.json"));
+            self.best_run = Some(run.clone());
+        }
+    }
+
+    pub fn restart_level(&mut self) {
+        self.model = Model::new(self.model.initial_level());
+        if self.rival.is_some() {
+            self.rival = Some(Rival::new(self.full_level()));
+        }
+        self.camera.snap(
+            &mut self.render.camera,
+            self.model.player.center.position.map(Coord::as_f32),
+        );
+    }
+
+    /// Returns the initial level with all of its surfaces, including the ones not streamed in.
+    fn full_level(&self) -> Level {
+        let mut level = self.model.initial_level();
+        if let Some(chunks) = &self.chunks {
+            level.surfaces = chunks.read_all();
+        }
+        level
+    }
+
+    /// Loads the level chunks around the player and unloads the distant ones.
+    fn stream_chunks(&mut self) {
+        let Some(chunks) = &mut self.chunks else {
+            return;
+        };
+        let model = &mut self.model;
+        if chunks.update(
+            &mut model.level.surfaces,
+            model.player.center.position,
+            false,
+        ) {
+            model.surface_grid = SurfaceGrid::enabled(&model.level.surfaces);
+        }
+    }
+
+    /// Locks or unlocks the cursor depending on the hand target mode.
+    fn lock_cursor(&self) {
+        match self.controls.hand_target_mode {
+            HandTargetMode::Relative => self.geng.window().lock_cursor(),
+            HandTargetMode::Absolute => self.geng.window().unlock_cursor(),
         }
     }
 }
@@ -41,58 +140,150 @@ impl Game {
 impl geng::State for Game {
     fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
         ugli::clear(framebuffer, Some(Rgba::BLACK), None, None);
+        self.framebuffer_size = framebuffer.size().as_f32();
+        self.render.draw(
+            &self.model,
+            &self.player_control,
+            self.best_run.as_ref(),
+            framebuffer,
+        );
+        if let Some(rival) = &self.rival {
+            rival.draw(&self.render, framebuffer);
+        }
+        self.render
+            .draw_hud(&self.model, self.best_run.as_ref(), framebuffer);
         self.render
-            .draw(&self.model, &self.player_control, framebuffer);
+            .draw_summary(&self.model, &self.results, framebuffer);
+        self.draw_pause(framebuffer);
     }
 
     fn handle_event(&mut self, event: geng::Event) {
+        if self.pause.is_some() {
+            self.handle_pause_event(event);
+            return;
+        }
+
+        let window = self.geng.window();
+        if self.controls.is_triggered(window, &event, Action::Pause) {
+            self.pause();
+            return;
+        }
+        if self
+            .controls
+            .is_triggered(window, &event, Action::ToggleEditor)
+        {
+            self.toggle_editor = true;
+        }
+        if self
+            .controls
+            .is_triggered(window, &event, Action::ToggleBot)
+        {
+            self.rival = match self.rival {
+                Some(_) => None,
+                None => Some(Rival::new(self.full_level())),
+            };
+        }
+        if self.model.run.is_finished() && self.controls.is_triggered(window, &event, Action::Jump)
+        {
+            self.restart_level();
+            return;
+        }
+
         match event {
             geng::Event::CursorMove { position } => {
                 self.cursor_pos = position.as_f32();
             }
             geng::Event::RawMouseMove { delta } => {
-                // let delta = position.as_f32() - self.cursor_pos;
-                let delta = delta.as_f32();
-                let delta = delta * MOUSE_SENSITIVITY;
-                self.player_control.hand_target += delta.as_r32();
+                if let HandTargetMode::Relative = self.controls.hand_target_mode {
+                    let delta = self.controls.mouse_delta(delta.as_f32());
+                    self.player_control.hand_target += delta.as_r32();
+                }
             }
-            geng::Event::KeyPress { key: geng::Key::T } => self.toggle_editor = true,
             _ => {}
         }
     }
 
     fn update(&mut self, delta_time: f64) {
         let delta_time = delta_time as f32;
+        if self.pause.is_some() {
+            self.audio.pause();
+            return;
+        }
 
         // Update control
         let window = self.geng.window();
-        let pressed =
-            |keys: &[geng::Key]| -> bool { keys.iter().any(|&key| window.is_key_pressed(key)) };
+        let pressed = |action| self.controls.is_pressed(window, action);
         let mut movement = 0.0;
-        if pressed(&KEYS_MOVE_RIGHT) {
+        if pressed(Action::MoveRight) {
             movement += 1.0;
         }
-        if pressed(&KEYS_MOVE_LEFT) {
+        if pressed(Action::MoveLeft) {
             movement -= 1.0;
         }
         self.player_control.move_speed = r32(movement);
 
-        self.player_control.jump = pressed(&[geng::Key::Space]);
+        self.player_control.jump = pressed(Action::Jump);
+
+        self.player_control.hold = pressed(Action::Hold);
 
-        self.player_control.hold = window.is_button_pressed(geng::MouseButton::Left);
+        if let HandTargetMode::Absolute = self.controls.hand_target_mode {
+            let cursor = self
+                .render
+                .camera
+                .screen_to_world(self.framebuffer_size, self.cursor_pos)
+                .as_r32();
+            self.player_control.hand_target = cursor - self.model.player.center.position;
+        }
+
+        if let Some(gilrs) = self.geng.gilrs() {
+            self.gamepad.update(
+                &*gilrs,
+                &mut self.player_control,
+                self.model.player.arm.max_reach(),
+            );
+        }
 
         // Update model
-        self.model
+        self.stream_chunks();
+        let events = self
+            .model
             .update(&mut self.player_control, Time::new(delta_time));
+        if self.settings.screen_shake {
+            for event in &events {
+                self.camera.react(event);
+            }
+        }
+        if let Some(rival) = &mut self.rival {
+            rival.update(Time::new(delta_time));
+        }
+        self.render.handle_events(&events);
+        self.render.update(delta_time);
+        self.audio.handle_events(&events);
+        self.audio.update(&self.model);
+        if events
+            .iter()
+            .any(|event| matches!(event, Event::Finished { .. }))
+        {
+            self.finish_run();
+        }
 
         // Update camera position
-        self.camera_target = self.model.player.center.position;
-        let delta = self.camera_target - self.render.camera.center.map(Coord::new);
-        self.render.camera.center +=
-            (delta * Coord::new(delta_time / CAMERA_INTERPOLATION)).map(Coord::as_f32);
+        self.camera.update(
+            &mut self.render.camera,
+            &self.model,
+            self.framebuffer_size,
+            self.settings.camera_smoothing,
+            delta_time,
+        );
     }
 
     fn transition(&mut self) -> Option<geng::state::Transition> {
+        if self.exit_to_menu {
+            return Some(geng::state::Transition::Switch(Box::new(MainMenu::new(
+                &self.geng,
+                &self.assets,
+            ))));
+        }
         self.toggle_editor.then(|| {
             geng::state::Transition::Switch(Box::new(crate::editor::Editor::new(
                 &self.geng,
@@ -0,0 +1,149 @@
+use super::*;
+
+const DEFAULT_FOV: f32 = 20.0;
+/// Half-size of the area around the focus where the player can move without moving the camera.
+const DEAD_ZONE: vec2<f32> = vec2(1.5, 1.0);
+/// How far ahead of the player the camera looks, in seconds of movement.
+const LOOK_AHEAD_TIME: f32 = 0.4;
+const MAX_LOOK_AHEAD: f32 = 4.0;
+/// Fraction of the view height the camera is lifted by when the player is on the ground,
+/// so that more of the screen is spent on what is ahead rather than below.
+const GROUND_FRAMING: f32 = 0.15;
+/// Extra space around the level geometry the camera is allowed to show.
+const BOUNDS_MARGIN: f32 = 5.0;
+/// Landings slower than this do not shake the camera.
+const SHAKE_LANDING_IMPACT: f32 = 8.0;
+/// Shake added per unit of impact above the threshold.
+const SHAKE_PER_IMPACT: f32 = 0.05;
+const SHAKE_HOLD_BROKE: f32 = 0.5;
+/// Camera offset at the full shake.
+const MAX_SHAKE_OFFSET: f32 = 0.6;
+/// How much of the full shake wears off per second.
+const SHAKE_DECAY: f32 = 1.5;
+const SHAKE_FREQUENCY: f32 = 25.0;
+
+/// Moves the camera to follow the player.
+pub struct CameraController {
+    /// The point the dead zone is centered around.
+    focus: vec2<f32>,
+    look_ahead: vec2<f32>,
+    vertical_bias: f32,
+    /// Smoothed camera position without the shake.
+    center: vec2<f32>,
+    /// Current shake strength, from 0 to 1.
+    shake: f32,
+    shake_time: f32,
+}
+
+impl CameraController {
+    pub fn new(position: vec2<f32>) -> Self {
+        Self {
+            focus: position,
+            look_ahead: vec2::ZERO,
+            vertical_bias: 0.0,
+            center: position,
+            shake: 0.0,
+            shake_time: 0.0,
+        }
+    }
+
+    /// Instantly moves the camera to look at the position.
+    pub fn snap(&mut self, camera: &mut Camera2d, position: vec2<f32>) {
+        *self = Self::new(position);
+        camera.center = position;
+    }
+
+    /// Shakes the camera in response to the hard impacts.
+    pub fn react(&mut self, event: &Event) {
+        let shake = match *event {
+            Event::Landed { impact, .. } => {
+                (impact.as_f32() - SHAKE_LANDING_IMPACT).max(0.0) * SHAKE_PER_IMPACT
+            }
+            Event::HoldEnded {
+                reason: HoldEndReason::Broken,
+                ..
+            } => SHAKE_HOLD_BROKE,
+            _ => 0.0,
+        };
+        self.shake = (self.shake + shake).min(1.0);
+    }
+
+    pub fn update(
+        &mut self,
+        camera: &mut Camera2d,
+        model: &Model,
+        framebuffer_size: vec2<f32>,
+        smoothing: f32,
+        delta_time: f32,
+    ) {
+        // Exponential smoothing, independent of the frame rate
+        let t = 1.0 - (-delta_time / smoothing.max(1e-3)).exp();
+
+        let player = &model.player;
+        let position = player.center.position.map(Coord::as_f32);
+        let velocity = player.center.velocity.map(Coord::as_f32);
+
+        // Dead zone
+        let delta = position - self.focus;
+        self.focus += vec2(
+            delta.x - delta.x.clamp_abs(DEAD_ZONE.x),
+            delta.y - delta.y.clamp_abs(DEAD_ZONE.y),
+        );
+
+        // Look-ahead
+        let look_ahead = vec2(velocity.x * LOOK_AHEAD_TIME, 0.0).clamp_len(..=MAX_LOOK_AHEAD);
+        self.look_ahead += (look_ahead - self.look_ahead) * t;
+
+        // Vertical framing
+        let zone = model
+            .level
+            .camera_zones
+            .iter()
+            .find(|zone| zone.area.contains(player.center.position));
+        let fov = zone.and_then(|zone| zone.fov).unwrap_or(DEFAULT_FOV);
+        let bias = if player.ground_normal.is_some() {
+            fov * GROUND_FRAMING
+        } else {
+            0.0
+        };
+        self.vertical_bias += (bias - self.vertical_bias) * t;
+
+        let target = match zone.and_then(|zone| zone.target) {
+            Some(target) => target.map(Coord::as_f32),
+            None => self.focus + self.look_ahead + vec2(0.0, self.vertical_bias),
+        };
+
+        camera.fov += (fov - camera.fov) * t;
+        self.center += (target - self.center) * t;
+
+        // Keep the view inside the level
+        let bounds = model
+            .level
+            .bounds()
+            .map(Coord::as_f32)
+            .extend_uniform(BOUNDS_MARGIN);
+        let aspect = framebuffer_size.x / framebuffer_size.y.max(1.0);
+        let half_view = vec2(camera.fov * aspect, camera.fov) / 2.0;
+        self.center = vec2(
+            clamp_view(self.center.x, half_view.x, bounds.min.x, bounds.max.x),
+            clamp_view(self.center.y, half_view.y, bounds.min.y, bounds.max.y),
+        );
+
+        // Shake, squared so that the small shakes stay subtle
+        self.shake = (self.shake - SHAKE_DECAY * delta_time).max(0.0);
+        self.shake_time += delta_time;
+        let phase = self.shake_time * SHAKE_FREQUENCY;
+        let direction = vec2(phase.sin(), (phase * 1.3 + 1.0).sin());
+        camera.center = self.center + direction * self.shake.sqr() * MAX_SHAKE_OFFSET;
+    }
+}
+
+/// Clamps the view center so that the view stays within the range,
+/// or centers it if the view is larger than the range.
+fn clamp_view(center: f32, half_view: f32, min: f32, max: f32) -> f32 {
+    if max - min <= half_view * 2.0 {
+        (min + max) / 2.0
+    } else {
+        center.clamp(min + half_view, max - half_view)
+    }
+}
@@ -0,0 +1,296 @@
+use super::*;
+
+const SENSITIVITY_STEP: f32 = 1.25;
+const SMOOTHING_STEP: f32 = 0.05;
+const MIN_SMOOTHING: f32 = 0.05;
+const MAX_SMOOTHING: f32 = 2.0;
+const VOLUME_STEP: f32 = 0.1;
+
+const OVERLAY_COLOR: Rgba<f32> = Rgba {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.7,
+};
+
+/// Actions that can be rebound from the settings page.
+const REBINDABLE_ACTIONS: [Action; 7] = [
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::Jump,
+    Action::Hold,
+    Action::Pause,
+    Action::ToggleEditor,
+    Action::ToggleBot,
+];
+
+pub struct PauseMenu {
+    page: PausePage,
+    list: MenuList,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PausePage {
+    Main,
+    Settings,
+    /// Waiting for the player to press the new input for the action.
+    Rebinding(Action),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MainItem {
+    Resume,
+    Restart,
+    RestartLevel,
+    Settings,
+    MainMenu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingsItem {
+    Sensitivity,
+    CameraSmoothing,
+    ScreenShake,
+    Volume,
+    InvertX,
+    InvertY,
+    HandTargetMode,
+    Bind(Action),
+    Back,
+}
+
+impl PauseMenu {
+    const MAIN_ITEMS: [MainItem; 5] = [
+        MainItem::Resume,
+        MainItem::Restart,
+        MainItem::RestartLevel,
+        MainItem::Settings,
+        MainItem::MainMenu,
+    ];
+
+    pub fn new(geng: &Geng) -> Self {
+        Self {
+            page: PausePage::Main,
+            list: MenuList::new(geng),
+        }
+    }
+
+    fn settings_items() -> Vec<SettingsItem> {
+        [
+            SettingsItem::Sensitivity,
+            SettingsItem::CameraSmoothing,
+            SettingsItem::ScreenShake,
+            SettingsItem::Volume,
+            SettingsItem::InvertX,
+            SettingsItem::InvertY,
+            SettingsItem::HandTargetMode,
+        ]
+        .into_iter()
+        .chain(REBINDABLE_ACTIONS.map(SettingsItem::Bind))
+        .chain([SettingsItem::Back])
+        .collect()
+    }
+
+    fn set_page(&mut self, page: PausePage) {
+        self.page = page;
+        self.list.selected = 0;
+    }
+}
+
+impl Game {
+    pub fn pause(&mut self) {
+        self.geng.window().unlock_cursor();
+        self.pause = Some(PauseMenu::new(&self.geng));
+    }
+
+    pub fn resume(&mut self) {
+        self.pause = None;
+        self.lock_cursor();
+    }
+
+    fn save_settings(&self) {
+        self.controls
+            .save(run_dir().join("assets").join("controls.json"));
+        self.settings
+            .save(run_dir().join("assets").join("settings.json"));
+    }
+
+    pub(super) fn handle_pause_event(&mut self, event: geng::Event) {
+        let Some(mut pause) = self.pause.take() else {
+            return;
+        };
+        let window = self.geng.window();
+        let mut resume = false;
+
+        match pause.page {
+            PausePage::Main => {
+                if self.controls.is_triggered(window, &event, Action::Pause) {
+                    resume = true;
+                } else if let Some(MenuInput::Activate(index)) =
+                    pause.list.handle_event(&event, PauseMenu::MAIN_ITEMS.len())
+                {
+                    match PauseMenu::MAIN_ITEMS[index] {
+                        MainItem::Resume => resume = true,
+                        MainItem::Restart => {
+                            self.model.restart();
+                            self.camera.snap(
+                                &mut self.render.camera,
+                                self.model.player.center.position.map(Coord::as_f32),
+                            );
+                            resume = true;
+                        }
+                        MainItem::RestartLevel => {
+                            self.restart_level();
+                            resume = true;
+                        }
+                        MainItem::Settings => pause.set_page(PausePage::Settings),
+                        MainItem::MainMenu => self.exit_to_menu = true,
+                    }
+                }
+            }
+            PausePage::Settings => {
+                let items = PauseMenu::settings_items();
+                if self.controls.is_triggered(window, &event, Action::Pause) {
+                    pause.set_page(PausePage::Main);
+                    self.save_settings();
+                } else if let Some(input) = pause.list.handle_event(&event, items.len()) {
+                    let (index, delta) = match input {
+                        MenuInput::Activate(index) => (index, 1),
+                        MenuInput::Adjust(index, delta) => (index, delta),
+                    };
+                    match items[index] {
+                        SettingsItem::Sensitivity => {
+                            self.controls.mouse_sensitivity *= SENSITIVITY_STEP.powi(delta);
+                        }
+                        SettingsItem::CameraSmoothing => {
+                            self.settings.camera_smoothing = (self.settings.camera_smoothing
+                                + SMOOTHING_STEP * delta as f32)
+                                .clamp(MIN_SMOOTHING, MAX_SMOOTHING);
+                        }
+                        SettingsItem::ScreenShake => {
+                            self.settings.screen_shake = !self.settings.screen_shake;
+                        }
+                        SettingsItem::Volume => {
+                            self.settings.volume =
+                                (self.settings.volume + VOLUME_STEP * delta as f32).clamp(0.0, 1.0);
+                            self.audio.set_volume(self.settings.volume);
+                        }
+                        SettingsItem::InvertX => self.controls.invert_x = !self.controls.invert_x,
+                        SettingsItem::InvertY => self.controls.invert_y = !self.controls.invert_y,
+                        SettingsItem::HandTargetMode => {
+                            self.controls.hand_target_mode = match self.controls.hand_target_mode {
+                                HandTargetMode::Relative => HandTargetMode::Absolute,
+                                HandTargetMode::Absolute => HandTargetMode::Relative,
+                            };
+                        }
+                        SettingsItem::Bind(action) => {
+                            if let MenuInput::Activate(_) = input {
+                                pause.page = PausePage::Rebinding(action);
+                            }
+                        }
+                        SettingsItem::Back => {
+                            pause.set_page(PausePage::Main);
+                            self.save_settings();
+                        }
+                    }
+                }
+            }
+            PausePage::Rebinding(action) => {
+                if let Some(input) = Input::from_event(&event) {
+                    if input == Input::Key(geng::Key::Escape) {
+                        pause.page = PausePage::Settings;
+                    } else if !Binding::is_modifier(input) {
+                        let binding = Binding {
+                            input,
+                            ctrl: window.is_key_pressed(geng::Key::ControlLeft)
+                                || window.is_key_pressed(geng::Key::ControlRight),
+                        };
+                        self.controls.rebind(action, binding);
+                        pause.page = PausePage::Settings;
+                    }
+                }
+            }
+        }
+
+        if resume {
+            self.resume();
+        } else {
+            self.pause = Some(pause);
+        }
+    }
+
+    pub(super) fn draw_pause(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        let Some(pause) = &mut self.pause else {
+            return;
+        };
+
+        let size = framebuffer.size().map(|x| x as f32);
+        self.geng.draw2d().draw2d(
+            framebuffer,
+            &geng::PixelPerfectCamera,
+            &draw2d::Quad::new(Aabb2::ZERO.extend_positive(size), OVERLAY_COLOR),
+        );
+
+        let on_off = |value: bool| if value { "On" } else { "Off" };
+        let (title, items) = match pause.page {
+            PausePage::Main => (
+                "Paused",
+                PauseMenu::MAIN_ITEMS
+                    .iter()
+                    .map(|item| match item {
+                        MainItem::Resume => "Resume".to_owned(),
+                        MainItem::Restart => "Restart from checkpoint".to_owned(),
+                        MainItem::RestartLevel => "Restart level".to_owned(),
+                        MainItem::Settings => "Settings".to_owned(),
+                        MainItem::MainMenu => "Main menu".to_owned(),
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            PausePage::Settings | PausePage::Rebinding(_) => (
+                "Settings",
+                PauseMenu::settings_items()
+                    .into_iter()
+                    .map(|item| match item {
+                        SettingsItem::Sensitivity => {
+                            format!("Mouse sensitivity: {:.4}", self.controls.mouse_sensitivity)
+                        }
+                        SettingsItem::CameraSmoothing => {
+                            format!("Camera smoothing: {:.2}", self.settings.camera_smoothing)
+                        }
+                        SettingsItem::ScreenShake => {
+                            format!("Screen shake: {}", on_off(self.settings.screen_shake))
+                        }
+                        SettingsItem::Volume => {
+                            format!("Volume: {:.0}%", self.settings.volume * 100.0)
+                        }
+                        SettingsItem::InvertX => {
+                            format!("Invert X: {}", on_off(self.controls.invert_x))
+                        }
+                        SettingsItem::InvertY => {
+                            format!("Invert Y: {}", on_off(self.controls.invert_y))
+                        }
+                        SettingsItem::HandTargetMode => {
+                            format!("Hand target: {:?}", self.controls.hand_target_mode)
+                        }
+                        SettingsItem::Bind(action)
+                            if pause.page == PausePage::Rebinding(action) =>
+                        {
+                            format!("{action:?}: press a key...")
+                        }
+                        SettingsItem::Bind(action) => {
+                            let bindings = self
+                                .controls
+                                .bindings(action)
+                                .iter()
+                                .map(|binding| binding.to_string())
+                                .collect::<Vec<_>>();
+                            format!("{action:?}: {}", bindings.join(", "))
+                        }
+                        SettingsItem::Back => "Back".to_owned(),
+                    })
+                    .collect(),
+            ),
+        };
+        pause.list.draw(title, &items, framebuffer);
+    }
+}
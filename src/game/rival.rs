@@ -0,0 +1,40 @@
+use super::*;
+
+use crate::bot::Bot;
+
+const RIVAL_ALPHA: f32 = 0.6;
+
+/// A bot running through its own copy of the level alongside the player.
+pub struct Rival {
+    model: Model,
+    bot: Bot,
+    control: BodyControl,
+}
+
+impl Rival {
+    pub fn new(level: Level) -> Self {
+        Self {
+            bot: Bot::new(&level),
+            model: Model::new(level),
+            control: default(),
+        }
+    }
+
+    pub fn update(&mut self, delta_time: Time) {
+        if self.model.run.is_finished() {
+            return;
+        }
+        self.control = self.bot.control(&self.model, delta_time);
+        self.model.update(&mut self.control, delta_time);
+    }
+
+    pub fn draw(&self, render: &Render, framebuffer: &mut ugli::Framebuffer) {
+        if self.model.death.is_none() {
+            render.draw_body(
+                &BodyPose::from_body(&self.model.player),
+                RIVAL_ALPHA,
+                framebuffer,
+            );
+        }
+    }
+}
@@ -0,0 +1,7 @@
+use super::*;
+
+mod config;
+mod gamepad;
+
+pub use config::*;
+pub use gamepad::*;
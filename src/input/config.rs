@@ -0,0 +1,352 @@
+use super::*;
+
+const MOUSE_SENSITIVITY: f32 = 0.005;
+
+/// Everything the player can do, independent of the device it is bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Hold,
+    Pause,
+    ToggleEditor,
+    /// Let the bot play the level, or take the control back.
+    ToggleBot,
+    EditorPlay,
+    EditorSave,
+    EditorSetSpawn,
+    EditorCheckpoint,
+    EditorFinish,
+    /// Toggle spikes on the hovered surface.
+    EditorSpikes,
+    /// Cycle the style of the hovered surface.
+    EditorSurfaceStyle,
+    EditorKillZone,
+    EditorCrusher,
+    EditorCollectible,
+    EditorHandCollectible,
+    EditorBar,
+    EditorRope,
+    EditorBox,
+    EditorBall,
+    /// Place the selected sprite as a decoration.
+    EditorSprite,
+    EditorNextSprite,
+    /// Add a vertex to the decoration polygon, placing it when the shape is closed.
+    EditorPolygon,
+    /// Toggle whether new decorations go over or behind the gameplay layer.
+    EditorForeground,
+    /// Add a background layer with the selected sprite.
+    EditorBackground,
+    /// Place a trigger volume for the level script.
+    EditorTrigger,
+    /// Give the hovered surface the current script tag, or remove its tag.
+    EditorSurfaceTag,
+    /// Start a new script tag for the following surfaces.
+    EditorNewTag,
+    /// Toggle whether the hovered surface starts disabled.
+    EditorSurfaceDisabled,
+    EditorPlate,
+    EditorLever,
+    EditorDoor,
+    /// Place a logic gate, or change the kind of the hovered one.
+    EditorGate,
+    /// Click two objects to connect them with a wire, or to remove the wire between them.
+    EditorWire,
+    /// Show which parts of the level can be reached from the spawn point, or hide them.
+    EditorReachability,
+    EditorImportSvg,
+    EditorExportSvg,
+    EditorImportTiled,
+    /// Store the surfaces in chunks next to the level, which only get loaded around the camera,
+    /// or move them back into the level file.
+    EditorChunked,
+    EditorClear,
+    /// Drag to create a new surface.
+    EditorDraw,
+    /// Drag to move the camera, click to remove a surface.
+    EditorPan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Input {
+    Key(geng::Key),
+    Mouse(geng::MouseButton),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binding {
+    pub input: Input,
+    #[serde(default)]
+    pub ctrl: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HandTargetMode {
+    /// The mouse moves the hand target relative to its current position.
+    Relative,
+    /// The hand target follows the cursor position in the world.
+    Absolute,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputConfig {
+    pub bindings: HashMap<Action, Vec<Binding>>,
+    pub mouse_sensitivity: f32,
+    pub invert_x: bool,
+    pub invert_y: bool,
+    pub hand_target_mode: HandTargetMode,
+}
+
+impl Input {
+    pub fn from_event(event: &geng::Event) -> Option<Self> {
+        match *event {
+            geng::Event::KeyPress { key } => Some(Self::Key(key)),
+            geng::Event::MousePress { button } => Some(Self::Mouse(button)),
+            _ => None,
+        }
+    }
+
+    pub fn is_pressed(self, window: &geng::Window) -> bool {
+        match self {
+            Self::Key(key) => window.is_key_pressed(key),
+            Self::Mouse(button) => window.is_button_pressed(button),
+        }
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "{key}"),
+            Self::Mouse(button) => write!(f, "Mouse {button}"),
+        }
+    }
+}
+
+impl Binding {
+    pub fn new(input: Input) -> Self {
+        Self { input, ctrl: false }
+    }
+
+    pub fn key(key: geng::Key) -> Self {
+        Self::new(Input::Key(key))
+    }
+
+    pub fn mouse(button: geng::MouseButton) -> Self {
+        Self::new(Input::Mouse(button))
+    }
+
+    pub fn with_ctrl(self) -> Self {
+        Self { ctrl: true, ..self }
+    }
+
+    /// Whether the key is a modifier and cannot be bound on its own.
+    pub fn is_modifier(input: Input) -> bool {
+        matches!(
+            input,
+            Input::Key(geng::Key::ControlLeft | geng::Key::ControlRight)
+        )
+    }
+
+    /// Checks whether the modifiers held down are exactly the ones of the binding,
+    /// so that `S` does not fire together with `Ctrl+S`.
+    pub fn modifiers_match(&self, ctrl: bool) -> bool {
+        self.ctrl == ctrl
+    }
+
+    /// Checks whether the modifiers of the binding are held down, extra ones are allowed
+    /// so that holding Ctrl does not interrupt the movement.
+    pub fn modifiers_held(&self, ctrl: bool) -> bool {
+        !self.ctrl || ctrl
+    }
+}
+
+fn ctrl_pressed(window: &geng::Window) -> bool {
+    window.is_key_pressed(geng::Key::ControlLeft) || window.is_key_pressed(geng::Key::ControlRight)
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        write!(f, "{}", self.input)
+    }
+}
+
+impl InputConfig {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], |bindings| bindings)
+    }
+
+    /// Replaces all bindings of the action with the given one.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        self.bindings.insert(action, vec![binding]);
+    }
+
+    /// Checks whether the input is bound to the action, ignoring the modifiers.
+    pub fn is_bound(&self, action: Action, input: Input) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.input == input)
+    }
+
+    /// Checks whether any of the bindings of the action is currently held down.
+    pub fn is_pressed(&self, window: &geng::Window, action: Action) -> bool {
+        self.bindings(action).iter().any(|binding| {
+            binding.input.is_pressed(window) && binding.modifiers_held(ctrl_pressed(window))
+        })
+    }
+
+    /// Checks whether the event is a press of one of the bindings of the action.
+    pub fn is_triggered(&self, window: &geng::Window, event: &geng::Event, action: Action) -> bool {
+        Input::from_event(event).is_some_and(|input| {
+            self.bindings(action).iter().any(|binding| {
+                binding.input == input && binding.modifiers_match(ctrl_pressed(window))
+            })
+        })
+    }
+
+    /// Converts a raw mouse movement into the hand target movement.
+    pub fn mouse_delta(&self, delta: vec2<f32>) -> vec2<f32> {
+        let mut delta = delta * self.mouse_sensitivity;
+        if self.invert_x {
+            delta.x = -delta.x;
+        }
+        if self.invert_y {
+            delta.y = -delta.y;
+        }
+        delta
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            serde_json::to_writer_pretty(
+                std::fs::File::create(path).expect("Failed to create a file"),
+                &self,
+            )
+            .expect("Failed to serialize the input config");
+            log::info!("Input config saved");
+        }
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Option<Self> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            None
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let file = std::fs::File::open(path).ok()?;
+            match serde_json::from_reader(file) {
+                Ok(config) => Some(config),
+                Err(err) => {
+                    log::error!("Failed to deserialize the input config: {err}");
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        use geng::{Key, MouseButton};
+        let bindings = [
+            (
+                Action::MoveLeft,
+                vec![Binding::key(Key::A), Binding::key(Key::ArrowLeft)],
+            ),
+            (
+                Action::MoveRight,
+                vec![Binding::key(Key::D), Binding::key(Key::ArrowRight)],
+            ),
+            (Action::Jump, vec![Binding::key(Key::Space)]),
+            (Action::Hold, vec![Binding::mouse(MouseButton::Left)]),
+            (Action::Pause, vec![Binding::key(Key::Escape)]),
+            (Action::ToggleEditor, vec![Binding::key(Key::T)]),
+            (Action::ToggleBot, vec![Binding::key(Key::B)]),
+            (Action::EditorPlay, vec![Binding::key(Key::P)]),
+            (Action::EditorSave, vec![Binding::key(Key::S).with_ctrl()]),
+            (Action::EditorSetSpawn, vec![Binding::key(Key::R)]),
+            (Action::EditorCheckpoint, vec![Binding::key(Key::C)]),
+            (Action::EditorFinish, vec![Binding::key(Key::F)]),
+            (Action::EditorSpikes, vec![Binding::key(Key::K)]),
+            (Action::EditorSurfaceStyle, vec![Binding::key(Key::Q)]),
+            (Action::EditorKillZone, vec![Binding::key(Key::Z)]),
+            (Action::EditorCrusher, vec![Binding::key(Key::X)]),
+            (Action::EditorCollectible, vec![Binding::key(Key::G)]),
+            (Action::EditorHandCollectible, vec![Binding::key(Key::H)]),
+            (Action::EditorBar, vec![Binding::key(Key::B)]),
+            (Action::EditorRope, vec![Binding::key(Key::L)]),
+            (Action::EditorBox, vec![Binding::key(Key::V)]),
+            (Action::EditorBall, vec![Binding::key(Key::O)]),
+            (Action::EditorSprite, vec![Binding::key(Key::I)]),
+            (Action::EditorNextSprite, vec![Binding::key(Key::U)]),
+            (Action::EditorPolygon, vec![Binding::key(Key::Y)]),
+            (Action::EditorForeground, vec![Binding::key(Key::J)]),
+            (Action::EditorBackground, vec![Binding::key(Key::N)]),
+            (Action::EditorTrigger, vec![Binding::key(Key::E)]),
+            (Action::EditorSurfaceTag, vec![Binding::key(Key::M)]),
+            (Action::EditorNewTag, vec![Binding::key(Key::W)]),
+            (Action::EditorSurfaceDisabled, vec![Binding::key(Key::S)]),
+            (Action::EditorPlate, vec![Binding::key(Key::Digit1)]),
+            (Action::EditorLever, vec![Binding::key(Key::Digit2)]),
+            (Action::EditorDoor, vec![Binding::key(Key::Digit3)]),
+            (Action::EditorGate, vec![Binding::key(Key::Digit4)]),
+            (Action::EditorWire, vec![Binding::key(Key::Digit5)]),
+            (Action::EditorReachability, vec![Binding::key(Key::Digit6)]),
+            (
+                Action::EditorImportSvg,
+                vec![Binding::key(Key::I).with_ctrl()],
+            ),
+            (
+                Action::EditorExportSvg,
+                vec![Binding::key(Key::E).with_ctrl()],
+            ),
+            (
+                Action::EditorImportTiled,
+                vec![Binding::key(Key::M).with_ctrl()],
+            ),
+            (
+                Action::EditorChunked,
+                vec![Binding::key(Key::K).with_ctrl()],
+            ),
+            (Action::EditorClear, vec![Binding::key(Key::R).with_ctrl()]),
+            (Action::EditorDraw, vec![Binding::mouse(MouseButton::Left)]),
+            (Action::EditorPan, vec![Binding::mouse(MouseButton::Right)]),
+        ];
+        Self {
+            bindings: bindings.into_iter().collect(),
+            mouse_sensitivity: MOUSE_SENSITIVITY,
+            invert_x: false,
+            invert_y: false,
+            hand_target_mode: HandTargetMode::Relative,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modifiers() {
+        let plain = Binding::key(geng::Key::S);
+        let ctrl = plain.with_ctrl();
+        // Presses tell `S` and `Ctrl+S` apart
+        assert!(plain.modifiers_match(false));
+        assert!(!plain.modifiers_match(true));
+        assert!(ctrl.modifiers_match(true));
+        assert!(!ctrl.modifiers_match(false));
+        // Held actions keep going while Ctrl is held
+        assert!(plain.modifiers_held(false));
+        assert!(plain.modifiers_held(true));
+        assert!(ctrl.modifiers_held(true));
+        assert!(!ctrl.modifiers_held(false));
+    }
+}
@@ -0,0 +1,256 @@
+use super::*;
+
+use gilrs::{Axis, Button};
+
+const DEAD_ZONE: f32 = 0.2;
+const TRIGGER_THRESHOLD: f32 = 0.5;
+
+/// Raw state of a single gamepad, before the dead zone is applied.
+#[derive(Debug, Clone, Copy)]
+pub struct GamepadState {
+    pub left_stick: vec2<f32>,
+    pub right_stick: vec2<f32>,
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    pub jump: bool,
+}
+
+/// Anything that can report the currently connected gamepads.
+pub trait GamepadSource {
+    /// Returns the ids and states of all connected gamepads.
+    fn gamepads(&self) -> Vec<(usize, GamepadState)>;
+}
+
+impl GamepadSource for Gilrs {
+    fn gamepads(&self) -> Vec<(usize, GamepadState)> {
+        Gilrs::gamepads(self)
+            .map(|(id, gamepad)| {
+                let trigger = |button| gamepad.button_data(button).map_or(0.0, |data| data.value());
+                let state = GamepadState {
+                    left_stick: vec2(
+                        gamepad.value(Axis::LeftStickX),
+                        gamepad.value(Axis::LeftStickY),
+                    ),
+                    right_stick: vec2(
+                        gamepad.value(Axis::RightStickX),
+                        gamepad.value(Axis::RightStickY),
+                    ),
+                    left_trigger: trigger(Button::LeftTrigger2),
+                    right_trigger: trigger(Button::RightTrigger2),
+                    jump: gamepad.is_pressed(Button::South),
+                };
+                (usize::from(id), state)
+            })
+            .collect()
+    }
+}
+
+/// Maps the first connected gamepad onto the body control.
+#[derive(Debug, Clone)]
+pub struct GamepadInput {
+    /// Id of the gamepad currently in use.
+    active: Option<usize>,
+    pub dead_zone: f32,
+}
+
+impl GamepadInput {
+    pub fn new() -> Self {
+        Self {
+            active: None,
+            dead_zone: DEAD_ZONE,
+        }
+    }
+
+    /// Applies the gamepad on top of the control produced by other devices.
+    /// Picks up a newly connected gamepad if the active one has been disconnected.
+    pub fn update(
+        &mut self,
+        source: &impl GamepadSource,
+        control: &mut BodyControl,
+        max_reach: Coord,
+    ) {
+        let gamepads = source.gamepads();
+        let state = match self
+            .active
+            .and_then(|active| gamepads.iter().find(|(id, _)| *id == active))
+            .or_else(|| gamepads.first())
+        {
+            Some(&(id, state)) => {
+                if self.active != Some(id) {
+                    log::info!("Using gamepad {id}");
+                    self.active = Some(id);
+                }
+                state
+            }
+            None => {
+                if let Some(id) = self.active.take() {
+                    log::info!("Gamepad {id} disconnected");
+                }
+                return;
+            }
+        };
+
+        let left_stick = self.apply_dead_zone(state.left_stick);
+        control.move_speed += r32(left_stick.x);
+
+        let right_stick = self.apply_dead_zone(state.right_stick);
+        if right_stick != vec2::ZERO {
+            control.hand_target = right_stick.map(r32) * max_reach;
+        }
+
+        control.hold |= state.left_trigger.max(state.right_trigger) > TRIGGER_THRESHOLD;
+        control.jump |= state.jump;
+    }
+
+    /// Applies a radial dead zone and rescales the rest of the range to `0.0..=1.0`.
+    fn apply_dead_zone(&self, stick: vec2<f32>) -> vec2<f32> {
+        let len = stick.len();
+        if len <= self.dead_zone {
+            return vec2::ZERO;
+        }
+        let t = ((len - self.dead_zone) / (1.0 - self.dead_zone)).min(1.0);
+        stick / len * t
+    }
+}
+
+impl Default for GamepadInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gamepads connected in the test, in the order they are reported.
+    struct FakeGamepads(Vec<(usize, GamepadState)>);
+
+    impl GamepadSource for FakeGamepads {
+        fn gamepads(&self) -> Vec<(usize, GamepadState)> {
+            self.0.clone()
+        }
+    }
+
+    const IDLE: GamepadState = GamepadState {
+        left_stick: vec2(0.0, 0.0),
+        right_stick: vec2(0.0, 0.0),
+        left_trigger: 0.0,
+        right_trigger: 0.0,
+        jump: false,
+    };
+
+    fn control(input: &mut GamepadInput, source: &FakeGamepads) -> BodyControl {
+        let mut control = BodyControl::default();
+        input.update(source, &mut control, r32(1.5));
+        control
+    }
+
+    #[test]
+    fn dead_zone_is_radial() {
+        let input = GamepadInput::new();
+        assert_eq!(input.apply_dead_zone(vec2(0.15, 0.1)), vec2::ZERO);
+        // Past the dead zone as a whole, even though each axis is within it
+        let diagonal = input.apply_dead_zone(vec2(0.19, 0.19));
+        assert!(diagonal.x > 0.0 && diagonal.y > 0.0);
+        // The direction is kept and the rest of the range is rescaled
+        let half = input.apply_dead_zone(vec2(0.6, 0.0));
+        assert!((half.x - 0.5).abs() < 1e-5 && half.y == 0.0);
+        let full = input.apply_dead_zone(vec2(1.0, 1.0));
+        assert!((full.len() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn left_stick_moves() {
+        let mut input = GamepadInput::new();
+        let source = FakeGamepads(vec![(
+            0,
+            GamepadState {
+                left_stick: vec2(-1.0, 0.0),
+                ..IDLE
+            },
+        )]);
+        assert_eq!(control(&mut input, &source).move_speed, r32(-1.0));
+        let source = FakeGamepads(vec![(
+            0,
+            GamepadState {
+                left_stick: vec2(0.1, 0.1),
+                ..IDLE
+            },
+        )]);
+        assert_eq!(control(&mut input, &source).move_speed, Coord::ZERO);
+    }
+
+    #[test]
+    fn right_stick_aims_the_hand_within_reach() {
+        let mut input = GamepadInput::new();
+        for stick in [vec2(1.0, 1.0), vec2(-1.0, 0.0), vec2(0.3, -0.9)] {
+            let source = FakeGamepads(vec![(
+                0,
+                GamepadState {
+                    right_stick: stick,
+                    ..IDLE
+                },
+            )]);
+            let target = control(&mut input, &source).hand_target.map(Coord::as_f32);
+            assert!(target.len() <= 1.5 + 1e-5);
+            assert!(vec2::dot(target, stick) > 0.0);
+        }
+
+        // The hand is left to the other devices while the stick is released
+        let mut control = BodyControl {
+            hand_target: vec2(0.5, 0.5).map(r32),
+            ..default()
+        };
+        input.update(&FakeGamepads(vec![(0, IDLE)]), &mut control, r32(1.5));
+        assert_eq!(control.hand_target, vec2(0.5, 0.5).map(r32));
+    }
+
+    #[test]
+    fn triggers_hold() {
+        let mut input = GamepadInput::new();
+        assert!(!control(&mut input, &FakeGamepads(vec![(0, IDLE)])).hold);
+        for (left_trigger, right_trigger) in [(0.8, 0.0), (0.0, 0.8)] {
+            let source = FakeGamepads(vec![(
+                0,
+                GamepadState {
+                    left_trigger,
+                    right_trigger,
+                    ..IDLE
+                },
+            )]);
+            assert!(control(&mut input, &source).hold);
+        }
+        let source = FakeGamepads(vec![(
+            0,
+            GamepadState {
+                right_trigger: 0.3,
+                ..IDLE
+            },
+        )]);
+        assert!(!control(&mut input, &source).hold);
+    }
+
+    #[test]
+    fn hot_plug() {
+        let mut input = GamepadInput::new();
+        control(&mut input, &FakeGamepads(Vec::new()));
+        assert_eq!(input.active, None);
+
+        let jumping = GamepadState { jump: true, ..IDLE };
+        assert!(control(&mut input, &FakeGamepads(vec![(3, jumping)])).jump);
+        assert_eq!(input.active, Some(3));
+
+        // The active gamepad is kept when another one is connected
+        let both = FakeGamepads(vec![(1, IDLE), (3, jumping)]);
+        assert!(control(&mut input, &both).jump);
+        assert_eq!(input.active, Some(3));
+
+        // The other one is picked up once the active one is disconnected
+        assert!(!control(&mut input, &FakeGamepads(vec![(1, IDLE)])).jump);
+        assert_eq!(input.active, Some(1));
+
+        control(&mut input, &FakeGamepads(Vec::new()));
+        assert_eq!(input.active, None);
+    }
+}
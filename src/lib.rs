@@ -0,0 +1,35 @@
+//! Everything of the game but the command line entry point, so that the simulation can also be
+//! driven from other code, such as the training code through the [`env`].
+
+pub mod assets;
+mod audio;
+pub mod benchmark;
+pub mod bot;
+pub mod check;
+pub mod control;
+mod editor;
+pub mod env;
+pub mod formats;
+mod game;
+mod input;
+pub mod logic;
+pub mod menu;
+pub mod model;
+pub mod physics;
+mod random;
+mod render;
+mod settings;
+
+use assets::*;
+use audio::Audio;
+use control::*;
+use game::*;
+use input::*;
+use menu::*;
+use model::*;
+use physics::*;
+use random::Lcg;
+use render::Render;
+use settings::Settings;
+
+use geng::prelude::*;
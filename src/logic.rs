@@ -1,44 +1,151 @@
 use super::*;
 
 mod collision;
+mod script;
+#[cfg(test)]
+mod tests;
+mod wiring;
 
-const GRAVITY: vec2<f32> = vec2(0.0, -9.8);
+pub const GRAVITY: vec2<f32> = vec2(0.0, -9.8);
 
 const GROUND_ACCELERATION: f32 = 30.0;
 const AIR_ACCELERATION: f32 = 5.0;
+/// Speed given to the body along the ground normal by a jump, before the arm adds to it.
+pub const JUMP_SPEED: f32 = 5.0;
+/// Multiplier of the arm impulse added to the jumps.
+const ARM_JUMP_BOOST: f32 = 4.0;
+/// Surfaces with the normal closer than this angle in radians to the vertical are ground.
+pub const GROUND_ANGLE: f32 = 0.5;
+
+pub const CHECKPOINT_RADIUS: f32 = 1.5;
+pub const COLLECTIBLE_RADIUS: f32 = 0.3;
+
+/// Returns the velocity a jump off the ground with the normal adds to the body,
+/// given the impulse of the swinging arm.
+pub fn jump_velocity(normal: vec2<Coord>, arm_impulse: vec2<Coord>, mass: Mass) -> vec2<Coord> {
+    normal * r32(JUMP_SPEED) + arm_impulse * r32(ARM_JUMP_BOOST) / mass
+}
+
+/// How long the death animation plays before the player is respawned.
+pub const DEATH_DURATION: f32 = 0.8;
+/// Extra distance at which spikes kill, since collisions keep the body just outside of them.
+const SPIKES_TOLERANCE: f32 = 0.05;
 
 pub struct Logic<'a> {
     pub model: &'a mut Model,
     pub delta_time: Time,
     pub player_control: &'a mut BodyControl,
+    /// Events that happened during the step so far.
+    pub events: Vec<Event>,
 }
 
 impl Model {
-    pub fn update(&mut self, player_control: &mut BodyControl, delta_time: Time) {
+    /// Advances the simulation and returns what happened during the step.
+    pub fn update(&mut self, player_control: &mut BodyControl, delta_time: Time) -> Vec<Event> {
         let logic = Logic {
             model: self,
             delta_time,
             player_control,
+            events: Vec::new(),
         };
-        logic.process();
+        logic.process()
     }
 }
 
 impl<'a> Logic<'a> {
-    pub fn process(mut self) {
+    pub fn process(mut self) -> Vec<Event> {
+        self.model.time += self.delta_time;
+        if self.model.death.is_some() {
+            self.death();
+            self.doors();
+            self.triggers();
+            self.run();
+            self.scripts();
+            return self.events;
+        }
+
         self.gravity();
         self.player_control();
+        self.ropes();
+        self.props();
+        self.doors();
         self.collisions();
+        self.hazards();
+        self.switches();
+        self.collectibles();
+        self.checkpoints();
+        self.triggers();
+        self.run();
+        self.scripts();
+        self.events
+    }
+
+    fn death(&mut self) {
+        if let Some(death) = &mut self.model.death {
+            death.time += self.delta_time;
+            if death.time > Time::new(DEATH_DURATION) {
+                self.model.restart();
+            }
+        }
+    }
+
+    fn hazards(&mut self) {
+        let model = &mut *self.model;
+        let center = &model.player.center;
+        let area = Aabb2::point(center.position)
+            .extend_uniform(center.radius + Coord::new(SPIKES_TOLERANCE));
+        let spikes = model
+            .surface_grid
+            .surfaces_in(&model.level.surfaces, area)
+            .any(|(_, surface)| {
+                surface.flags.spikes
+                    && surface.delta_to(center.position).len()
+                        < center.radius + Coord::new(SPIKES_TOLERANCE)
+            });
+        let hazard = model
+            .level
+            .hazards
+            .iter()
+            .any(|hazard| hazard.touches(center.position, center.radius, model.time));
+        if spikes || hazard {
+            let position = center.position;
+            model.kill();
+            self.events.push(Event::Died { position });
+        }
     }
 
     fn player_control(&mut self) {
         let control = self.player_control.verify(&self.model.player);
         *self.player_control = control.into();
+        let was_holding = self.model.player.holding_to.is_some();
         if self.player_control.hold {
-            self.model.player.try_holding(&self.model.level.surfaces);
+            let model = &mut *self.model;
+            model.player.try_holding(
+                &model.level.surfaces,
+                &model.surface_grid,
+                &model.level.bars,
+                &model.ropes,
+                &model.props,
+            );
         } else {
             self.model.player.holding_to = None;
         }
+        let player = &self.model.player;
+        let impact = player.center.velocity.len();
+        let [_, _, hand] = player.arm.get_skeleton(&player.center);
+        match (was_holding, player.holding_to) {
+            (false, Some(hold)) => self.events.push(Event::HoldStarted {
+                hold,
+                position: hand.position,
+                impact,
+            }),
+            (true, None) => self.events.push(Event::HoldEnded {
+                reason: HoldEndReason::Released,
+                position: hand.position,
+                impact,
+            }),
+            _ => {}
+        }
 
         let player = &mut self.model.player;
         // Record
@@ -57,6 +164,9 @@ impl<'a> Logic<'a> {
             None => (vec2(Coord::ONE, Coord::ZERO), AIR_ACCELERATION),
         };
         let control = BodyControl::from(control);
+        if control.move_speed != Coord::ZERO {
+            player.facing_left = control.move_speed < Coord::ZERO;
+        }
         let target_speed = control.move_speed * stats.move_speed;
         let delta_speed = target_speed - player.center.velocity.x;
         player.center.velocity +=
@@ -65,13 +175,19 @@ impl<'a> Logic<'a> {
         // Jump
         if let Some(normal) = player.ground_normal.filter(|_| control.jump) {
             player.center.velocity +=
-                normal * r32(5.0) + player.arm.impulse() * r32(4.0) / player.center.mass;
+                jump_velocity(normal, player.arm.impulse(), player.center.mass);
+            self.events.push(Event::Jumped {
+                position: player.center.position,
+                normal,
+            });
         }
 
         // Movement
         player.center.movement(self.delta_time);
         let relative_target = control.hand_target;
-        let hold = player.holding_to.map(|pos| pos - player.center.position);
+        let hold = player
+            .hold_position(&self.model.ropes)
+            .map(|pos| pos - player.center.position);
         let (impulse, release) = player.arm.control(
             relative_target,
             hold,
@@ -81,12 +197,102 @@ impl<'a> Logic<'a> {
         player.center.velocity -= impulse / player.center.mass;
         if release {
             player.holding_to = None;
-        } else if let Some(hold) = hold {
+            let [_, _, hand] = player.arm.get_skeleton(&player.center);
+            self.events.push(Event::HoldEnded {
+                reason: HoldEndReason::Broken,
+                position: hand.position,
+                impact: player.center.velocity.len(),
+            });
+        } else if let Some(Hold::Point(position)) = player.holding_to {
+            // Holding onto ropes is resolved together with the rope simulation
+            let hold = position - player.center.position;
             let reach = player.arm.max_reach();
             if hold.len() > reach {
-                player.center.position =
-                    player.holding_to.unwrap() - hold.normalize_or_zero() * reach;
+                player.center.position = position - hold.normalize_or_zero() * reach;
+            }
+        }
+    }
+
+    fn ropes(&mut self) {
+        let model = &mut *self.model;
+        let player = &mut model.player;
+        let reach = player.arm.max_reach();
+        for (index, rope) in model.ropes.iter_mut().enumerate() {
+            let holding = match player.holding_to {
+                Some(Hold::Rope { rope, point }) if rope == index => {
+                    Some((point, &mut player.center, reach))
+                }
+                _ => None,
+            };
+            rope.update(GRAVITY.map(Coord::new), holding, self.delta_time);
+        }
+    }
+
+    fn props(&mut self) {
+        let model = &mut *self.model;
+        let gravity = GRAVITY.map(Coord::new) * self.delta_time;
+        for prop in &mut model.props {
+            prop.point.velocity += gravity;
+            prop.point.movement(self.delta_time);
+        }
+
+        // The carried prop follows the hand and weighs the body down
+        let player = &mut model.player;
+        if let Some(Hold::Prop { prop, offset }) = player.holding_to {
+            if let Some(prop) = model.props.get_mut(prop) {
+                let [_, _, hand] = player.arm.get_skeleton(&player.center);
+                prop.point.position = hand.position - offset;
+                prop.point.velocity = player.center.velocity;
+                player.center.velocity += gravity * prop.point.mass / player.center.mass;
+            }
+        }
+    }
+
+    fn collectibles(&mut self) {
+        let model = &mut *self.model;
+        let body = &model.player.center;
+        let [_, _, hand] = model.player.arm.get_skeleton(body);
+        let touches = |point: &PhysicsPoint, position: Position| {
+            (point.position - position).len() < point.radius + Coord::new(COLLECTIBLE_RADIUS)
+        };
+        for (index, item) in model.level.collectibles.iter().enumerate() {
+            if model.run.is_collected(index) {
+                continue;
+            }
+            if touches(&hand, item.position) || !item.hand_only && touches(body, item.position) {
+                model.run.collected.push(index);
+                self.events.push(Event::Collected { index });
+            }
+        }
+    }
+
+    fn checkpoints(&mut self) {
+        let position = self.model.player.center.position;
+        if let Some(index) = self.model.level.checkpoints.iter().position(|checkpoint| {
+            (checkpoint.position - position).len() < Coord::new(CHECKPOINT_RADIUS)
+        }) {
+            if self.model.checkpoint != Some(index) {
+                self.events.push(Event::CheckpointReached { index });
+            }
+            self.model.checkpoint = Some(index);
+            self.model.run.reach_checkpoint(index);
+        }
+    }
+
+    fn run(&mut self) {
+        let model = &mut *self.model;
+        model.run.update(&model.player, self.delta_time);
+        if model
+            .level
+            .finish
+            .is_some_and(|finish| finish.contains(model.player.center.position))
+        {
+            if !model.run.is_finished() {
+                self.events.push(Event::Finished {
+                    time: model.run.time,
+                });
             }
+            model.run.finish();
         }
     }
 
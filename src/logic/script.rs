@@ -0,0 +1,154 @@
+use super::*;
+
+impl Logic<'_> {
+    /// Reports the player entering and leaving the trigger volumes.
+    pub fn triggers(&mut self) {
+        let model = &mut *self.model;
+        let position = model.player.center.position;
+        for (index, volume) in model.level.triggers.iter().enumerate() {
+            let inside = model.death.is_none() && volume.area.contains(position);
+            let Some(was_inside) = model.script.inside.get_mut(index) else {
+                continue;
+            };
+            if inside != *was_inside {
+                *was_inside = inside;
+                self.events.push(if inside {
+                    Event::TriggerEntered { volume: index }
+                } else {
+                    Event::TriggerLeft { volume: index }
+                });
+            }
+        }
+    }
+
+    /// Runs the script handlers for the events of the step and advances the surface movements.
+    pub fn scripts(&mut self) {
+        let model = &mut *self.model;
+
+        let mut actions = Vec::new();
+        if !model.level.script.is_empty() {
+            for call in self
+                .events
+                .iter()
+                .filter_map(|event| ScriptCall::new(event, &model.level))
+            {
+                actions.extend(model.level.script.run(&call, &mut model.script.fired));
+            }
+        }
+        for action in actions {
+            model.apply_script_action(action);
+        }
+
+        let delta_time = self.delta_time;
+        let state = &mut model.script;
+        for movement in &mut state.moves {
+            let time = delta_time.min(movement.time_left);
+            movement.time_left -= time;
+            let offset = movement.velocity * time;
+            for &index in &movement.surfaces {
+                change_surface(
+                    &mut model.level.surfaces,
+                    &mut model.surface_grid,
+                    index,
+                    |surface| {
+                        surface.p1 += offset;
+                        surface.p2 += offset;
+                    },
+                );
+            }
+        }
+        state
+            .moves
+            .retain(|movement| movement.time_left > Time::ZERO);
+    }
+}
+
+/// Changes the surface and keeps the grid over the enabled surfaces up to date.
+fn change_surface(
+    surfaces: &mut [Surface],
+    grid: &mut SurfaceGrid,
+    index: usize,
+    change: impl FnOnce(&mut Surface),
+) {
+    let surface = &mut surfaces[index];
+    if !surface.flags.disabled {
+        grid.remove(index, surface);
+    }
+    change(surface);
+    if !surface.flags.disabled {
+        grid.insert(index, surface);
+    }
+}
+
+impl Model {
+    /// Applies the action of the level script to the surfaces with the tag.
+    fn apply_script_action(&mut self, action: ScriptAction) {
+        let tag = match &action {
+            ScriptAction::Enable(tag)
+            | ScriptAction::Disable(tag)
+            | ScriptAction::Toggle(tag)
+            | ScriptAction::Move { tag, .. } => tag,
+        };
+        let surfaces: Vec<usize> = self
+            .level
+            .surfaces
+            .iter()
+            .enumerate()
+            .filter(|(_, surface)| surface.tag.as_ref() == Some(tag))
+            .map(|(index, _)| index)
+            .collect();
+        if surfaces.is_empty() {
+            log::warn!("The script refers to {tag:?}, but no surface has this tag");
+            return;
+        }
+        for &index in &surfaces {
+            self.script
+                .original
+                .entry(index)
+                .or_insert_with(|| self.level.surfaces[index].clone());
+        }
+
+        match action {
+            ScriptAction::Enable(_) | ScriptAction::Disable(_) | ScriptAction::Toggle(_) => {
+                for &index in &surfaces {
+                    change_surface(
+                        &mut self.level.surfaces,
+                        &mut self.surface_grid,
+                        index,
+                        |surface| {
+                            let disabled = &mut surface.flags.disabled;
+                            *disabled = match action {
+                                ScriptAction::Enable(_) => false,
+                                ScriptAction::Disable(_) => true,
+                                _ => !*disabled,
+                            };
+                        },
+                    );
+                }
+            }
+            ScriptAction::Move {
+                offset, duration, ..
+            } => {
+                if duration > Time::ZERO {
+                    self.script.moves.push(SurfaceMove {
+                        surfaces,
+                        velocity: offset / duration,
+                        time_left: duration,
+                    });
+                } else {
+                    for index in surfaces {
+                        change_surface(
+                            &mut self.level.surfaces,
+                            &mut self.surface_grid,
+                            index,
+                            |surface| {
+                                surface.p1 += offset;
+                                surface.p2 += offset;
+                            },
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
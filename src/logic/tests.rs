@@ -0,0 +1,318 @@
+use super::*;
+
+/// Frames simulated per second, the same as the environment steps.
+const FPS: usize = 60;
+const RECORD_EVERY: usize = 10;
+
+/// Levels shipped in the assets, which the scripted runs go through.
+const LEVELS: [&str; 1] = ["new_level"];
+
+/// A scripted run: the control at each moment of time, and the duration of the run.
+type ScriptedRun = (&'static str, fn(f32) -> BodyControl, f32);
+
+const SCRIPTS: [ScriptedRun; 4] = [
+    ("idle", |_| BodyControl::default(), 3.0),
+    ("walk", walk, 4.0),
+    ("run_and_jump", run_and_jump, 5.0),
+    ("jump_reaching_up", jump_reaching_up, 4.0),
+];
+
+fn walk(_time: f32) -> BodyControl {
+    BodyControl {
+        move_speed: Coord::ONE,
+        target_height: Coord::ONE,
+        ..default()
+    }
+}
+
+fn run_and_jump(time: f32) -> BodyControl {
+    let swing = (time * 2.0 * f32::PI).sin() * 1.5;
+    BodyControl {
+        hand_target: vec2(swing, -0.5).map(r32),
+        move_speed: Coord::ONE,
+        target_height: Coord::ONE,
+        jump: (2.0..2.1).contains(&time),
+        ..default()
+    }
+}
+
+fn jump_reaching_up(time: f32) -> BodyControl {
+    BodyControl {
+        hand_target: vec2(0.0, 1.5).map(r32),
+        target_height: Coord::ONE,
+        hold: true,
+        jump: (0.5..0.6).contains(&time),
+        ..default()
+    }
+}
+
+/// Loads the copy of the level kept with the tests, the editor saves over the one in the assets.
+fn load_level(name: &str) -> Level {
+    let levels = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/levels");
+    Level::load_full(levels.join(format!("{name}.json")))
+        .unwrap_or_else(|| panic!("Failed to load the level {name}"))
+}
+
+/// Runs the script through the level and describes the trajectory, one line per record.
+fn trajectory(level: &Level, control: fn(f32) -> BodyControl, duration: f32) -> String {
+    let mut model = Model::new(level.clone());
+    let delta_time = Time::new(1.0 / FPS as f32);
+    let mut lines = Vec::new();
+    for frame in 0..(duration * FPS as f32) as usize {
+        let time = frame as f32 / FPS as f32;
+        let events = model.update(&mut control(time), delta_time);
+        let died = events
+            .iter()
+            .any(|event| matches!(event, Event::Died { .. }));
+        if died || frame % RECORD_EVERY == 0 {
+            let body = &model.player;
+            let [_, _, hand] = body.arm.get_skeleton(&body.center);
+            let (position, velocity) = (body.center.position, body.center.velocity);
+            let hand = hand.position - position;
+            lines.push(format!(
+                "{:.3} pos ({:.3}, {:.3}) vel ({:.3}, {:.3}) hand ({:.3}, {:.3}){}{}{}",
+                time,
+                position.x,
+                position.y,
+                velocity.x,
+                velocity.y,
+                hand.x,
+                hand.y,
+                if body.ground_normal.is_some() {
+                    " grounded"
+                } else {
+                    ""
+                },
+                if body.holding_to.is_some() {
+                    " holding"
+                } else {
+                    ""
+                },
+                if died { " died" } else { "" },
+            ));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Compares the scripted runs with the recorded ones, so that any change to the physics shows up
+/// as a diff of the files in `tests/golden`. Run with `UPDATE_GOLDEN=1` to record them again.
+#[test]
+fn golden_trajectories() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut mismatches = Vec::new();
+    for name in LEVELS {
+        let level = load_level(name);
+        for (script, control, duration) in SCRIPTS {
+            let actual = trajectory(&level, control, duration);
+            let path = dir.join(format!("{name}_{script}.txt"));
+            if update {
+                std::fs::create_dir_all(&dir).unwrap();
+                std::fs::write(&path, actual).unwrap();
+                continue;
+            }
+            let expected = std::fs::read_to_string(&path)
+                .unwrap_or_else(|_| panic!("Missing {}, run with UPDATE_GOLDEN=1", path.display()));
+            if let Some((line, (expected, actual))) = expected
+                .lines()
+                .zip(actual.lines())
+                .enumerate()
+                .find(|(_, (expected, actual))| expected != actual)
+            {
+                mismatches.push(format!(
+                    "{}:{}\n  expected: {expected}\n    actual: {actual}",
+                    path.display(),
+                    line + 1
+                ));
+            } else if expected.lines().count() != actual.lines().count() {
+                mismatches.push(format!("{}: the length differs", path.display()));
+            }
+        }
+    }
+    assert!(
+        mismatches.is_empty(),
+        "The trajectories changed:\n{}",
+        mismatches.join("\n")
+    );
+}
+
+/// A floor to drop onto, with the spawn point above it and a bar within reach from the floor.
+fn floor_level() -> Level {
+    let mut level = Level::new();
+    level.surfaces = vec![Surface::new(
+        vec2(-20.0, 0.0).map(r32),
+        vec2(20.0, 0.0).map(r32),
+    )];
+    level.spawn_point = vec2(0.0, 3.0).map(r32);
+    level.bars.push(Bar {
+        p1: vec2(-2.0, 2.3).map(r32),
+        p2: vec2(2.0, 2.3).map(r32),
+    });
+    level
+}
+
+/// Simulates the duration with the same control and returns all the events.
+fn events(model: &mut Model, control: BodyControl, duration: f32) -> Vec<Event> {
+    let delta_time = Time::new(1.0 / FPS as f32);
+    (0..(duration * FPS as f32) as usize)
+        .flat_map(|_| model.update(&mut control.clone(), delta_time))
+        .collect()
+}
+
+fn is_up(normal: vec2<Coord>) -> bool {
+    (normal - vec2(Coord::ZERO, Coord::ONE)).len().as_f32() < 1e-3
+}
+
+#[test]
+fn scripted_drop_events() {
+    let mut model = Model::new(floor_level());
+    let stand = BodyControl {
+        target_height: Coord::ONE,
+        ..default()
+    };
+
+    // Dropping onto the floor
+    let drop = events(&mut model, stand, 1.0);
+    let landed: Vec<&Event> = drop
+        .iter()
+        .filter(|event| matches!(event, Event::Landed { .. }))
+        .collect();
+    assert_eq!(landed.len(), 1, "{drop:?}");
+    assert!(matches!(landed[0], &Event::Landed { normal, impact, .. }
+        if is_up(normal) && impact > Coord::ZERO));
+    assert!(drop.iter().any(|event| matches!(event, &Event::Collided {
+        obstacle: Obstacle::Surface(0),
+        normal,
+        ..
+    } if is_up(normal))));
+    assert!(!drop
+        .iter()
+        .any(|event| matches!(event, Event::Jumped { .. } | Event::LeftGround { .. })));
+
+    // Jumping off it
+    let mut jump = events(
+        &mut model,
+        BodyControl {
+            jump: true,
+            ..stand
+        },
+        1.0 / FPS as f32,
+    );
+    jump.extend(events(&mut model, stand, 2.0));
+    let order: Vec<&str> = jump
+        .iter()
+        .filter_map(|event| match event {
+            &Event::Jumped { normal, .. } if is_up(normal) => Some("jumped"),
+            Event::LeftGround { .. } => Some("left ground"),
+            Event::Landed { .. } => Some("landed"),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(order, ["jumped", "left ground", "landed"]);
+
+    // Holding onto the bar overhead and letting go
+    let reach = model.player.arm.max_reach();
+    let hold = BodyControl {
+        hand_target: vec2(Coord::ZERO, reach),
+        hold: true,
+        ..stand
+    };
+    let holding = events(&mut model, hold, 0.5);
+    assert!(holding.iter().any(|event| matches!(
+        event,
+        Event::HoldStarted {
+            hold: Hold::Point(_),
+            ..
+        }
+    )));
+    assert!(model.player.holding_to.is_some());
+    let release = events(&mut model, stand, 0.1);
+    assert!(release.iter().any(|event| matches!(
+        event,
+        Event::HoldEnded {
+            reason: HoldEndReason::Released,
+            ..
+        }
+    )));
+    assert!(!release
+        .iter()
+        .any(|event| matches!(event, Event::Died { .. })));
+}
+
+#[test]
+fn walking_into_a_door_collides() {
+    let mut level = floor_level();
+    level.spawn_point = vec2(0.0, 1.0).map(r32);
+    level.doors.push(Door {
+        area: Aabb2::from_corners(vec2(2.0, 0.0), vec2(3.0, 4.0)).map(r32),
+        offset: vec2(0.0, 4.0).map(r32),
+        speed: r32(1.0),
+    });
+    let mut model = Model::new(level);
+    let walk = BodyControl {
+        move_speed: Coord::ONE,
+        target_height: Coord::ONE,
+        ..default()
+    };
+    let events = events(&mut model, walk, 2.0);
+    assert!(events.iter().any(|event| matches!(event, &Event::Collided {
+        obstacle: Obstacle::Door(0),
+        normal,
+        impact,
+    } if normal.x < Coord::ZERO && impact > Coord::ZERO)));
+    // The door keeps the body on its side
+    assert!(model.player.center.position.x < r32(2.0));
+}
+
+#[test]
+fn script_keeps_the_surface_grid_up_to_date() {
+    let mut level = floor_level();
+    let tagged = |tag: &str, p1: (f32, f32), p2: (f32, f32), disabled: bool| {
+        let mut surface = Surface::new(vec2(p1.0, p1.1).map(r32), vec2(p2.0, p2.1).map(r32));
+        surface.tag = Some(tag.to_owned());
+        surface.flags.disabled = disabled;
+        surface
+    };
+    level.surfaces.extend([
+        tagged("bridge", (5.0, 2.0), (9.0, 2.0), false),
+        tagged("wall", (12.0, 0.0), (12.0, 5.0), true),
+        tagged("ledge", (-9.0, 2.0), (-5.0, 2.0), false),
+    ]);
+    level.script = Script::parse(
+        r#"fn on_land() {
+            if once("land") {
+                move("bridge", 3, 10, 0.5);
+                enable("wall");
+                disable("ledge");
+            }
+        }"#,
+    )
+    .unwrap();
+    let mut model = Model::new(level);
+
+    let stand = BodyControl {
+        target_height: Coord::ONE,
+        ..default()
+    };
+    let delta_time = Time::new(1.0 / FPS as f32);
+    for _ in 0..2 * FPS {
+        model.update(&mut stand.clone(), delta_time);
+        let rebuilt = SurfaceGrid::enabled(&model.level.surfaces);
+        for x in -6..=6 {
+            for y in -2..=6 {
+                let area = Aabb2::point(vec2(x as f32 * 4.0, y as f32 * 4.0))
+                    .extend_uniform(2.0)
+                    .map(r32);
+                assert_eq!(model.surface_grid.query(area), rebuilt.query(area));
+            }
+        }
+    }
+
+    let surfaces = &model.level.surfaces;
+    let moved = surfaces[1].p1 - vec2(8.0, 12.0).map(r32);
+    assert!(moved.len().as_f32() < 1e-4);
+    assert!(!surfaces[2].flags.disabled);
+    assert!(surfaces[3].flags.disabled);
+    assert!(model.script.moves.is_empty());
+}
@@ -0,0 +1,61 @@
+use super::*;
+
+impl Logic<'_> {
+    /// Updates the switches from what the player and the props are doing.
+    pub fn switches(&mut self) {
+        let model = &mut *self.model;
+        let player = &model.player;
+        let center = &player.center;
+        let [_, _, hand] = player.arm.get_skeleton(center);
+        let state = &mut model.wiring;
+        for (index, switch) in model.level.switches.iter().enumerate() {
+            let on = match switch.kind {
+                SwitchKind::Plate => {
+                    let area = switch.plate_area();
+                    let closest = vec2(
+                        center.position.x.clamp(area.min.x, area.max.x),
+                        center.position.y.clamp(area.min.y, area.max.y),
+                    );
+                    (closest - center.position).len() < center.radius
+                        || model.props.iter().any(|prop| prop.aabb().intersects(&area))
+                }
+                SwitchKind::Lever => {
+                    let pulling = self.player_control.hold
+                        && (hand.position - switch.position).len()
+                            < Coord::new(LEVER_RADIUS) + hand.radius;
+                    let was_pulling = std::mem::replace(&mut state.pulling[index], pulling);
+                    state.switches[index] ^ (pulling && !was_pulling)
+                }
+            };
+            if on != state.switches[index] {
+                state.switches[index] = on;
+                self.events.push(Event::SwitchChanged { switch: index, on });
+            }
+        }
+    }
+
+    /// Moves the doors towards the position given by their signal.
+    pub fn doors(&mut self) {
+        let model = &mut *self.model;
+        let signals = model.level.door_signals(&model.wiring.switches);
+        for (index, door) in model.level.doors.iter().enumerate() {
+            let target = if signals[index] {
+                Coord::ONE
+            } else {
+                Coord::ZERO
+            };
+            let length = door.offset.len();
+            let open = &mut model.wiring.doors[index];
+            if length == Coord::ZERO {
+                *open = target;
+                continue;
+            }
+            let step = door.speed * self.delta_time / length;
+            *open = if *open < target {
+                (*open + step).min(target)
+            } else {
+                (*open - step).max(target)
+            };
+        }
+    }
+}
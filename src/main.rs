@@ -1,33 +1,82 @@
-mod control;
-mod editor;
-mod game;
-mod logic;
-mod model;
-mod physics;
-mod render;
-
-use control::*;
-use game::*;
-use model::*;
-use physics::*;
-use render::Render;
+use parkour::{assets::Assets, benchmark, bot, check, formats, menu::MainMenu};
 
 use geng::prelude::*;
 
 #[derive(clap::Parser)]
 struct Opts {
+    /// Measure the surface queries on a generated level with this many surfaces and exit.
+    #[clap(long)]
+    benchmark: Option<usize>,
+    /// Check that the finish and the checkpoints of the level file, the bundled level by default,
+    /// can be reached and exit, with an error code if they cannot.
+    #[clap(long)]
+    check: Option<Option<std::path::PathBuf>>,
+    /// Let the bot play the level file without the window and exit,
+    /// with an error code if it does not reach the finish.
+    #[clap(long)]
+    bot: Option<std::path::PathBuf>,
+    /// Import the SVG file into the level and exit.
+    #[clap(long)]
+    import_svg: Option<std::path::PathBuf>,
+    /// Export the level to the SVG file and exit.
+    #[clap(long)]
+    export_svg: Option<std::path::PathBuf>,
+    /// Replace the level with the one imported from the Tiled map (TMX or JSON) and exit.
+    #[clap(long)]
+    import_tiled: Option<std::path::PathBuf>,
+    /// Number of world units per SVG unit.
+    #[clap(long)]
+    svg_scale: Option<f32>,
+    /// Maximum distance in world units between an SVG curve and its flattened segments.
+    #[clap(long)]
+    svg_tolerance: Option<f32>,
     #[clap(flatten)]
     geng: geng::CliArgs,
 }
 
-#[derive(geng::asset::Load)]
-pub struct Assets {}
-
 fn main() {
     logger::init();
     geng::setup_panic_handler();
 
     let opts: Opts = clap::Parser::parse();
+    if let Some(surfaces) = opts.benchmark {
+        benchmark::run(surfaces);
+        return;
+    }
+    if let Some(path) = &opts.check {
+        let path = path
+            .clone()
+            .unwrap_or_else(|| run_dir().join("assets").join("new_level.json"));
+        if !check::run(&path) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(path) = &opts.bot {
+        if !bot::run(path) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    let mut svg_options = formats::SvgOptions::default();
+    if let Some(scale) = opts.svg_scale {
+        svg_options.scale = scale;
+    }
+    if let Some(tolerance) = opts.svg_tolerance {
+        svg_options.tolerance = tolerance;
+    }
+    if let Some(path) = &opts.import_svg {
+        formats::import_svg_file(path, &svg_options).expect("Failed to import the SVG");
+        return;
+    }
+    if let Some(path) = &opts.import_tiled {
+        formats::import_tiled_file(path).expect("Failed to import the Tiled map");
+        return;
+    }
+    if let Some(path) = &opts.export_svg {
+        formats::export_svg_file(path, &svg_options).expect("Failed to export the SVG");
+        return;
+    }
 
     let mut options = geng::ContextOptions::default();
     options.window.title = "Untitled Parkour Game".to_owned();
@@ -40,7 +89,7 @@ fn main() {
                 .await
                 .expect("Failed to load assets");
         let assets = Rc::new(assets);
-        let state = Game::new(&geng, &assets);
+        let state = MainMenu::new(&geng, &assets);
         geng.run_state(state).await;
     });
 }
@@ -0,0 +1,181 @@
+use super::*;
+
+const ITEM_HEIGHT: f32 = 1.5;
+const TEXT_SIZE: f32 = 0.6;
+const TITLE_SIZE: f32 = 1.2;
+
+const ITEM_COLOR: Rgba<f32> = Rgba {
+    r: 0.6,
+    g: 0.6,
+    b: 0.6,
+    a: 1.0,
+};
+const SELECTED_COLOR: Rgba<f32> = Rgba::WHITE;
+
+/// What the player did to the menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuInput {
+    /// Item has been clicked or confirmed.
+    Activate(usize),
+    /// Item has been adjusted to the left (-1) or right (+1).
+    Adjust(usize, i32),
+}
+
+/// A vertical list of selectable text items, controlled with both keyboard and mouse.
+pub struct MenuList {
+    geng: Geng,
+    camera: Camera2d,
+    framebuffer_size: vec2<f32>,
+    pub selected: usize,
+}
+
+impl MenuList {
+    pub fn new(geng: &Geng) -> Self {
+        Self {
+            geng: geng.clone(),
+            camera: Camera2d {
+                center: vec2::ZERO,
+                rotation: Angle::ZERO,
+                fov: 20.0,
+            },
+            framebuffer_size: vec2(1.0, 1.0),
+            selected: 0,
+        }
+    }
+
+    fn item_position(&self, index: usize, items: usize) -> vec2<f32> {
+        let top = (items as f32 - 1.0) * ITEM_HEIGHT / 2.0 - ITEM_HEIGHT;
+        vec2(0.0, top - index as f32 * ITEM_HEIGHT)
+    }
+
+    fn hovered_item(&self, position: vec2<f32>, items: usize) -> Option<usize> {
+        let world_pos = self.camera.screen_to_world(self.framebuffer_size, position);
+        (0..items).find(|&index| {
+            (self.item_position(index, items).y - world_pos.y).abs() < ITEM_HEIGHT / 2.0
+        })
+    }
+
+    pub fn handle_event(&mut self, event: &geng::Event, items: usize) -> Option<MenuInput> {
+        use geng::Key;
+
+        if items == 0 {
+            return None;
+        }
+        self.selected = self.selected.min(items - 1);
+        match *event {
+            geng::Event::KeyPress { key } => match key {
+                Key::ArrowUp | Key::W => {
+                    self.selected = (self.selected + items - 1) % items;
+                    None
+                }
+                Key::ArrowDown | Key::S => {
+                    self.selected = (self.selected + 1) % items;
+                    None
+                }
+                Key::ArrowLeft | Key::A => Some(MenuInput::Adjust(self.selected, -1)),
+                Key::ArrowRight | Key::D => Some(MenuInput::Adjust(self.selected, 1)),
+                Key::Enter | Key::Space => Some(MenuInput::Activate(self.selected)),
+                _ => None,
+            },
+            geng::Event::CursorMove { position } => {
+                if let Some(index) = self.hovered_item(position.map(|x| x as f32), items) {
+                    self.selected = index;
+                }
+                None
+            }
+            geng::Event::MousePress {
+                button: geng::MouseButton::Left,
+            } => {
+                let position = self.geng.window().cursor_position()?;
+                self.hovered_item(position.map(|x| x as f32), items)
+                    .map(MenuInput::Activate)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn draw(&mut self, title: &str, items: &[String], framebuffer: &mut ugli::Framebuffer) {
+        self.framebuffer_size = framebuffer.size().map(|x| x as f32);
+        let font = self.geng.default_font();
+        let align = vec2::splat(geng::TextAlign::CENTER);
+
+        let title_pos = self.item_position(0, items.len()) + vec2(0.0, ITEM_HEIGHT * 1.5);
+        font.draw(
+            framebuffer,
+            &self.camera,
+            title,
+            align,
+            mat3::translate(title_pos) * mat3::scale_uniform(TITLE_SIZE),
+            Rgba::WHITE,
+        );
+
+        for (index, item) in items.iter().enumerate() {
+            let color = if index == self.selected {
+                SELECTED_COLOR
+            } else {
+                ITEM_COLOR
+            };
+            font.draw(
+                framebuffer,
+                &self.camera,
+                item,
+                align,
+                mat3::translate(self.item_position(index, items.len()))
+                    * mat3::scale_uniform(TEXT_SIZE),
+                color,
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MainMenuItem {
+    Play,
+    Editor,
+    Quit,
+}
+
+pub struct MainMenu {
+    geng: Geng,
+    assets: Rc<Assets>,
+    list: MenuList,
+    transition: Option<MainMenuItem>,
+}
+
+impl MainMenu {
+    const ITEMS: [MainMenuItem; 3] = [MainMenuItem::Play, MainMenuItem::Editor, MainMenuItem::Quit];
+
+    pub fn new(geng: &Geng, assets: &Rc<Assets>) -> Self {
+        geng.window().unlock_cursor();
+        Self {
+            geng: geng.clone(),
+            assets: assets.clone(),
+            list: MenuList::new(geng),
+            transition: None,
+        }
+    }
+}
+
+impl geng::State for MainMenu {
+    fn handle_event(&mut self, event: geng::Event) {
+        if let Some(MenuInput::Activate(index)) = self.list.handle_event(&event, Self::ITEMS.len())
+        {
+            self.transition = Some(Self::ITEMS[index]);
+        }
+    }
+
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        ugli::clear(framebuffer, Some(Rgba::BLACK), None, None);
+        let items = Self::ITEMS.map(|item| format!("{item:?}"));
+        self.list.draw("Untitled Parkour Game", &items, framebuffer);
+    }
+
+    fn transition(&mut self) -> Option<geng::state::Transition> {
+        let state: Box<dyn geng::State> = match self.transition.take()? {
+            MainMenuItem::Play => Box::new(Game::new(&self.geng, &self.assets)),
+            MainMenuItem::Editor => Box::new(crate::editor::Editor::new(&self.geng, &self.assets)),
+            MainMenuItem::Quit => return Some(geng::state::Transition::Pop),
+        };
+        Some(geng::state::Transition::Switch(state))
+    }
+}
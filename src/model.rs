@@ -1,8 +1,24 @@
 use super::*;
 
+mod chunks;
+mod decoration;
+mod event;
+mod ghost;
+mod hazard;
 mod level;
+mod results;
+mod script;
+mod wiring;
 
+pub use chunks::*;
+pub use decoration::*;
+pub use event::*;
+pub use ghost::*;
+pub use hazard::*;
 pub use level::*;
+pub use results::*;
+pub use script::*;
+pub use wiring::*;
 
 pub type Time = R32;
 pub type Coord = R32;
@@ -10,15 +26,74 @@ pub type Position = vec2<Coord>;
 pub type Mass = R32;
 
 pub struct Model {
+    pub time: Time,
     pub player: Body,
+    pub death: Option<Death>,
     pub level: Level,
+    /// Spatial index over the enabled level surfaces, rebuilt when the script changes them.
+    pub surface_grid: SurfaceGrid,
+    pub ropes: Vec<RopeChain>,
+    pub props: Vec<PropBody>,
+    /// Index of the last checkpoint reached by the player.
+    pub checkpoint: Option<usize>,
+    pub run: Run,
+    pub script: ScriptState,
+    pub wiring: WiringState,
 }
 
 impl Model {
     pub fn new(level: Level) -> Self {
         Self {
+            time: Time::ZERO,
             player: Body::new(level.spawn_point),
+            death: None,
+            surface_grid: SurfaceGrid::enabled(&level.surfaces),
+            ropes: level.ropes.iter().map(Rope::chain).collect(),
+            props: level.props.iter().map(Prop::body).collect(),
+            script: ScriptState {
+                inside: vec![false; level.triggers.len()],
+                ..default()
+            },
+            wiring: WiringState::new(&level),
             level,
+            checkpoint: None,
+            run: default(),
         }
     }
+
+    /// Returns the level as it was before the script changed it.
+    pub fn initial_level(&self) -> Level {
+        let mut level = self.level.clone();
+        for (&index, surface) in &self.script.original {
+            level.surfaces[index] = surface.clone();
+        }
+        level
+    }
+
+    pub fn respawn_point(&self) -> Position {
+        self.checkpoint
+            .and_then(|index| self.level.checkpoints.get(index))
+            .map(|checkpoint| checkpoint.position)
+            .unwrap_or(self.level.spawn_point)
+    }
+
+    /// Restarts the player from the last checkpoint.
+    pub fn restart(&mut self) {
+        self.player = Body::new(self.respawn_point());
+        self.ropes = self.level.ropes.iter().map(Rope::chain).collect();
+        self.props = self.level.props.iter().map(Prop::body).collect();
+        self.death = None;
+    }
+
+    /// Kills the player, who is going to be respawned after a short delay.
+    pub fn kill(&mut self) {
+        if self.death.is_some() {
+            return;
+        }
+        self.death = Some(Death {
+            position: self.player.center.position,
+            time: Time::ZERO,
+        });
+        self.run.deaths += 1;
+    }
 }
@@ -0,0 +1,242 @@
+use super::*;
+
+/// Side of a square chunk of the level.
+const CHUNK_SIZE: f32 = 32.0;
+/// Chunks at most this many chunks away from the focus are loaded.
+const LOAD_RADIUS: i64 = 2;
+/// Chunks further away than this are unloaded.
+/// It is bigger than the load radius, so that moving along the border does not reload chunks.
+const UNLOAD_RADIUS: i64 = 3;
+
+/// File in the chunks directory with the bounds of each of the chunks.
+const INDEX_FILE: &str = "index.json";
+
+pub type ChunkPos = (i64, i64);
+
+/// Streams the surfaces of a level, that are stored on the disk in chunks,
+/// in and out of the list of the active surfaces.
+#[derive(Debug, Clone)]
+pub struct ChunkStream {
+    dir: std::path::PathBuf,
+    loaded: HashSet<ChunkPos>,
+    /// Bounds of the surfaces of every chunk on the disk, kept in the index file
+    /// so that the extent of the level is known without reading all the chunks.
+    index: HashMap<ChunkPos, Aabb2<Coord>>,
+}
+
+impl ChunkStream {
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        let mut stream = Self {
+            dir: dir.into(),
+            loaded: HashSet::new(),
+            index: HashMap::new(),
+        };
+        stream.index = stream.read_index();
+        stream
+    }
+
+    /// Returns the bounds of all the surfaces stored in the chunks.
+    pub fn bounds(&self) -> Option<Aabb2<Coord>> {
+        Aabb2::points_bounding_box(self.index.values().flat_map(|bounds| bounds.corners()))
+    }
+
+    /// Returns the chunk the surface belongs to, determined by its middle point.
+    pub fn chunk_of(surface: &Surface) -> ChunkPos {
+        let middle = (surface.p1 + surface.p2) / Coord::new(2.0);
+        chunk_at(middle)
+    }
+
+    /// Loads the chunks around the focus and unloads the distant ones.
+    /// If `persist` is set, then the unloaded surfaces are written to the disk,
+    /// otherwise they are assumed to be unchanged.
+    /// Returns whether the list of surfaces has changed.
+    pub fn update(&mut self, surfaces: &mut Vec<Surface>, focus: Position, persist: bool) -> bool {
+        let center = chunk_at(focus);
+        let distance = |pos: ChunkPos| (pos.0 - center.0).abs().max((pos.1 - center.1).abs());
+        let mut changed = false;
+
+        let unload: Vec<ChunkPos> = self
+            .loaded
+            .iter()
+            .copied()
+            .filter(|&pos| distance(pos) > UNLOAD_RADIUS)
+            .collect();
+        for pos in unload {
+            self.loaded.remove(&pos);
+            let (chunk, rest) = std::mem::take(surfaces)
+                .into_iter()
+                .partition(|surface| Self::chunk_of(surface) == pos);
+            *surfaces = rest;
+            if persist {
+                self.write_chunk(pos, &chunk);
+            }
+            changed = true;
+        }
+
+        for x in center.0 - LOAD_RADIUS..=center.0 + LOAD_RADIUS {
+            for y in center.1 - LOAD_RADIUS..=center.1 + LOAD_RADIUS {
+                changed |= self.load_chunk((x, y), surfaces);
+            }
+        }
+        changed
+    }
+
+    /// Writes all the loaded chunks to the disk.
+    pub fn save(&mut self, surfaces: &mut Vec<Surface>) {
+        // Surfaces might have been added to the chunks that have not been loaded,
+        // so they have to be loaded to avoid overwriting their contents
+        let touched: HashSet<ChunkPos> = surfaces.iter().map(Self::chunk_of).collect();
+        for pos in touched {
+            self.load_chunk(pos, surfaces);
+        }
+
+        let mut chunks: HashMap<ChunkPos, Vec<Surface>> = HashMap::new();
+        for surface in surfaces.iter() {
+            chunks
+                .entry(Self::chunk_of(surface))
+                .or_default()
+                .push(surface.clone());
+        }
+        for pos in self.loaded.clone() {
+            self.write_chunk(pos, chunks.get(&pos).map_or(&[], |chunk| chunk));
+        }
+        log::info!("Level chunks saved");
+    }
+
+    /// Reads the surfaces of all the chunks stored on the disk.
+    pub fn read_all(&self) -> Vec<Surface> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            Vec::new()
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let Ok(entries) = std::fs::read_dir(&self.dir) else {
+                return Vec::new();
+            };
+            entries
+                .filter_map(|entry| {
+                    let name = entry.ok()?.file_name().into_string().ok()?;
+                    let (x, y) = name.strip_suffix(".json")?.split_once('_')?;
+                    Some((x.parse().ok()?, y.parse().ok()?))
+                })
+                .flat_map(|pos| self.read_chunk(pos))
+                .collect()
+        }
+    }
+
+    /// Deletes all the chunks from the disk.
+    pub fn clear(&mut self) {
+        self.loaded.clear();
+        self.index.clear();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if self.dir.exists() {
+                std::fs::remove_dir_all(&self.dir).expect("Failed to remove the level chunks");
+            }
+        }
+    }
+
+    /// Appends the surfaces of the chunk if it has not been loaded yet.
+    /// Returns whether the chunk had any surfaces.
+    fn load_chunk(&mut self, pos: ChunkPos, surfaces: &mut Vec<Surface>) -> bool {
+        if !self.loaded.insert(pos) {
+            return false;
+        }
+        let chunk = self.read_chunk(pos);
+        let changed = !chunk.is_empty();
+        surfaces.extend(chunk);
+        changed
+    }
+
+    fn chunk_path(&self, pos: ChunkPos) -> std::path::PathBuf {
+        self.dir.join(format!("{}_{}.json", pos.0, pos.1))
+    }
+
+    fn read_chunk(&self, pos: ChunkPos) -> Vec<Surface> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = pos;
+            Vec::new()
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let Ok(file) = std::fs::File::open(self.chunk_path(pos)) else {
+                return Vec::new();
+            };
+            match serde_json::from_reader(std::io::BufReader::new(file)) {
+                Ok(surfaces) => surfaces,
+                Err(err) => {
+                    log::error!("Failed to deserialize the level chunk {pos:?}: {err}");
+                    Vec::new()
+                }
+            }
+        }
+    }
+
+    fn write_chunk(&mut self, pos: ChunkPos, surfaces: &[Surface]) {
+        let bounds = Aabb2::points_bounding_box(
+            surfaces.iter().flat_map(|surface| [surface.p1, surface.p2]),
+        );
+        match bounds {
+            Some(bounds) => self.index.insert(pos, bounds),
+            None => self.index.remove(&pos),
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = self.chunk_path(pos);
+            if surfaces.is_empty() {
+                if path.exists() {
+                    std::fs::remove_file(path).expect("Failed to remove a level chunk");
+                }
+            } else {
+                std::fs::create_dir_all(&self.dir).expect("Failed to create the chunks directory");
+                serde_json::to_writer(
+                    std::fs::File::create(path).expect("Failed to create a file"),
+                    surfaces,
+                )
+                .expect("Failed to serialize the level chunk");
+            }
+            if self.dir.exists() {
+                let index: Vec<(ChunkPos, Aabb2<Coord>)> = self
+                    .index
+                    .iter()
+                    .map(|(&pos, &bounds)| (pos, bounds))
+                    .collect();
+                serde_json::to_writer(
+                    std::fs::File::create(self.dir.join(INDEX_FILE))
+                        .expect("Failed to create a file"),
+                    &index,
+                )
+                .expect("Failed to serialize the level chunk index");
+            }
+        }
+    }
+
+    fn read_index(&self) -> HashMap<ChunkPos, Aabb2<Coord>> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            HashMap::new()
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let Ok(file) = std::fs::File::open(self.dir.join(INDEX_FILE)) else {
+                return HashMap::new();
+            };
+            match serde_json::from_reader::<_, Vec<(ChunkPos, Aabb2<Coord>)>>(file) {
+                Ok(index) => index.into_iter().collect(),
+                Err(err) => {
+                    log::error!("Failed to deserialize the level chunk index: {err}");
+                    HashMap::new()
+                }
+            }
+        }
+    }
+}
+
+fn chunk_at(position: Position) -> ChunkPos {
+    (
+        (position.x.as_f32() / CHUNK_SIZE).floor() as i64,
+        (position.y.as_f32() / CHUNK_SIZE).floor() as i64,
+    )
+}
@@ -0,0 +1,50 @@
+use super::*;
+
+/// A non-colliding visual element of the level.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Decoration {
+    pub shape: DecorationShape,
+    /// Whether the decoration is drawn over the gameplay layer instead of behind it.
+    #[serde(default)]
+    pub foreground: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DecorationShape {
+    /// A sprite from the assets stretched over the area.
+    Sprite { sprite: String, area: Aabb2<Coord> },
+    /// A convex polygon filled with a solid color.
+    Polygon {
+        vertices: Vec<vec2<Coord>>,
+        color: Rgba<f32>,
+    },
+}
+
+/// A sprite repeated horizontally behind the level.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackgroundLayer {
+    pub sprite: String,
+    /// How much the layer follows the camera:
+    /// 0 stays still on the screen, 1 moves together with the level.
+    pub parallax: f32,
+    /// Area of one repetition of the sprite when the camera is at the origin.
+    pub area: Aabb2<f32>,
+}
+
+impl Decoration {
+    pub fn bounds(&self) -> Aabb2<Coord> {
+        match &self.shape {
+            DecorationShape::Sprite { area, .. } => *area,
+            DecorationShape::Polygon { vertices, .. } => {
+                Aabb2::points_bounding_box(vertices.iter().copied()).unwrap_or(Aabb2::ZERO)
+            }
+        }
+    }
+}
+
+impl BackgroundLayer {
+    /// Returns the area of the repetition at the origin for the given camera position.
+    pub fn area_at(&self, camera: vec2<f32>) -> Aabb2<f32> {
+        self.area.translate(camera * (1.0 - self.parallax))
+    }
+}
@@ -0,0 +1,77 @@
+use super::*;
+
+/// Something noteworthy that happened during a simulation step.
+/// Impacts are speeds in world units per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    /// The body touched the ground after being in the air.
+    Landed {
+        position: Position,
+        normal: vec2<Coord>,
+        impact: Coord,
+    },
+    LeftGround {
+        position: Position,
+        normal: vec2<Coord>,
+        impact: Coord,
+    },
+    /// The body was pushed out of the obstacle.
+    Collided {
+        obstacle: Obstacle,
+        normal: vec2<Coord>,
+        impact: Coord,
+    },
+    /// The body jumped off the ground with the normal.
+    Jumped {
+        position: Position,
+        normal: vec2<Coord>,
+    },
+    HoldStarted {
+        hold: Hold,
+        position: Position,
+        impact: Coord,
+    },
+    HoldEnded {
+        reason: HoldEndReason,
+        position: Position,
+        impact: Coord,
+    },
+    /// The player entered the checkpoint, which became the respawn point.
+    CheckpointReached {
+        index: usize,
+    },
+    Collected {
+        index: usize,
+    },
+    Died {
+        position: Position,
+    },
+    SwitchChanged {
+        switch: usize,
+        on: bool,
+    },
+    TriggerEntered {
+        volume: usize,
+    },
+    TriggerLeft {
+        volume: usize,
+    },
+    /// The player reached the finish, the time is the final time of the run.
+    Finished {
+        time: Time,
+    },
+}
+
+/// Something the body collides with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Obstacle {
+    Surface(usize),
+    Door(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoldEndReason {
+    Released,
+    /// The arm could not withstand the force.
+    Broken,
+}
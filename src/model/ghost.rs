@@ -0,0 +1,207 @@
+use super::*;
+
+/// How often the pose of the player is recorded.
+const RECORD_INTERVAL: f32 = 1.0 / 30.0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PosePoint {
+    pub position: Position,
+    pub radius: Coord,
+}
+
+/// Positions of the body and the arm joints in world coordinates.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BodyPose {
+    pub body: PosePoint,
+    pub shoulder: PosePoint,
+    pub elbow: PosePoint,
+    pub hand: PosePoint,
+    #[serde(default)]
+    pub facing_left: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GhostFrame {
+    pub time: Time,
+    pub pose: BodyPose,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GhostRun {
+    /// Time it took to reach the finish, if it has been reached.
+    pub finish_time: Option<Time>,
+    /// Indices of the checkpoints in the order they have been reached and the time of reaching.
+    pub splits: Vec<(usize, Time)>,
+    pub frames: Vec<GhostFrame>,
+}
+
+/// State of the current attempt at the level.
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub time: Time,
+    pub deaths: usize,
+    pub collected: Vec<usize>,
+    next_record: Time,
+    pub recording: GhostRun,
+}
+
+impl PosePoint {
+    fn new(point: &PhysicsPoint) -> Self {
+        Self {
+            position: point.position,
+            radius: point.radius,
+        }
+    }
+
+    fn lerp(self, other: Self, t: Coord) -> Self {
+        Self {
+            position: self.position + (other.position - self.position) * t,
+            radius: self.radius + (other.radius - self.radius) * t,
+        }
+    }
+}
+
+impl BodyPose {
+    pub fn from_body(body: &Body) -> Self {
+        let [shoulder, elbow, hand] = body.arm.get_skeleton(&body.center);
+        Self {
+            body: PosePoint::new(&body.center),
+            shoulder: PosePoint::new(&shoulder),
+            elbow: PosePoint::new(&elbow),
+            hand: PosePoint::new(&hand),
+            facing_left: body.facing_left,
+        }
+    }
+
+    fn lerp(&self, other: &Self, t: Coord) -> Self {
+        Self {
+            body: self.body.lerp(other.body, t),
+            shoulder: self.shoulder.lerp(other.shoulder, t),
+            elbow: self.elbow.lerp(other.elbow, t),
+            hand: self.hand.lerp(other.hand, t),
+            facing_left: if t < Coord::new(0.5) {
+                self.facing_left
+            } else {
+                other.facing_left
+            },
+        }
+    }
+}
+
+impl GhostRun {
+    /// Returns the interpolated pose at the given time since the start of the run.
+    pub fn pose_at(&self, time: Time) -> Option<BodyPose> {
+        let index = self.frames.partition_point(|frame| frame.time <= time);
+        match (
+            self.frames.get(index.checked_sub(1)?),
+            self.frames.get(index),
+        ) {
+            (Some(prev), Some(next)) => {
+                let t = (time - prev.time) / (next.time - prev.time).max(Time::new(1e-5));
+                Some(prev.pose.lerp(&next.pose, t))
+            }
+            (Some(last), None) => Some(last.pose),
+            _ => None,
+        }
+    }
+
+    /// Returns the time the checkpoint has been reached at.
+    pub fn split(&self, checkpoint: usize) -> Option<Time> {
+        self.splits
+            .iter()
+            .find(|(index, _)| *index == checkpoint)
+            .map(|(_, time)| *time)
+    }
+
+    /// Checks whether this run has reached the finish faster than the other one.
+    pub fn is_better_than(&self, other: Option<&Self>) -> bool {
+        match (self.finish_time, other.and_then(|other| other.finish_time)) {
+            (Some(time), Some(best)) => time < best,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            serde_json::to_writer(
+                std::fs::File::create(path).expect("Failed to create a file"),
+                &self,
+            )
+            .expect("Failed to serialize the ghost");
+            log::info!("Ghost saved");
+        }
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Option<Self> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            None
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let file = std::fs::File::open(path).ok()?;
+            match serde_json::from_reader(std::io::BufReader::new(file)) {
+                Ok(ghost) => Some(ghost),
+                Err(err) => {
+                    log::error!("Failed to deserialize the ghost: {err}");
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl Run {
+    pub fn new() -> Self {
+        Self {
+            time: Time::ZERO,
+            deaths: 0,
+            collected: Vec::new(),
+            next_record: Time::ZERO,
+            recording: default(),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.recording.finish_time.is_some()
+    }
+
+    /// Advances the timer and records the pose of the body.
+    pub fn update(&mut self, body: &Body, delta_time: Time) {
+        if self.is_finished() {
+            return;
+        }
+        self.time += delta_time;
+        if self.time >= self.next_record {
+            self.next_record = self.time + Time::new(RECORD_INTERVAL);
+            self.recording.frames.push(GhostFrame {
+                time: self.time,
+                pose: BodyPose::from_body(body),
+            });
+        }
+    }
+
+    pub fn is_collected(&self, item: usize) -> bool {
+        self.collected.contains(&item)
+    }
+
+    pub fn reach_checkpoint(&mut self, checkpoint: usize) {
+        if self.recording.split(checkpoint).is_none() {
+            self.recording.splits.push((checkpoint, self.time));
+        }
+    }
+
+    pub fn finish(&mut self) {
+        if !self.is_finished() {
+            self.recording.finish_time = Some(self.time);
+        }
+    }
+}
+
+impl Default for Run {
+    fn default() -> Self {
+        Self::new()
+    }
+}
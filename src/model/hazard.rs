@@ -0,0 +1,55 @@
+use super::*;
+
+/// A part of the level that kills the player on contact.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Hazard {
+    /// A static area.
+    KillZone(Aabb2<Coord>),
+    /// A block moving back and forth.
+    Crusher(Crusher),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Crusher {
+    /// The area at the start of the cycle.
+    pub area: Aabb2<Coord>,
+    /// How far the crusher travels from its starting area.
+    pub offset: vec2<Coord>,
+    /// Duration of the full cycle there and back.
+    pub period: Time,
+}
+
+/// The player has died and is waiting to be respawned.
+#[derive(Debug, Clone, Copy)]
+pub struct Death {
+    pub position: Position,
+    /// Time since the death.
+    pub time: Time,
+}
+
+impl Hazard {
+    /// Returns the area covered by the hazard at the given time.
+    pub fn area(&self, time: Time) -> Aabb2<Coord> {
+        match self {
+            Self::KillZone(area) => *area,
+            Self::Crusher(crusher) => crusher.area(time),
+        }
+    }
+
+    pub fn touches(&self, position: Position, radius: Coord, time: Time) -> bool {
+        let area = self.area(time);
+        let closest = vec2(
+            position.x.clamp(area.min.x, area.max.x),
+            position.y.clamp(area.min.y, area.max.y),
+        );
+        (closest - position).len() < radius
+    }
+}
+
+impl Crusher {
+    pub fn area(&self, time: Time) -> Aabb2<Coord> {
+        let phase = (time / self.period.max(Time::new(0.1))).as_f32() * f32::PI * 2.0;
+        let t = Coord::new(0.5 - 0.5 * phase.cos());
+        self.area.translate(self.offset * t)
+    }
+}
@@ -3,7 +3,103 @@ use super::*;
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Level {
     pub spawn_point: vec2<Coord>,
+    /// Empty if the level is chunked.
     pub surfaces: Vec<Surface>,
+    /// Whether the surfaces are stored in the chunks next to the level file instead of inline,
+    /// which is meant for the levels too big to be loaded at once.
+    #[serde(default)]
+    pub chunked: bool,
+    /// Bounds of the surfaces in the chunks, since only some of them are loaded at a time.
+    #[serde(default)]
+    pub chunk_bounds: Option<Aabb2<Coord>>,
+    #[serde(default)]
+    pub checkpoints: Vec<Checkpoint>,
+    #[serde(default)]
+    pub hazards: Vec<Hazard>,
+    #[serde(default)]
+    pub collectibles: Vec<Collectible>,
+    #[serde(default)]
+    pub bars: Vec<Bar>,
+    #[serde(default)]
+    pub ropes: Vec<Rope>,
+    #[serde(default)]
+    pub props: Vec<Prop>,
+    #[serde(default)]
+    pub camera_zones: Vec<CameraZone>,
+    #[serde(default)]
+    pub decorations: Vec<Decoration>,
+    /// Drawn behind everything else, from the farthest to the nearest.
+    #[serde(default)]
+    pub backgrounds: Vec<BackgroundLayer>,
+    /// Reaching this area completes the level.
+    #[serde(default)]
+    pub finish: Option<Aabb2<Coord>>,
+    #[serde(default)]
+    pub switches: Vec<Switch>,
+    #[serde(default)]
+    pub gates: Vec<Gate>,
+    #[serde(default)]
+    pub doors: Vec<Door>,
+    /// Connections of the signal graph between the switches, gates and doors.
+    #[serde(default)]
+    pub wires: Vec<Wire>,
+    /// Areas that run the script handlers when the player enters or leaves them.
+    #[serde(default)]
+    pub triggers: Vec<TriggerVolume>,
+    /// Loaded from the `.rhai` file next to the level, which is edited by hand.
+    #[serde(skip)]
+    pub script: Script,
+}
+
+/// A point the player restarts from after reaching it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub position: vec2<Coord>,
+    /// Name the scripts refer to the checkpoint by.
+    #[serde(default)]
+    pub name: String,
+}
+
+/// An item the player picks up by touching it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collectible {
+    pub position: vec2<Coord>,
+    #[serde(default)]
+    pub name: String,
+    /// Whether the item can only be picked up with the hand.
+    #[serde(default)]
+    pub hand_only: bool,
+}
+
+/// A rope hanging from a fixed point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rope {
+    pub anchor: vec2<Coord>,
+    pub length: Coord,
+    #[serde(default)]
+    pub name: String,
+}
+
+/// Starting state of a dynamic object.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Prop {
+    pub position: vec2<Coord>,
+    pub shape: PropShape,
+    pub mass: Mass,
+    #[serde(default)]
+    pub name: String,
+}
+
+/// An area of the level that overrides the camera behaviour while the player is inside.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraZone {
+    pub area: Aabb2<Coord>,
+    /// Vertical field of view to use instead of the default one.
+    #[serde(default)]
+    pub fov: Option<f32>,
+    /// Fixed position to look at instead of following the player.
+    #[serde(default)]
+    pub target: Option<vec2<Coord>>,
 }
 
 impl Level {
@@ -11,9 +107,74 @@ impl Level {
         Self {
             spawn_point: vec2::ZERO,
             surfaces: default(),
+            chunked: false,
+            chunk_bounds: None,
+            checkpoints: default(),
+            hazards: default(),
+            collectibles: default(),
+            bars: default(),
+            ropes: default(),
+            props: default(),
+            camera_zones: default(),
+            decorations: default(),
+            backgrounds: default(),
+            finish: None,
+            switches: default(),
+            gates: default(),
+            doors: default(),
+            wires: default(),
+            triggers: default(),
+            script: default(),
         }
     }
 
+    /// Returns the bounding box of all the geometry in the level.
+    pub fn bounds(&self) -> Aabb2<Coord> {
+        Aabb2::points_bounding_box(
+            self.surfaces
+                .iter()
+                .flat_map(|surface| [surface.p1, surface.p2])
+                .chain(
+                    self.checkpoints
+                        .iter()
+                        .map(|checkpoint| checkpoint.position),
+                )
+                .chain(self.collectibles.iter().map(|item| item.position))
+                .chain(self.bars.iter().flat_map(|bar| [bar.p1, bar.p2]))
+                .chain(
+                    self.ropes.iter().flat_map(|rope| {
+                        [rope.anchor, rope.anchor - vec2(Coord::ZERO, rope.length)]
+                    }),
+                )
+                .chain(
+                    self.hazards
+                        .iter()
+                        .flat_map(|hazard| hazard.area(Time::ZERO).corners()),
+                )
+                .chain(self.finish.iter().flat_map(|finish| finish.corners()))
+                .chain(
+                    self.triggers
+                        .iter()
+                        .flat_map(|volume| volume.area.corners()),
+                )
+                .chain(self.switches.iter().map(|switch| switch.position))
+                .chain(self.gates.iter().map(|gate| gate.position))
+                .chain(self.doors.iter().flat_map(|door| {
+                    [door.area, door.area_at(Coord::ONE)]
+                        .into_iter()
+                        .flat_map(|area| area.corners())
+                }))
+                .chain(
+                    self.chunk_bounds
+                        .filter(|_| self.chunked)
+                        .iter()
+                        .flat_map(|bounds| bounds.corners()),
+                )
+                .chain([self.spawn_point]),
+        )
+        .unwrap_or(Aabb2::point(self.spawn_point))
+    }
+
     pub fn save(&self, path: impl AsRef<std::path::Path>) {
         #[cfg(not(target_arch = "wasm32"))]
         {
@@ -33,11 +194,61 @@ impl Level {
         }
         #[cfg(not(target_arch = "wasm32"))]
         {
-            Some(
+            let path = path.as_ref();
+            let mut level: Self =
                 serde_json::from_reader(std::fs::File::open(path).expect("Failed to open a file"))
-                    .expect("Failed to deserialize the level"),
-            )
+                    .expect("Failed to deserialize the level");
+            level.script = Script::load(path.with_extension("rhai")).unwrap_or_default();
+            Some(level)
+        }
+    }
+
+    /// Saves the level with all of its surfaces, writing them to the chunks if it is chunked.
+    /// The chunks left over from the previous contents of the level are removed.
+    pub fn save_full(&self, path: impl AsRef<std::path::Path>) {
+        let path = path.as_ref();
+        let mut chunks = ChunkStream::new(Self::chunks_dir(path));
+        chunks.clear();
+        if self.chunked {
+            chunks.save(&mut self.surfaces.clone());
+            Self {
+                surfaces: Vec::new(),
+                chunk_bounds: chunks.bounds(),
+                ..self.clone()
+            }
+            .save(path);
+        } else {
+            self.save(path);
+        }
+    }
+
+    /// Loads the level with all of its surfaces, reading them from the chunks if it is chunked.
+    pub fn load_full(path: impl AsRef<std::path::Path>) -> Option<Self> {
+        let path = path.as_ref();
+        let mut level = Self::load(path)?;
+        if level.chunked {
+            level.surfaces = ChunkStream::new(Self::chunks_dir(path)).read_all();
         }
+        Some(level)
+    }
+
+    /// Returns the directory with the chunks of the level stored in the file.
+    pub fn chunks_dir(path: impl AsRef<std::path::Path>) -> std::path::PathBuf {
+        path.as_ref().with_extension("chunks")
+    }
+}
+
+impl Rope {
+    /// Creates the simulated rope in its resting state.
+    pub fn chain(&self) -> RopeChain {
+        RopeChain::new(self.anchor, self.length)
+    }
+}
+
+impl Prop {
+    /// Creates the simulated prop at rest.
+    pub fn body(&self) -> PropBody {
+        PropBody::new(self.position, self.shape, self.mass)
     }
 }
 
@@ -46,3 +257,50 @@ impl Default for Level {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunked_level_round_trip() {
+        let dir = std::env::temp_dir().join(format!("parkour-level-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("level.json");
+
+        let mut level = Level::new();
+        level.surfaces = (0..10)
+            .map(|i| {
+                let x = i as f32 * 20.0;
+                Surface::new(vec2(x, 0.0).map(r32), vec2(x + 5.0, 1.0).map(r32))
+            })
+            .collect();
+        level.chunked = true;
+        level.save_full(&path);
+        let inline = Level::load(&path).unwrap();
+        assert!(inline.chunked && inline.surfaces.is_empty());
+        let full = Level::load_full(&path).unwrap();
+        let ends = |level: &Level| {
+            let mut ends: Vec<_> = level.surfaces.iter().map(|s| (s.p1, s.p2)).collect();
+            ends.sort_by_key(|&(p1, _)| (p1.x, p1.y));
+            ends
+        };
+        assert_eq!(ends(&full), ends(&level));
+
+        // The bounds cover the chunks that are not loaded, also after saving only the loaded ones
+        assert_eq!(inline.bounds(), level.bounds());
+        let mut chunks = ChunkStream::new(Level::chunks_dir(&path));
+        let mut surfaces = Vec::new();
+        chunks.update(&mut surfaces, vec2::ZERO, true);
+        assert!(surfaces.len() < level.surfaces.len());
+        chunks.save(&mut surfaces);
+        assert_eq!(chunks.bounds(), inline.chunk_bounds);
+
+        // Storing the level inline removes the chunks
+        level.chunked = false;
+        level.save_full(&path);
+        assert!(!Level::chunks_dir(&path).exists());
+        assert_eq!(ends(&Level::load_full(&path).unwrap()), ends(&level));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
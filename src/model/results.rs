@@ -0,0 +1,57 @@
+use super::*;
+
+/// Personal records of the player on a level.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelResults {
+    pub finishes: usize,
+    pub best_time: Option<Time>,
+    pub most_collected: usize,
+    pub fewest_deaths: Option<usize>,
+}
+
+impl LevelResults {
+    /// Updates the records with a finished run.
+    pub fn record(&mut self, run: &Run) {
+        let Some(time) = run.recording.finish_time else {
+            return;
+        };
+        self.finishes += 1;
+        self.best_time = Some(self.best_time.map_or(time, |best| best.min(time)));
+        self.most_collected = self.most_collected.max(run.collected.len());
+        self.fewest_deaths = Some(
+            self.fewest_deaths
+                .map_or(run.deaths, |fewest| fewest.min(run.deaths)),
+        );
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            serde_json::to_writer_pretty(
+                std::fs::File::create(path).expect("Failed to create a file"),
+                &self,
+            )
+            .expect("Failed to serialize the level results");
+            log::info!("Level results saved");
+        }
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Option<Self> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            None
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let file = std::fs::File::open(path).ok()?;
+            match serde_json::from_reader(file) {
+                Ok(results) => Some(results),
+                Err(err) => {
+                    log::error!("Failed to deserialize the level results: {err}");
+                    None
+                }
+            }
+        }
+    }
+}
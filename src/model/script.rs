@@ -0,0 +1,453 @@
+use super::*;
+
+/// Handlers the scripts can define, with their numbers of parameters.
+const HANDLERS: [(&str, usize); 10] = [
+    ("on_enter", 1),
+    ("on_leave", 1),
+    ("on_checkpoint", 1),
+    ("on_collect", 1),
+    ("on_grab", 2),
+    ("on_release", 0),
+    ("on_land", 0),
+    ("on_jump", 0),
+    ("on_death", 0),
+    ("on_finish", 0),
+];
+
+/// Limits a single handler call, so that a runaway loop cannot freeze the game.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Level-specific behavior, written in [Rhai](https://rhai.rs)
+/// and stored in the `.rhai` file next to the level:
+///
+/// ```text
+/// // Open the door when the first checkpoint is reached
+/// fn on_checkpoint(name) {
+///     if name == "checkpoint_1" && once("door") {
+///         move("door", 0, 3, 1.5);
+///         enable("bridge");
+///     }
+/// }
+/// ```
+///
+/// The script defines handlers for the events, called with the names of the objects involved:
+/// `on_enter(volume)`, `on_leave(volume)`, `on_checkpoint(name)`, `on_collect(name)`,
+/// `on_grab(kind, name)` with the kind `"rope"`, `"prop"` or `"point"`, `on_release()`,
+/// `on_land()`, `on_jump()`, `on_death()` and `on_finish()`.
+/// Handlers change the surfaces with the tag by calling `enable(tag)`, `disable(tag)`,
+/// `toggle(tag)` and `move(tag, dx, dy)` or `move(tag, dx, dy, seconds)`.
+/// `once(key)` is true only the first time it is called with the key.
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    ast: rhai::AST,
+}
+
+/// A call of a script handler for an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptCall {
+    pub handler: &'static str,
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptAction {
+    Enable(String),
+    Disable(String),
+    Toggle(String),
+    /// Moves the surfaces by the offset, smoothly over the duration.
+    Move {
+        tag: String,
+        offset: vec2<Coord>,
+        duration: Time,
+    },
+}
+
+/// An area of the level that triggers the script handlers when the player enters or leaves it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerVolume {
+    /// Name the scripts refer to the volume by.
+    pub name: String,
+    pub area: Aabb2<Coord>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScriptState {
+    /// Whether the player is inside each of the trigger volumes.
+    pub inside: Vec<bool>,
+    /// Keys `once` has already been called with.
+    pub fired: HashSet<String>,
+    pub moves: Vec<SurfaceMove>,
+    /// Surfaces as they were before the script changed them, to restore the level on restart.
+    pub original: HashMap<usize, Surface>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SurfaceMove {
+    pub surfaces: Vec<usize>,
+    pub velocity: vec2<Coord>,
+    pub time_left: Time,
+}
+
+impl Script {
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let ast = rhai::Engine::new()
+            .compile(source)
+            .map_err(|err| anyhow!("{err}"))?;
+        for function in ast.iter_functions() {
+            if !function.name.starts_with("on_") {
+                continue;
+            }
+            let Some(&(name, params)) = HANDLERS.iter().find(|(name, _)| *name == function.name)
+            else {
+                anyhow::bail!("Unknown handler {}", function.name);
+            };
+            anyhow::ensure!(
+                function.params.len() == params,
+                "{name} takes {params} parameters"
+            );
+        }
+        Ok(Self { ast })
+    }
+
+    /// Loads the script stored next to the level, if there is one.
+    pub fn load(path: impl AsRef<std::path::Path>) -> Option<Self> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = path;
+            None
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = path.as_ref();
+            let source = std::fs::read_to_string(path).ok()?;
+            match Self::parse(&source) {
+                Ok(script) => Some(script),
+                Err(err) => {
+                    log::error!("Failed to parse {}: {err:#}", path.display());
+                    None
+                }
+            }
+        }
+    }
+
+    /// Returns the names of the handlers the script defines.
+    pub fn handlers(&self) -> impl Iterator<Item = &str> {
+        self.ast
+            .iter_functions()
+            .map(|function| function.name)
+            .filter(|name| name.starts_with("on_"))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers().next().is_none()
+    }
+
+    /// Calls the handler, if the script defines it, and returns the actions it took.
+    /// Errors are logged, keeping the actions taken before them.
+    pub fn run(&self, call: &ScriptCall, fired: &mut HashSet<String>) -> Vec<ScriptAction> {
+        if !self.handlers().any(|name| name == call.handler) {
+            return Vec::new();
+        }
+        let actions = Rc::new(RefCell::new(Vec::new()));
+        let keys = Rc::new(RefCell::new(std::mem::take(fired)));
+
+        let mut engine = rhai::Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| log::info!("{text}"));
+        for name in ["enable", "disable", "toggle"] {
+            let actions = actions.clone();
+            engine.register_fn(name, move |tag: &str| {
+                let tag = tag.to_owned();
+                actions.borrow_mut().push(match name {
+                    "enable" => ScriptAction::Enable(tag),
+                    "disable" => ScriptAction::Disable(tag),
+                    _ => ScriptAction::Toggle(tag),
+                });
+            });
+        }
+        {
+            let actions = actions.clone();
+            engine.register_fn(
+                "move",
+                move |tag: &str, dx: rhai::Dynamic, dy: rhai::Dynamic| {
+                    let action = move_action(tag, dx, dy, rhai::Dynamic::from_int(0))?;
+                    actions.borrow_mut().push(action);
+                    Ok::<_, Box<rhai::EvalAltResult>>(())
+                },
+            );
+        }
+        {
+            let actions = actions.clone();
+            engine.register_fn(
+                "move",
+                move |tag: &str, dx: rhai::Dynamic, dy: rhai::Dynamic, seconds: rhai::Dynamic| {
+                    let action = move_action(tag, dx, dy, seconds)?;
+                    actions.borrow_mut().push(action);
+                    Ok::<_, Box<rhai::EvalAltResult>>(())
+                },
+            );
+        }
+        {
+            let keys = keys.clone();
+            engine.register_fn("once", move |key: &str| {
+                keys.borrow_mut().insert(key.to_owned())
+            });
+        }
+
+        let args: Vec<rhai::Dynamic> = call.args.iter().map(|arg| arg.as_str().into()).collect();
+        let result = engine.call_fn_with_options::<rhai::Dynamic>(
+            rhai::CallFnOptions::new().eval_ast(false),
+            &mut rhai::Scope::new(),
+            &self.ast,
+            call.handler,
+            args,
+        );
+        if let Err(err) = result {
+            log::error!("The script failed in {}: {err}", call.handler);
+        }
+        *fired = keys.take();
+        actions.take()
+    }
+}
+
+impl ScriptCall {
+    /// Returns the handler call for the event, if there is a handler for it.
+    pub fn new(event: &Event, level: &Level) -> Option<Self> {
+        let call = |handler, args: &[&str]| {
+            Some(Self {
+                handler,
+                args: args.iter().map(|arg| arg.to_string()).collect(),
+            })
+        };
+        match *event {
+            Event::TriggerEntered { volume } => {
+                call("on_enter", &[&level.triggers.get(volume)?.name])
+            }
+            Event::TriggerLeft { volume } => call("on_leave", &[&level.triggers.get(volume)?.name]),
+            Event::CheckpointReached { index } => {
+                call("on_checkpoint", &[&level.checkpoints.get(index)?.name])
+            }
+            Event::Collected { index } => {
+                call("on_collect", &[&level.collectibles.get(index)?.name])
+            }
+            Event::HoldStarted { hold, .. } => match hold {
+                Hold::Rope { rope, .. } => call("on_grab", &["rope", &level.ropes.get(rope)?.name]),
+                Hold::Prop { prop, .. } => call("on_grab", &["prop", &level.props.get(prop)?.name]),
+                Hold::Point(_) => call("on_grab", &["point", ""]),
+            },
+            Event::HoldEnded { .. } => call("on_release", &[]),
+            Event::Landed { .. } => call("on_land", &[]),
+            Event::Jumped { .. } => call("on_jump", &[]),
+            Event::Died { .. } => call("on_death", &[]),
+            Event::Finished { .. } => call("on_finish", &[]),
+            _ => None,
+        }
+    }
+}
+
+fn move_action(
+    tag: &str,
+    dx: rhai::Dynamic,
+    dy: rhai::Dynamic,
+    seconds: rhai::Dynamic,
+) -> Result<ScriptAction, Box<rhai::EvalAltResult>> {
+    Ok(ScriptAction::Move {
+        tag: tag.to_owned(),
+        offset: vec2(number(dx)?, number(dy)?),
+        duration: number(seconds)?.max(Time::ZERO),
+    })
+}
+
+/// Converts an integer or a float of the script into a coordinate.
+fn number(value: rhai::Dynamic) -> Result<Coord, Box<rhai::EvalAltResult>> {
+    let number = match (value.as_float(), value.as_int()) {
+        (Ok(float), _) => float as f32,
+        (_, Ok(int)) => int as f32,
+        _ => return Err(format!("Expected a number, got {}", value.type_name()).into()),
+    };
+    if !number.is_finite() {
+        return Err(format!("Invalid number {number}").into());
+    }
+    Ok(Coord::new(number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str, handler: &'static str, args: &[&str]) -> Vec<ScriptAction> {
+        let call = ScriptCall {
+            handler,
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        };
+        Script::parse(source)
+            .unwrap()
+            .run(&call, &mut HashSet::new())
+    }
+
+    #[test]
+    fn run_handlers() {
+        let source = r#"
+            // Functions other than the handlers are allowed
+            fn lift(height) {
+                move("lift", 0, height, 1.5);
+            }
+            fn on_enter(volume) {
+                if volume == "gate_area" {
+                    enable("floor");
+                    lift(-3);
+                }
+            }
+            fn on_grab(kind, name) {
+                toggle(kind + "_" + name);
+                move("lift", 2.5, 0);
+                move("lift", 1, 1.0, -4);
+                disable("spikes");
+            }
+        "#;
+        assert_eq!(
+            run(source, "on_enter", &["gate_area"]),
+            [
+                ScriptAction::Enable("floor".to_owned()),
+                ScriptAction::Move {
+                    tag: "lift".to_owned(),
+                    offset: vec2(0.0, -3.0).map(r32),
+                    duration: r32(1.5),
+                },
+            ]
+        );
+        assert_eq!(run(source, "on_enter", &["other"]), []);
+        assert_eq!(run(source, "on_land", &[]), []);
+        assert_eq!(
+            run(source, "on_grab", &["rope", "vine"]),
+            [
+                ScriptAction::Toggle("rope_vine".to_owned()),
+                // Without the duration the surfaces jump to the place
+                ScriptAction::Move {
+                    tag: "lift".to_owned(),
+                    offset: vec2(2.5, 0.0).map(r32),
+                    duration: Time::ZERO,
+                },
+                // Negative durations are clamped
+                ScriptAction::Move {
+                    tag: "lift".to_owned(),
+                    offset: vec2(1.0, 1.0).map(r32),
+                    duration: Time::ZERO,
+                },
+                ScriptAction::Disable("spikes".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn run_once() {
+        let script =
+            Script::parse(r#"fn on_land() { if once("land") { enable("floor"); } }"#).unwrap();
+        let call = ScriptCall {
+            handler: "on_land",
+            args: Vec::new(),
+        };
+        let mut fired = HashSet::new();
+        assert_eq!(script.run(&call, &mut fired).len(), 1);
+        assert_eq!(fired, HashSet::from(["land".to_owned()]));
+        assert_eq!(script.run(&call, &mut fired), []);
+    }
+
+    #[test]
+    fn runtime_errors() {
+        // The actions before the error are kept
+        let actions = run(
+            r#"fn on_land() { enable("a"); move("b", "up", 0); enable("c"); }"#,
+            "on_land",
+            &[],
+        );
+        assert_eq!(actions, [ScriptAction::Enable("a".to_owned())]);
+        // Runaway loops are stopped
+        assert_eq!(run("fn on_jump() { loop {} }", "on_jump", &[]), []);
+        assert_eq!(run("fn on_jump() { fly(); }", "on_jump", &[]), []);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |source: &str| format!("{:#}", Script::parse(source).unwrap_err());
+        assert!(error("fn on_land() {\n\n  enable(\"a\"").contains("line 3"));
+        assert!(error("fn on_swim() {}").contains("Unknown handler on_swim"));
+        assert!(error("fn on_grab(name) {}").contains("on_grab takes 2 parameters"));
+        let script = Script::parse("fn helper() {} fn on_death() {}").unwrap();
+        assert_eq!(script.handlers().collect::<Vec<_>>(), ["on_death"]);
+        assert!(Script::parse("// Nothing yet").unwrap().is_empty());
+    }
+
+    #[test]
+    fn calls_for_events() {
+        let mut level = Level::new();
+        level.triggers.push(TriggerVolume {
+            name: "start".to_owned(),
+            area: Aabb2::ZERO,
+        });
+        level.checkpoints.push(Checkpoint {
+            position: vec2::ZERO,
+            name: "middle".to_owned(),
+        });
+        level.ropes.push(Rope {
+            anchor: vec2::ZERO,
+            length: Coord::ONE,
+            name: "vine".to_owned(),
+        });
+        let call = |event: Event| {
+            ScriptCall::new(&event, &level).map(|call| {
+                let mut words = vec![call.handler.to_owned()];
+                words.extend(call.args);
+                words.join(" ")
+            })
+        };
+        let hold = |hold| Event::HoldStarted {
+            hold,
+            position: vec2::ZERO,
+            impact: Coord::ZERO,
+        };
+
+        let called = |text: &str| Some(text.to_owned());
+        assert_eq!(
+            call(Event::TriggerEntered { volume: 0 }),
+            called("on_enter start")
+        );
+        assert_eq!(
+            call(Event::TriggerLeft { volume: 0 }),
+            called("on_leave start")
+        );
+        // Objects missing from the level are not reported
+        assert_eq!(call(Event::TriggerEntered { volume: 5 }), None);
+        assert_eq!(call(Event::Collected { index: 0 }), None);
+        assert_eq!(
+            call(Event::CheckpointReached { index: 0 }),
+            called("on_checkpoint middle")
+        );
+        assert_eq!(
+            call(hold(Hold::Rope { rope: 0, point: 4 })),
+            called("on_grab rope vine")
+        );
+        assert_eq!(
+            call(hold(Hold::Point(vec2::ZERO))),
+            called("on_grab point ")
+        );
+        assert_eq!(
+            call(Event::HoldEnded {
+                reason: HoldEndReason::Broken,
+                position: vec2::ZERO,
+                impact: Coord::ZERO,
+            }),
+            called("on_release")
+        );
+        assert_eq!(
+            call(Event::Died {
+                position: vec2::ZERO
+            }),
+            called("on_death")
+        );
+        assert_eq!(
+            call(Event::Finished { time: Time::ZERO }),
+            called("on_finish")
+        );
+    }
+}
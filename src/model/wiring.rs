@@ -0,0 +1,436 @@
+use super::*;
+
+/// Half size of a pressure plate.
+pub const PLATE_SIZE: vec2<f32> = vec2(0.6, 0.1);
+/// Distance from the lever at which the hand can pull it.
+pub const LEVER_RADIUS: f32 = 0.5;
+
+/// An object the player interacts with to send a signal through the wires.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Switch {
+    pub position: vec2<Coord>,
+    pub kind: SwitchKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwitchKind {
+    /// Powered while the body or a prop is standing on it.
+    Plate,
+    /// Flipped every time the hand pulls it.
+    Lever,
+}
+
+/// Combines the signals coming into it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Gate {
+    pub position: vec2<Coord>,
+    pub kind: GateKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GateKind {
+    /// Powered when all the inputs are.
+    And,
+    /// Powered when any of the inputs is.
+    Or,
+    /// Powered when none of the inputs are.
+    Not,
+}
+
+/// A solid block that slides by the offset while powered, used for doors and moving platforms.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Door {
+    /// Position of the closed door.
+    pub area: Aabb2<Coord>,
+    pub offset: vec2<Coord>,
+    /// Speed of the movement in world units per second.
+    pub speed: Coord,
+}
+
+/// An object of the signal graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WireNode {
+    Switch(usize),
+    Gate(usize),
+    Door(usize),
+}
+
+/// Carries the signal from a switch or a gate into a gate or a door.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Wire {
+    pub from: WireNode,
+    pub to: WireNode,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WiringState {
+    pub switches: Vec<bool>,
+    /// Whether the hand is pulling each of the levers, so that holding it flips it only once.
+    pub pulling: Vec<bool>,
+    /// How far each door has moved towards the open position, from 0 to 1.
+    pub doors: Vec<Coord>,
+}
+
+impl GateKind {
+    pub fn next(self) -> Self {
+        match self {
+            Self::And => Self::Or,
+            Self::Or => Self::Not,
+            Self::Not => Self::And,
+        }
+    }
+}
+
+impl Switch {
+    /// Returns the area that presses the plate.
+    pub fn plate_area(&self) -> Aabb2<Coord> {
+        Aabb2::point(self.position).extend_symmetric(PLATE_SIZE.map(Coord::new))
+    }
+}
+
+impl Door {
+    pub fn area_at(&self, open: Coord) -> Aabb2<Coord> {
+        self.area.translate(self.offset * open)
+    }
+
+    /// Returns the solid box of the door for the collisions.
+    pub fn body(&self, open: Coord) -> PropBody {
+        let area = self.area_at(open);
+        PropBody::new(
+            area.center(),
+            PropShape::Box {
+                half_size: area.size() / Coord::new(2.0),
+            },
+            Mass::ONE,
+        )
+    }
+}
+
+impl WireNode {
+    pub fn is_source(self) -> bool {
+        !matches!(self, Self::Door(_))
+    }
+
+    pub fn is_sink(self) -> bool {
+        !matches!(self, Self::Switch(_))
+    }
+
+    pub fn index(self) -> usize {
+        match self {
+            Self::Switch(index) | Self::Gate(index) | Self::Door(index) => index,
+        }
+    }
+
+    fn index_mut(&mut self) -> &mut usize {
+        match self {
+            Self::Switch(index) | Self::Gate(index) | Self::Door(index) => index,
+        }
+    }
+}
+
+impl WiringState {
+    pub fn new(level: &Level) -> Self {
+        Self {
+            switches: vec![false; level.switches.len()],
+            pulling: vec![false; level.switches.len()],
+            doors: vec![Coord::ZERO; level.doors.len()],
+        }
+    }
+}
+
+impl Level {
+    /// Returns the point the wires of the node are drawn to.
+    pub fn node_position(&self, node: WireNode) -> Option<vec2<Coord>> {
+        match node {
+            WireNode::Switch(index) => self.switches.get(index).map(|switch| switch.position),
+            WireNode::Gate(index) => self.gates.get(index).map(|gate| gate.position),
+            WireNode::Door(index) => self.doors.get(index).map(|door| door.area.center()),
+        }
+    }
+
+    /// Evaluates the signal coming out of the node, or into it in case of a door.
+    pub fn signal(&self, node: WireNode, switches: &[bool]) -> bool {
+        self.signal_impl(node, switches, &mut HashSet::new(), &mut HashMap::new())
+            .0
+    }
+
+    /// Evaluates the signals into all the doors, sharing the work between them.
+    pub fn door_signals(&self, switches: &[bool]) -> Vec<bool> {
+        let mut known = HashMap::new();
+        (0..self.doors.len())
+            .map(|index| {
+                let node = WireNode::Door(index);
+                self.signal_impl(node, switches, &mut HashSet::new(), &mut known)
+                    .0
+            })
+            .collect()
+    }
+
+    /// Returns the signal and whether it is the same wherever the evaluation started,
+    /// which it is not when a loop was cut below the node.
+    /// Only such signals are remembered in `known`, keeping the evaluation linear.
+    fn signal_impl(
+        &self,
+        node: WireNode,
+        switches: &[bool],
+        visiting: &mut HashSet<WireNode>,
+        known: &mut HashMap<WireNode, bool>,
+    ) -> (bool, bool) {
+        if let WireNode::Switch(index) = node {
+            return (switches.get(index).copied().unwrap_or(false), true);
+        }
+        if let Some(&signal) = known.get(&node) {
+            return (signal, true);
+        }
+        // A loop in the graph carries no signal
+        if !visiting.insert(node) {
+            return (false, false);
+        }
+        let mut complete = true;
+        let inputs: Vec<bool> = self
+            .wires
+            .iter()
+            .filter(|wire| wire.to == node)
+            .map(|wire| {
+                let (signal, input_complete) =
+                    self.signal_impl(wire.from, switches, visiting, known);
+                complete &= input_complete;
+                signal
+            })
+            .collect();
+        let signal = match node {
+            WireNode::Gate(index) => match self.gates.get(index).map(|gate| gate.kind) {
+                Some(GateKind::And) => !inputs.is_empty() && inputs.iter().all(|&input| input),
+                Some(GateKind::Or) => inputs.iter().any(|&input| input),
+                Some(GateKind::Not) => !inputs.iter().any(|&input| input),
+                None => false,
+            },
+            _ => inputs.iter().any(|&input| input),
+        };
+        visiting.remove(&node);
+        if complete {
+            known.insert(node, signal);
+        }
+        (signal, complete)
+    }
+
+    /// Removes the object of the signal graph along with its wires.
+    pub fn remove_node(&mut self, node: WireNode) {
+        match node {
+            WireNode::Switch(index) => {
+                self.switches.remove(index);
+            }
+            WireNode::Gate(index) => {
+                self.gates.remove(index);
+            }
+            WireNode::Door(index) => {
+                self.doors.remove(index);
+            }
+        }
+        self.wires
+            .retain(|wire| wire.from != node && wire.to != node);
+        // The following objects of the same kind have moved back by one
+        for wire in &mut self.wires {
+            for other in [&mut wire.from, &mut wire.to] {
+                let same_kind = std::mem::discriminant(other) == std::mem::discriminant(&node);
+                if same_kind && other.index() > node.index() {
+                    *other.index_mut() -= 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn switch(x: f32) -> Switch {
+        Switch {
+            position: vec2(x, 0.0).map(r32),
+            kind: SwitchKind::Plate,
+        }
+    }
+
+    fn gate(kind: GateKind) -> Gate {
+        Gate {
+            position: vec2::ZERO,
+            kind,
+        }
+    }
+
+    fn door(x: f32) -> Door {
+        Door {
+            area: Aabb2::point(vec2(x, 0.0)).extend_uniform(0.5).map(r32),
+            offset: vec2(0.0, 2.0).map(r32),
+            speed: r32(1.0),
+        }
+    }
+
+    fn wire(from: WireNode, to: WireNode) -> Wire {
+        Wire { from, to }
+    }
+
+    /// Two switches going into a gate of the kind, which powers the door.
+    fn gated(kind: GateKind) -> Level {
+        let mut level = Level::new();
+        level.switches = vec![switch(0.0), switch(1.0)];
+        level.gates = vec![gate(kind)];
+        level.doors = vec![door(5.0)];
+        level.wires = vec![
+            wire(WireNode::Switch(0), WireNode::Gate(0)),
+            wire(WireNode::Switch(1), WireNode::Gate(0)),
+            wire(WireNode::Gate(0), WireNode::Door(0)),
+        ];
+        level
+    }
+
+    fn truth_table(level: &Level, node: WireNode) -> [bool; 4] {
+        [[false, false], [true, false], [false, true], [true, true]]
+            .map(|switches| level.signal(node, &switches))
+    }
+
+    #[test]
+    fn gates() {
+        let door = WireNode::Door(0);
+        assert_eq!(
+            truth_table(&gated(GateKind::And), door),
+            [false, false, false, true]
+        );
+        assert_eq!(
+            truth_table(&gated(GateKind::Or), door),
+            [false, true, true, true]
+        );
+        assert_eq!(
+            truth_table(&gated(GateKind::Not), door),
+            [true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn unconnected_nodes() {
+        let mut level = gated(GateKind::And);
+        level.wires.clear();
+        // An AND without inputs is off, a NOT without inputs is on
+        assert!(!level.signal(WireNode::Gate(0), &[true, true]));
+        level.gates[0].kind = GateKind::Not;
+        assert!(level.signal(WireNode::Gate(0), &[true, true]));
+        assert!(!level.signal(WireNode::Door(0), &[true, true]));
+        // Missing switches and gates carry no signal
+        assert!(!level.signal(WireNode::Switch(5), &[true, true]));
+        assert!(!level.signal(WireNode::Gate(5), &[true, true]));
+    }
+
+    #[test]
+    fn loops_carry_no_signal() {
+        let mut level = gated(GateKind::Or);
+        level.gates.push(gate(GateKind::Or));
+        // The gates feed each other, and the first one also takes the switch
+        level.wires = vec![
+            wire(WireNode::Switch(0), WireNode::Gate(0)),
+            wire(WireNode::Gate(0), WireNode::Gate(1)),
+            wire(WireNode::Gate(1), WireNode::Gate(0)),
+            wire(WireNode::Gate(1), WireNode::Door(0)),
+        ];
+        assert!(level.signal(WireNode::Door(0), &[true, false]));
+        assert!(!level.signal(WireNode::Door(0), &[false, false]));
+
+        // A NOT feeding itself sees no input through the loop
+        let mut level = gated(GateKind::Not);
+        level.wires = vec![
+            wire(WireNode::Gate(0), WireNode::Gate(0)),
+            wire(WireNode::Gate(0), WireNode::Door(0)),
+        ];
+        assert!(level.signal(WireNode::Door(0), &[false, false]));
+    }
+
+    #[test]
+    fn diamonds_are_evaluated_once() {
+        // Every gate feeds both gates of the next layer, so the paths double with each layer
+        let layers = 40;
+        let mut level = Level::new();
+        level.switches = vec![switch(0.0)];
+        level.doors = vec![door(5.0)];
+        level.gates = (0..layers * 2).map(|_| gate(GateKind::Or)).collect();
+        level.wires = vec![
+            wire(WireNode::Switch(0), WireNode::Gate(0)),
+            wire(WireNode::Switch(0), WireNode::Gate(1)),
+            wire(WireNode::Gate(layers * 2 - 1), WireNode::Door(0)),
+        ];
+        for layer in 1..layers {
+            for from in [layer * 2 - 2, layer * 2 - 1] {
+                for to in [layer * 2, layer * 2 + 1] {
+                    level
+                        .wires
+                        .push(wire(WireNode::Gate(from), WireNode::Gate(to)));
+                }
+            }
+        }
+        assert!(level.signal(WireNode::Door(0), &[true]));
+        assert_eq!(level.door_signals(&[false]), [false]);
+    }
+
+    #[test]
+    fn loops_are_not_remembered() {
+        // The NOT gates feed each other, so each door sees the gate next to it
+        // powered by the other one, which sees no input through the loop
+        let mut level = gated(GateKind::Not);
+        level.gates.push(gate(GateKind::Not));
+        level.doors.push(door(8.0));
+        level.wires = vec![
+            wire(WireNode::Gate(1), WireNode::Gate(0)),
+            wire(WireNode::Gate(0), WireNode::Gate(1)),
+            wire(WireNode::Gate(0), WireNode::Door(0)),
+            wire(WireNode::Gate(1), WireNode::Door(1)),
+        ];
+        let separately = [0, 1].map(|index| level.signal(WireNode::Door(index), &[false, false]));
+        assert_eq!(separately, [false, false]);
+        assert_eq!(level.door_signals(&[false, false]), separately);
+    }
+
+    #[test]
+    fn removing_a_node_shifts_the_wires() {
+        let mut level = gated(GateKind::And);
+        level.switches.push(switch(2.0));
+        level.gates.push(gate(GateKind::Or));
+        level.doors.push(door(8.0));
+        level.wires.extend([
+            wire(WireNode::Switch(2), WireNode::Gate(1)),
+            wire(WireNode::Gate(1), WireNode::Door(1)),
+        ]);
+
+        level.remove_node(WireNode::Switch(0));
+        assert_eq!(level.switches.len(), 2);
+        assert_eq!(
+            level.wires,
+            [
+                wire(WireNode::Switch(0), WireNode::Gate(0)),
+                wire(WireNode::Gate(0), WireNode::Door(0)),
+                wire(WireNode::Switch(1), WireNode::Gate(1)),
+                wire(WireNode::Gate(1), WireNode::Door(1)),
+            ]
+        );
+
+        level.remove_node(WireNode::Gate(0));
+        assert_eq!(
+            level.wires,
+            [
+                wire(WireNode::Switch(1), WireNode::Gate(0)),
+                wire(WireNode::Gate(0), WireNode::Door(1)),
+            ]
+        );
+
+        level.remove_node(WireNode::Door(0));
+        assert_eq!(level.doors.len(), 1);
+        assert_eq!(
+            level.wires,
+            [
+                wire(WireNode::Switch(1), WireNode::Gate(0)),
+                wire(WireNode::Gate(0), WireNode::Door(0)),
+            ]
+        );
+        // The remaining chain still works
+        assert!(level.signal(WireNode::Door(0), &[false, true]));
+        assert!(!level.signal(WireNode::Door(0), &[true, false]));
+    }
+}
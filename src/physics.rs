@@ -1,14 +1,22 @@
 use super::*;
 
 mod arm_skeleton;
+mod bar;
 mod body;
+mod prop;
+mod rope;
 mod running;
 mod surface;
+mod surface_grid;
 
 pub use arm_skeleton::*;
+pub use bar::*;
 pub use body::*;
+pub use prop::*;
+pub use rope::*;
 pub use running::*;
 pub use surface::*;
+pub use surface_grid::*;
 
 #[derive(Debug, Clone, Copy)]
 pub struct PhysicsPoint {
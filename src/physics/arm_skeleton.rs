@@ -46,6 +46,15 @@ impl ArmSkeleton {
         self.elbow.point.distance + self.hand.point.distance
     }
 
+    /// Returns the angles of the elbow and the hand joints along with their angular velocities,
+    /// the elbow relative to the horizontal and the hand relative to the elbow.
+    pub fn joints(&self) -> [(Angle<R32>, R32); 2] {
+        [
+            (self.elbow.point.angle, self.elbow.velocity),
+            (self.hand.point.angle, self.hand.velocity),
+        ]
+    }
+
     pub fn impulse(&self) -> vec2<Coord> {
         let elbow_dir = self.elbow.point.angle.unit_vec().rotate_90();
         let elbow_speed = self.elbow.velocity * self.elbow.point.distance;
@@ -59,6 +68,14 @@ impl ArmSkeleton {
         elbow_impulse + hand_impulse
     }
 
+    /// Returns the length of the largest impulse, with the joints straight
+    /// and turning at the highest angular velocity.
+    pub fn max_impulse(&self) -> Coord {
+        let elbow_speed = r32(MAX_ANGULAR_VELOCITY) * self.elbow.point.distance;
+        let hand_speed = elbow_speed + r32(MAX_ANGULAR_VELOCITY) * self.hand.point.distance;
+        elbow_speed * self.elbow.mass + hand_speed * self.hand.mass
+    }
+
     /// The returns the skeleton in world coordinates as an array `[shoulder, elbow, hand]`.
     pub fn get_skeleton(&self, body: &PhysicsPoint) -> [PhysicsPoint; 3] {
         let shoulder = self.shoulder.relative(body);
@@ -225,3 +242,67 @@ impl From<PhysicsPoint> for PolarPhysicsPoint {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    /// Arm with the elbow bone longer than the hand bone, so that it cannot reach close points.
+    fn arm(elbow: f32, hand: f32) -> ArmSkeleton {
+        ArmSkeleton::new(
+            PhysicsPoint::new(vec2::ZERO, r32(0.2), r32(0.5)),
+            PhysicsPoint::new(vec2(0.0, -elbow).map(r32), r32(0.15), r32(0.7)),
+            PhysicsPoint::new(vec2(0.0, -hand).map(r32), r32(0.2), r32(1.0)),
+        )
+    }
+
+    /// Returns the hand position relative to the shoulder for the joint angles.
+    fn forward(arm: &ArmSkeleton, [elbow, hand]: [Angle<R32>; 2]) -> vec2<f32> {
+        let elbow_position = elbow.unit_vec() * arm.elbow.point.distance;
+        (elbow_position + (elbow + hand).unit_vec() * arm.hand.point.distance).map(R32::as_f32)
+    }
+
+    proptest! {
+        #[test]
+        fn reaches_the_target_within_reach(
+            elbow in 0.3f32..1.5,
+            hand in 0.3f32..1.5,
+            angle in -3.1f32..3.1,
+            fraction in 0.0f32..1.0,
+        ) {
+            let arm = arm(elbow, hand);
+            let min = (elbow - hand).abs() + 0.01;
+            let max = elbow + hand - 0.01;
+            let distance = min + (max - min) * fraction;
+            let target = vec2(angle.cos(), angle.sin()) * distance;
+            let angles = arm.solve_angles(target.map(r32)).unwrap();
+            let reached = forward(&arm, angles);
+            prop_assert!((reached - target).len() < 1e-3, "{reached:?} != {target:?}");
+        }
+
+        #[test]
+        fn clamps_the_target_to_the_reach(
+            angle in -3.1f32..3.1,
+            distance in prop_oneof![0.01f32..0.4, 1.6f32..100.0],
+        ) {
+            let arm = arm(1.0, 0.5);
+            let direction = vec2(angle.cos(), angle.sin());
+            let angles = arm.solve_angles((direction * distance).map(r32)).unwrap();
+            let reached = forward(&arm, angles);
+            let expected = direction * distance.clamp(0.5, 1.5);
+            prop_assert!((reached - expected).len() < 1e-3, "{reached:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn no_solution_for_the_shoulder() {
+        assert!(arm(1.0, 0.5).solve_angles(vec2::ZERO).is_none());
+    }
+
+    #[test]
+    fn max_reach_is_the_length_of_the_bones() {
+        assert_eq!(arm(1.0, 0.5).max_reach(), r32(1.5));
+    }
+}
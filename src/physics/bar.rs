@@ -0,0 +1,26 @@
+use super::*;
+
+/// A thin bar the hand can hold onto anywhere along its length.
+/// Unlike surfaces, bars do not collide with the body.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Bar {
+    pub p1: vec2<Coord>,
+    pub p2: vec2<Coord>,
+}
+
+impl Bar {
+    pub fn segment_f32(&self) -> Segment<f32> {
+        Segment(self.p1.map(Coord::as_f32), self.p2.map(Coord::as_f32))
+    }
+
+    /// Returns the point on the bar closest to the given one.
+    pub fn closest_point(&self, point: vec2<Coord>) -> vec2<Coord> {
+        let direction = self.p2 - self.p1;
+        let len_sqr = vec2::dot(direction, direction);
+        if len_sqr == Coord::ZERO {
+            return self.p1;
+        }
+        let t = (vec2::dot(point - self.p1, direction) / len_sqr).clamp(Coord::ZERO, Coord::ONE);
+        self.p1 + direction * t
+    }
+}
@@ -1,13 +1,29 @@
 use super::*;
 
+/// Props heavier than this can only be pushed.
+const MAX_CARRY_MASS: f32 = 15.0;
+
 pub struct Body {
     pub center: PhysicsPoint,
     pub arm: ArmSkeleton,
-    pub holding_to: Option<vec2<Coord>>,
+    pub holding_to: Option<Hold>,
     pub ground_normal: Option<vec2<Coord>>,
+    /// The last direction of the running, only affects the look.
+    pub facing_left: bool,
     pub history: running::BodyMovementHistory,
 }
 
+/// What the hand is holding onto.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hold {
+    /// A fixed point in the world.
+    Point(Position),
+    /// A point of a rope.
+    Rope { rope: usize, point: usize },
+    /// A carried prop, held at the offset from its center.
+    Prop { prop: usize, offset: vec2<Coord> },
+}
+
 impl Body {
     pub fn new(position: Position) -> Self {
         Self {
@@ -19,18 +35,64 @@ impl Body {
             ),
             holding_to: None,
             ground_normal: None,
+            facing_left: false,
             history: default(),
         }
     }
 
-    pub fn try_holding(&mut self, surfaces: &[Surface]) {
+    /// Returns the position the hand is holding onto.
+    /// Carried props follow the hand instead, so there is no such position for them.
+    pub fn hold_position(&self, ropes: &[RopeChain]) -> Option<Position> {
+        match self.holding_to? {
+            Hold::Point(position) => Some(position),
+            Hold::Rope { rope, point } => ropes
+                .get(rope)
+                .and_then(|rope| rope.points.get(point))
+                .map(|point| point.position),
+            Hold::Prop { .. } => None,
+        }
+    }
+
+    pub fn try_holding(
+        &mut self,
+        surfaces: &[Surface],
+        grid: &SurfaceGrid,
+        bars: &[Bar],
+        ropes: &[RopeChain],
+        props: &[PropBody],
+    ) {
+        if self.holding_to.is_some() {
+            return;
+        }
         let [_, _, hand] = self.arm.get_skeleton(&self.center);
-        let point = surfaces
-            .iter()
-            .flat_map(|surface| [surface.p1, surface.p2])
+        let area = Aabb2::point(hand.position).extend_uniform(hand.radius);
+        let point = grid
+            .surfaces_in(surfaces, area)
+            .flat_map(|(_, surface)| [surface.p1, surface.p2])
+            .chain(bars.iter().map(|bar| bar.closest_point(hand.position)))
             .find(|&p| (p - hand.position).len() <= hand.radius);
         if let Some(point) = point {
-            self.holding_to = Some(point);
+            self.holding_to = Some(Hold::Point(point));
+            return;
         }
+        self.holding_to = ropes
+            .iter()
+            .enumerate()
+            .find_map(|(rope, chain)| {
+                chain
+                    .find_point(hand.position, hand.radius)
+                    .map(|point| Hold::Rope { rope, point })
+            })
+            .or_else(|| {
+                props.iter().enumerate().find_map(|(index, prop)| {
+                    (prop.point.mass.as_f32() <= MAX_CARRY_MASS
+                        && prop.contact_circle(hand.position, hand.radius).is_some())
+                    .then(|| Hold::Prop {
+                        prop: index,
+                        offset: prop.shape.closest_point(prop.point.position, hand.position)
+                            - prop.point.position,
+                    })
+                })
+            });
     }
 }
@@ -0,0 +1,222 @@
+use super::*;
+
+/// Fraction of the tangential velocity lost per second while touching a surface.
+const FRICTION: f32 = 4.0;
+
+/// Shape of a dynamic object. Boxes are axis aligned and never rotate.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum PropShape {
+    Circle { radius: Coord },
+    Box { half_size: vec2<Coord> },
+}
+
+/// A dynamic object that can be pushed, stacked and carried.
+#[derive(Debug, Clone)]
+pub struct PropBody {
+    /// The radius of the point is the radius of the bounding circle.
+    pub point: PhysicsPoint,
+    pub shape: PropShape,
+}
+
+/// Overlap of two objects.
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    /// Direction in which the second object has to move to resolve the overlap.
+    pub normal: vec2<Coord>,
+    pub penetration: Coord,
+}
+
+impl PropShape {
+    pub fn bounding_radius(&self) -> Coord {
+        match *self {
+            Self::Circle { radius } => radius,
+            Self::Box { half_size } => half_size.len(),
+        }
+    }
+
+    /// Returns the point of the shape centered at `center` that is closest to the given one.
+    pub fn closest_point(&self, center: Position, point: Position) -> Position {
+        match *self {
+            Self::Circle { radius } => {
+                let delta = point - center;
+                if delta.len() <= radius {
+                    point
+                } else {
+                    center + delta.normalize_or_zero() * radius
+                }
+            }
+            Self::Box { half_size } => vec2(
+                point
+                    .x
+                    .clamp(center.x - half_size.x, center.x + half_size.x),
+                point
+                    .y
+                    .clamp(center.y - half_size.y, center.y + half_size.y),
+            ),
+        }
+    }
+}
+
+impl PropBody {
+    pub fn new(position: Position, shape: PropShape, mass: Mass) -> Self {
+        Self {
+            point: PhysicsPoint::new(position, shape.bounding_radius(), mass),
+            shape,
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb2<Coord> {
+        let position = self.point.position;
+        match self.shape {
+            PropShape::Circle { radius } => Aabb2::point(position).extend_uniform(radius),
+            PropShape::Box { half_size } => Aabb2::point(position).extend_symmetric(half_size),
+        }
+    }
+
+    /// Checks the overlap with a circle. The normal points from the prop to the circle.
+    pub fn contact_circle(&self, center: Position, radius: Coord) -> Option<Contact> {
+        let position = self.point.position;
+        let contact = match self.shape {
+            PropShape::Circle { radius: own } => {
+                let delta = center - position;
+                Contact {
+                    normal: delta.normalize_or_zero(),
+                    penetration: own + radius - delta.len(),
+                }
+            }
+            PropShape::Box { .. } => {
+                let closest = self.shape.closest_point(position, center);
+                let delta = center - closest;
+                if delta.len() > Coord::ZERO {
+                    Contact {
+                        normal: delta.normalize_or_zero(),
+                        penetration: radius - delta.len(),
+                    }
+                } else {
+                    // The center is inside the box
+                    let other = Aabb2::point(center).extend_uniform(radius);
+                    aabb_contact(self.aabb(), other)
+                }
+            }
+        };
+        (contact.penetration > Coord::ZERO).then_some(contact)
+    }
+
+    /// Checks the overlap with another prop. The normal points from this prop to the other.
+    pub fn contact(&self, other: &Self) -> Option<Contact> {
+        match (self.shape, other.shape) {
+            (_, PropShape::Circle { radius }) => self.contact_circle(other.point.position, radius),
+            (PropShape::Circle { radius }, _) => other
+                .contact_circle(self.point.position, radius)
+                .map(|contact| Contact {
+                    normal: -contact.normal,
+                    ..contact
+                }),
+            (PropShape::Box { .. }, PropShape::Box { .. }) => {
+                let contact = aabb_contact(self.aabb(), other.aabb());
+                (contact.penetration > Coord::ZERO).then_some(contact)
+            }
+        }
+    }
+
+    /// Checks the overlap with a surface. The normal points from the surface to the prop.
+    pub fn contact_surface(&self, surface: &Surface) -> Option<Contact> {
+        let position = self.point.position;
+        match self.shape {
+            PropShape::Circle { radius } => {
+                let delta = surface.delta_to(position);
+                let penetration = radius - delta.len();
+                (penetration > Coord::ZERO).then(|| Contact {
+                    normal: -delta.normalize_or_zero(),
+                    penetration,
+                })
+            }
+            PropShape::Box { half_size } => {
+                // Separating axis test
+                let direction = surface.p2 - surface.p1;
+                let mut axes = vec![vec2(Coord::ONE, Coord::ZERO), vec2(Coord::ZERO, Coord::ONE)];
+                if direction.len() > Coord::ZERO {
+                    axes.push(direction.normalize_or_zero().rotate_90());
+                }
+                let mut best: Option<Contact> = None;
+                for axis in axes {
+                    let extent = half_size.x * axis.x.abs() + half_size.y * axis.y.abs();
+                    let center = vec2::dot(position, axis);
+                    let (p1, p2) = (vec2::dot(surface.p1, axis), vec2::dot(surface.p2, axis));
+                    let (min, max) = (p1.min(p2), p1.max(p2));
+                    let push_positive = max - (center - extent);
+                    let push_negative = (center + extent) - min;
+                    if push_positive <= Coord::ZERO || push_negative <= Coord::ZERO {
+                        return None;
+                    }
+                    let contact = if push_positive < push_negative {
+                        Contact {
+                            normal: axis,
+                            penetration: push_positive,
+                        }
+                    } else {
+                        Contact {
+                            normal: -axis,
+                            penetration: push_negative,
+                        }
+                    };
+                    if best.is_none_or(|best| contact.penetration < best.penetration) {
+                        best = Some(contact);
+                    }
+                }
+                best
+            }
+        }
+    }
+
+    pub fn collide_surfaces(&mut self, surfaces: &[Surface], grid: &SurfaceGrid, delta_time: Time) {
+        for (_, surface) in grid.surfaces_in(surfaces, self.aabb()) {
+            let Some(contact) = self.contact_surface(surface) else {
+                continue;
+            };
+            self.point.position += contact.normal * contact.penetration;
+            let normal_vel = vec2::dot(self.point.velocity, contact.normal);
+            if normal_vel < Coord::ZERO {
+                self.point.velocity -= contact.normal * normal_vel;
+            }
+            let tangent = contact.normal.rotate_90();
+            let tangent_vel = vec2::dot(self.point.velocity, tangent);
+            let friction = (Coord::new(FRICTION) * delta_time).min(Coord::ONE);
+            self.point.velocity -= tangent * tangent_vel * friction;
+        }
+    }
+}
+
+/// Moves the points apart along the normal proportionally to their inverse masses
+/// and removes their approaching velocity. The normal points from `a` to `b`.
+pub fn resolve_contact(a: &mut PhysicsPoint, b: &mut PhysicsPoint, contact: Contact) {
+    let wa = Coord::ONE / a.mass;
+    let wb = Coord::ONE / b.mass;
+    let total = wa + wb;
+    let normal = contact.normal;
+    a.position -= normal * contact.penetration * wa / total;
+    b.position += normal * contact.penetration * wb / total;
+    let approach = vec2::dot(b.velocity - a.velocity, normal);
+    if approach < Coord::ZERO {
+        let impulse = -approach / total;
+        a.velocity -= normal * impulse * wa;
+        b.velocity += normal * impulse * wb;
+    }
+}
+
+/// Returns the smallest push of `b` out of `a`.
+fn aabb_contact(a: Aabb2<Coord>, b: Aabb2<Coord>) -> Contact {
+    [
+        (vec2(Coord::ONE, Coord::ZERO), a.max.x - b.min.x),
+        (vec2(-Coord::ONE, Coord::ZERO), b.max.x - a.min.x),
+        (vec2(Coord::ZERO, Coord::ONE), a.max.y - b.min.y),
+        (vec2(Coord::ZERO, -Coord::ONE), b.max.y - a.min.y),
+    ]
+    .into_iter()
+    .map(|(normal, penetration)| Contact {
+        normal,
+        penetration,
+    })
+    .min_by_key(|contact| contact.penetration)
+    .unwrap()
+}
@@ -0,0 +1,158 @@
+use super::*;
+
+/// Rest distance between the neighbouring points of a rope.
+const SEGMENT_LENGTH: f32 = 0.4;
+const POINT_RADIUS: f32 = 0.1;
+const POINT_MASS: f32 = 0.5;
+/// Number of constraint relaxation passes per update.
+const ITERATIONS: usize = 10;
+/// Fraction of the velocity lost per second.
+const DAMPING: f32 = 0.1;
+
+/// A rope simulated as a chain of points connected by distance constraints.
+#[derive(Debug, Clone)]
+pub struct RopeChain {
+    /// Points from the anchor to the loose end. The first point never moves.
+    pub points: Vec<PhysicsPoint>,
+    segment_length: Coord,
+}
+
+impl RopeChain {
+    /// Creates a rope hanging straight down from the anchor.
+    pub fn new(anchor: Position, length: Coord) -> Self {
+        let segments = (length.as_f32() / SEGMENT_LENGTH).ceil().max(1.0) as usize;
+        let segment_length = length / Coord::new(segments as f32);
+        let points = (0..=segments)
+            .map(|i| {
+                PhysicsPoint::new(
+                    anchor - vec2(Coord::ZERO, segment_length * Coord::new(i as f32)),
+                    Coord::new(POINT_RADIUS),
+                    Mass::new(POINT_MASS),
+                )
+            })
+            .collect();
+        Self {
+            points,
+            segment_length,
+        }
+    }
+
+    pub fn anchor(&self) -> Position {
+        self.points[0].position
+    }
+
+    /// Returns the index of the point closest to the given position within the distance.
+    pub fn find_point(&self, position: Position, distance: Coord) -> Option<usize> {
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| (index, (point.position - position).len() - point.radius))
+            .filter(|(_, dist)| *dist <= distance)
+            .min_by_key(|(_, dist)| *dist)
+            .map(|(index, _)| index)
+    }
+
+    /// Simulates the rope. If a body is holding onto the rope at the `point` at most `reach`
+    /// away, then the body is pulled by the rope and the rope is pulled by the body.
+    pub fn update(
+        &mut self,
+        gravity: vec2<Coord>,
+        mut holding: Option<(usize, &mut PhysicsPoint, Coord)>,
+        delta_time: Time,
+    ) {
+        // Nothing moves, and the velocities could not be recovered from the corrections
+        if delta_time == Time::ZERO {
+            return;
+        }
+
+        // Integrate
+        let damping = (Coord::ONE - Coord::new(DAMPING) * delta_time).max(Coord::ZERO);
+        for point in self.points.iter_mut().skip(1) {
+            point.velocity = (point.velocity + gravity * delta_time) * damping;
+            point.movement(delta_time);
+        }
+        let integrated: Vec<Position> = self.points.iter().map(|point| point.position).collect();
+        let body_integrated = holding.as_ref().map(|(_, body, _)| body.position);
+
+        // Relax the constraints
+        let inverse_mass = |index: usize, point: &PhysicsPoint| {
+            if index == 0 {
+                Coord::ZERO
+            } else {
+                Coord::ONE / point.mass
+            }
+        };
+        for _ in 0..ITERATIONS {
+            for i in 1..self.points.len() {
+                let (head, tail) = self.points.split_at_mut(i);
+                let (a, b) = (&mut head[i - 1], &mut tail[0]);
+                let (wa, wb) = (inverse_mass(i - 1, a), inverse_mass(i, b));
+                separate(a, wa, b, wb, self.segment_length, false);
+            }
+            if let Some((index, body, reach)) = &mut holding {
+                let point = &mut self.points[*index];
+                let wp = inverse_mass(*index, point);
+                separate(point, wp, body, Coord::ONE / body.mass, *reach, true);
+            }
+        }
+
+        // The body is much heavier than the rope, so the relaxation cannot hold it on its own
+        if let Some((index, body, reach)) = &mut holding {
+            let limit = self.segment_length * Coord::new(*index as f32) + *reach;
+            let delta = body.position - self.anchor();
+            if delta.len() > limit {
+                body.position = self.anchor() + delta.normalize_or_zero() * limit;
+            }
+        }
+
+        // Convert the corrections into velocities
+        for (point, integrated) in self.points.iter_mut().zip(integrated).skip(1) {
+            point.velocity += (point.position - integrated) / delta_time;
+        }
+        if let (Some((_, body, _)), Some(integrated)) = (holding, body_integrated) {
+            body.velocity += (body.position - integrated) / delta_time;
+        }
+    }
+}
+
+/// Moves the points to the given distance apart proportionally to their inverse masses.
+/// If `max_only` is set, then the points are only pulled together, but never pushed apart.
+fn separate(
+    a: &mut PhysicsPoint,
+    wa: Coord,
+    b: &mut PhysicsPoint,
+    wb: Coord,
+    distance: Coord,
+    max_only: bool,
+) {
+    let delta = b.position - a.position;
+    let len = delta.len();
+    let total = wa + wb;
+    if len == Coord::ZERO || total == Coord::ZERO || max_only && len <= distance {
+        return;
+    }
+    let correction = delta / len * (len - distance) / total;
+    a.position += correction * wa;
+    b.position -= correction * wb;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_step_changes_nothing() {
+        let mut rope = RopeChain::new(vec2::ZERO, r32(2.0));
+        let gravity = vec2(0.0, -9.8).map(r32);
+        let mut body = PhysicsPoint::new(vec2(1.0, -2.5).map(r32), r32(1.0), r32(20.0));
+        let index = rope.points.len() - 1;
+        rope.update(gravity, Some((index, &mut body, r32(1.0))), Time::new(0.1));
+
+        let points: Vec<Position> = rope.points.iter().map(|point| point.position).collect();
+        let body_position = body.position;
+        rope.update(gravity, Some((index, &mut body, r32(1.0))), Time::ZERO);
+        let after: Vec<Position> = rope.points.iter().map(|point| point.position).collect();
+        assert_eq!(points, after);
+        assert_eq!(body.position, body_position);
+    }
+}
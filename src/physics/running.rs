@@ -6,7 +6,7 @@ const RECORD_LENGTH: f32 = 2.0;
 const MAX_AMPLITUDE: f32 = 0.8;
 
 const WALKING_SPEED: f32 = 2.0;
-const MAX_RUNNING_SPEED: f32 = 6.0;
+pub const MAX_RUNNING_SPEED: f32 = 6.0;
 
 #[derive(Debug, Clone)]
 pub struct BodyMovementHistory {
@@ -47,6 +47,18 @@ impl BodyMovementHistory {
         self.states.push_back(state);
     }
 
+    /// Whether the hand has swung past the body center in the last update,
+    /// which happens once per step.
+    pub fn swung_past_center(&self) -> bool {
+        let mut last = self.states.iter().rev();
+        match (last.next(), last.next()) {
+            (Some(state), Some(previous)) => {
+                (state.hand.x >= Coord::ZERO) != (previous.hand.x >= Coord::ZERO)
+            }
+            _ => false,
+        }
+    }
+
     pub fn analyze(&self) -> BodyMovementInfo {
         let mut info = BodyMovementInfo {
             positive_time: Time::ZERO,
@@ -98,3 +110,50 @@ impl Default for BodyMovementHistory {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    proptest! {
+        /// Only the records from the last `RECORD_LENGTH` seconds are kept.
+        #[test]
+        fn history_is_trimmed(steps in proptest::collection::vec(0.001f32..0.5, 1..500)) {
+            let mut history = BodyMovementHistory::default();
+            for &delta_time in &steps {
+                let state = BodyMovementState {
+                    time: Time::ZERO,
+                    hand: vec2(r32(delta_time), Coord::ZERO),
+                };
+                history.update(state, r32(delta_time));
+                let limit = history.time - Time::new(RECORD_LENGTH);
+                prop_assert!(history.states.iter().all(|state| state.time > limit));
+                // The latest record is always kept
+                prop_assert_eq!(history.states.back().unwrap().time, history.time);
+            }
+            let total: f32 = steps.iter().sum();
+            prop_assert!((history.time.as_f32() - total).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn swinging_the_arm_speeds_up_the_running() {
+        let mut still = BodyMovementHistory::default();
+        let mut swinging = BodyMovementHistory::default();
+        let delta_time = r32(1.0 / 60.0);
+        for frame in 0..120 {
+            let hand = |x: f32| BodyMovementState {
+                time: Time::ZERO,
+                hand: vec2(r32(x), r32(-0.5)),
+            };
+            still.update(hand(0.0), delta_time);
+            swinging.update(hand((frame as f32 / 10.0).sin()), delta_time);
+        }
+        let still = still.analyze().calc_stats().move_speed.as_f32();
+        let swinging = swinging.analyze().calc_stats().move_speed.as_f32();
+        assert_eq!(still, WALKING_SPEED);
+        assert_eq!(swinging, MAX_RUNNING_SPEED);
+    }
+}
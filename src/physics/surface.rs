@@ -1,12 +1,39 @@
 use super::*;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Surface {
     pub p1: vec2<Coord>,
     pub p2: vec2<Coord>,
+    #[serde(default)]
+    pub flags: SurfaceFlags,
+    /// Name of the surface style from the assets.
+    #[serde(default)]
+    pub style: Option<String>,
+    /// Name the level script refers to the surface by, shared by the surfaces changed together.
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SurfaceFlags {
+    /// Touching the surface kills the player.
+    pub spikes: bool,
+    /// The surface neither collides nor is drawn, until the level script enables it.
+    pub disabled: bool,
 }
 
 impl Surface {
+    pub fn new(p1: vec2<Coord>, p2: vec2<Coord>) -> Self {
+        Self {
+            p1,
+            p2,
+            flags: default(),
+            style: None,
+            tag: None,
+        }
+    }
+
     pub fn segment_f32(&self) -> Segment<f32> {
         Segment(self.p1.map(Coord::as_f32), self.p2.map(Coord::as_f32))
     }
@@ -23,3 +50,57 @@ impl Surface {
         normal * penetration
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    fn surface(p1: (f32, f32), p2: (f32, f32)) -> Surface {
+        Surface::new(vec2(p1.0, p1.1).map(r32), vec2(p2.0, p2.1).map(r32))
+    }
+
+    #[test]
+    fn endpoint_regions() {
+        let surface = surface((0.0, 0.0), (4.0, 0.0));
+        let delta = surface.delta_to(vec2(-1.0, 1.0).map(r32));
+        assert_eq!(delta, vec2(1.0, -1.0).map(r32));
+        let delta = surface.delta_to(vec2(6.0, -2.0).map(r32));
+        assert_eq!(delta, vec2(-2.0, 2.0).map(r32));
+    }
+
+    #[test]
+    fn interior_region() {
+        let surface = surface((0.0, 0.0), (4.0, 0.0));
+        let delta = surface.delta_to(vec2(1.5, 2.0).map(r32));
+        assert_eq!(delta, vec2(0.0, -2.0).map(r32));
+        let delta = surface.delta_to(vec2(3.0, -0.5).map(r32));
+        assert_eq!(delta, vec2(0.0, 0.5).map(r32));
+    }
+
+    proptest! {
+        /// The delta leads to the closest point of the segment.
+        #[test]
+        fn delta_leads_to_the_closest_point(
+            p1 in (-10.0f32..10.0, -10.0f32..10.0),
+            p2 in (-10.0f32..10.0, -10.0f32..10.0),
+            point in (-20.0f32..20.0, -20.0f32..20.0),
+            t in 0.0f32..=1.0,
+        ) {
+            let surface = surface(p1, p2);
+            prop_assume!((surface.p2 - surface.p1).len() > r32(0.01));
+            let point = vec2(point.0, point.1);
+            let closest = point + surface.delta_to(point.map(r32)).map(R32::as_f32);
+            // The closest point lies on the segment
+            let Segment(a, b) = surface.segment_f32();
+            let side = vec2::skew(b - a, closest - a) / (b - a).len();
+            prop_assert!(side.abs() < 1e-3);
+            let along = vec2::dot(closest - a, b - a) / (b - a).len_sqr();
+            prop_assert!((-1e-3..=1.0 + 1e-3).contains(&along));
+            // And no other point of the segment is closer
+            let other = a + (b - a) * t;
+            prop_assert!((closest - point).len() <= (other - point).len() + 1e-3);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use super::*;
+
+/// Side of a square grid cell.
+const CELL_SIZE: f32 = 4.0;
+
+/// Uniform grid over the surfaces of a level to speed up the spatial queries.
+/// It stores the indices of the surfaces, so it has to be rebuilt when surfaces are removed
+/// from the level, while the surfaces that move can be removed and inserted again.
+#[derive(Debug, Clone, Default)]
+pub struct SurfaceGrid {
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl SurfaceGrid {
+    pub fn new(surfaces: &[Surface]) -> Self {
+        let mut grid = Self::default();
+        for (index, surface) in surfaces.iter().enumerate() {
+            grid.insert(index, surface);
+        }
+        grid
+    }
+
+    /// Creates the grid over the surfaces that are not disabled, so that they are ignored
+    /// by the collisions.
+    pub fn enabled(surfaces: &[Surface]) -> Self {
+        let mut grid = Self::default();
+        for (index, surface) in surfaces.iter().enumerate() {
+            if !surface.flags.disabled {
+                grid.insert(index, surface);
+            }
+        }
+        grid
+    }
+
+    /// Adds the surface with the given index to every cell it passes through.
+    pub fn insert(&mut self, index: usize, surface: &Surface) {
+        for cell in cells_of(surface) {
+            self.cells.entry(cell).or_default().push(index);
+        }
+    }
+
+    /// Removes the surface with the given index, which has to be where it was when inserted.
+    pub fn remove(&mut self, index: usize, surface: &Surface) {
+        for cell in cells_of(surface) {
+            if let Some(indices) = self.cells.get_mut(&cell) {
+                indices.retain(|&other| other != index);
+                if indices.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// Returns the sorted indices of the surfaces that may intersect the area.
+    pub fn query(&self, area: Aabb2<Coord>) -> Vec<usize> {
+        let mut indices: Vec<usize> = cells_in(area)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    /// Returns the surfaces that may intersect the area, in the order they appear in the level.
+    pub fn surfaces_in<'a>(
+        &self,
+        surfaces: &'a [Surface],
+        area: Aabb2<Coord>,
+    ) -> impl Iterator<Item = (usize, &'a Surface)> + 'a {
+        self.query(area)
+            .into_iter()
+            .filter_map(|index| surfaces.get(index).map(|surface| (index, surface)))
+    }
+}
+
+fn cells_of(surface: &Surface) -> impl Iterator<Item = (i64, i64)> + '_ {
+    let bounds = Aabb2::points_bounding_box([surface.p1, surface.p2]).unwrap();
+    let half_cell = Coord::new(CELL_SIZE / 2.0);
+    let cell_radius = half_cell * Coord::new(std::f32::consts::SQRT_2);
+    cells_in(bounds).filter(move |cell| {
+        let center = vec2(
+            Coord::new(cell.0 as f32 * CELL_SIZE),
+            Coord::new(cell.1 as f32 * CELL_SIZE),
+        ) + vec2::splat(half_cell);
+        surface.delta_to(center).len() <= cell_radius
+    })
+}
+
+fn cells_in(area: Aabb2<Coord>) -> impl Iterator<Item = (i64, i64)> {
+    let cell = |x: Coord| (x.as_f32() / CELL_SIZE).floor() as i64;
+    let (min_x, max_x) = (cell(area.min.x), cell(area.max.x));
+    let (min_y, max_y) = (cell(area.min.y), cell(area.max.y));
+    (min_x..=max_x).flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+}
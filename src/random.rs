@@ -0,0 +1,15 @@
+/// Tiny deterministic random number generator, so that the runs are comparable
+/// and the effects do not depend on the platform.
+#[derive(Debug, Clone, Default)]
+pub struct Lcg(pub u64);
+
+impl Lcg {
+    /// Returns a number in the range `0.0..1.0`.
+    pub fn next(&mut self) -> f32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
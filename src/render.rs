@@ -2,7 +2,9 @@ use geng::Camera2d;
 
 use super::*;
 
-use physics::*;
+mod particles;
+
+pub use particles::*;
 
 const HAND_TARGET_COLOR: Rgba<f32> = Rgba {
     r: 0.7,
@@ -16,50 +18,181 @@ const HAND_TARGET_HOLD_COLOR: Rgba<f32> = Rgba {
     b: 0.4,
     a: 1.0,
 };
-const SHOULDER_COLOR: Rgba<f32> = Rgba {
-    r: 0.5,
+const CHECKPOINT_COLOR: Rgba<f32> = Rgba {
+    r: 0.3,
+    g: 0.8,
+    b: 0.3,
+    a: 1.0,
+};
+const FINISH_COLOR: Rgba<f32> = Rgba {
+    r: 0.9,
+    g: 0.8,
+    b: 0.2,
+    a: 0.3,
+};
+const GHOST_ALPHA: f32 = 0.3;
+const HAZARD_COLOR: Rgba<f32> = Rgba {
+    r: 0.8,
+    g: 0.2,
+    b: 0.2,
+    a: 1.0,
+};
+const KILL_ZONE_COLOR: Rgba<f32> = Rgba {
+    r: 0.8,
+    g: 0.2,
+    b: 0.2,
+    a: 0.3,
+};
+const SPIKE_SIZE: f32 = 0.3;
+const COLLECTIBLE_COLOR: Rgba<f32> = Rgba {
+    r: 1.0,
+    g: 0.85,
+    b: 0.2,
+    a: 1.0,
+};
+const HAND_COLLECTIBLE_COLOR: Rgba<f32> = Rgba {
+    r: 0.3,
+    g: 0.9,
+    b: 1.0,
+    a: 1.0,
+};
+const BAR_COLOR: Rgba<f32> = Rgba {
+    r: 0.8,
+    g: 0.6,
+    b: 0.4,
+    a: 1.0,
+};
+const ROPE_COLOR: Rgba<f32> = Rgba {
+    r: 0.7,
     g: 0.5,
+    b: 0.3,
+    a: 1.0,
+};
+const PROP_COLOR: Rgba<f32> = Rgba {
+    r: 0.6,
+    g: 0.45,
+    b: 0.25,
+    a: 1.0,
+};
+const DOOR_COLOR: Rgba<f32> = Rgba {
+    r: 0.35,
+    g: 0.4,
     b: 0.5,
     a: 1.0,
 };
-const ELBOW_COLOR: Rgba<f32> = Rgba {
-    r: 0.7,
-    g: 0.7,
-    b: 0.7,
+const SWITCH_OFF_COLOR: Rgba<f32> = Rgba {
+    r: 0.6,
+    g: 0.3,
+    b: 0.3,
     a: 1.0,
 };
-const HAND_COLOR: Rgba<f32> = Rgba {
-    r: 0.9,
+const SWITCH_ON_COLOR: Rgba<f32> = Rgba {
+    r: 0.3,
+    g: 0.8,
+    b: 0.4,
+    a: 1.0,
+};
+const LEVER_LENGTH: f32 = 0.6;
+/// Tilt of the lever from the vertical, to the left when off and to the right when on.
+const LEVER_TILT: f32 = 30.0;
+/// Drawn in place of the sprites missing from the assets.
+const MISSING_SPRITE_COLOR: Rgba<f32> = Rgba {
+    r: 1.0,
+    g: 0.0,
+    b: 1.0,
+    a: 0.5,
+};
+/// Extra space around the view, so that the thick lines crossing the border are not culled.
+const CULL_MARGIN: f32 = 1.0;
+const SUMMARY_TEXT_SIZE: f32 = 32.0;
+const OVERLAY_COLOR: Rgba<f32> = Rgba {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.7,
+};
+const DEATH_RING_RADIUS: f32 = 3.0;
+
+const HUD_TEXT_SIZE: f32 = 24.0;
+const HUD_MARGIN: f32 = 16.0;
+const SPLIT_AHEAD_COLOR: Rgba<f32> = Rgba {
+    r: 0.4,
     g: 0.9,
-    b: 0.9,
+    b: 0.4,
     a: 1.0,
 };
+const SPLIT_BEHIND_COLOR: Rgba<f32> = Rgba {
+    r: 0.9,
+    g: 0.4,
+    b: 0.4,
+    a: 1.0,
+};
+/// How long the split time delta stays on the screen after reaching a checkpoint.
+const SPLIT_DISPLAY_TIME: f32 = 3.0;
 
 pub struct Render {
     geng: Geng,
-    // assets: Rc<Assets>,
+    assets: Rc<Assets>,
     pub camera: Camera2d,
+    pub particles: Particles,
 }
 
 impl Render {
-    pub fn new(geng: &Geng, _assets: &Rc<Assets>) -> Self {
+    pub fn new(geng: &Geng, assets: &Rc<Assets>) -> Self {
         Self {
             geng: geng.clone(),
-            // assets: assets.clone(),
+            assets: assets.clone(),
             camera: Camera2d {
                 center: vec2::ZERO,
                 rotation: Angle::ZERO,
                 fov: 20.0,
             },
+            particles: default(),
+        }
+    }
+
+    /// Spawns the effects of the gameplay events.
+    pub fn handle_events(&mut self, events: &[Event]) {
+        for event in events {
+            self.particles.spawn(event);
         }
     }
 
-    pub fn draw(&self, model: &Model, control: &BodyControl, framebuffer: &mut ugli::Framebuffer) {
+    pub fn update(&mut self, delta_time: f32) {
+        self.particles.update(delta_time);
+    }
+
+    pub fn draw(
+        &self,
+        model: &Model,
+        control: &BodyControl,
+        ghost: Option<&GhostRun>,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        self.draw_background(&model.level, framebuffer);
+
         // Level
-        self.draw_level(&model.level, framebuffer);
+        self.draw_level(&model.level, &model.surface_grid, model.time, framebuffer);
+        self.draw_wiring(&model.level, &model.wiring, framebuffer);
+        self.draw_ropes(&model.ropes, framebuffer);
+        self.draw_props(&model.props, framebuffer);
+        self.draw_collectibles(&model.level, &model.run.collected, framebuffer);
+
+        // Ghost
+        if let Some(pose) = ghost.and_then(|ghost| ghost.pose_at(model.run.time)) {
+            self.draw_body(&pose, GHOST_ALPHA, framebuffer);
+        }
 
         // Body
-        self.draw_body(&model.player, framebuffer);
+        if let Some(death) = &model.death {
+            self.draw_death(&model.player, death, framebuffer);
+            self.draw_particles(framebuffer);
+            self.draw_foreground(&model.level, framebuffer);
+            return;
+        }
+        self.draw_body(&BodyPose::from_body(&model.player), 1.0, framebuffer);
+        self.draw_particles(framebuffer);
+        self.draw_foreground(&model.level, framebuffer);
 
         // Hand target
         let hand_target = control.hand_target + model.player.center.position;
@@ -71,35 +204,685 @@ impl Render {
         self.draw_point(hand_target, Coord::new(0.3), color, framebuffer);
     }
 
-    fn draw_body(&self, body: &Body, framebuffer: &mut ugli::Framebuffer) {
-        // Body
-        self.draw_point(
-            body.center.position,
-            body.center.radius,
-            Rgba::GRAY,
+    /// Draws the character with the skin from the assets.
+    pub fn draw_body(&self, pose: &BodyPose, alpha: f32, framebuffer: &mut ugli::Framebuffer) {
+        let skin = &self.assets.character;
+        let center = pose.body.position.map(Coord::as_f32);
+        let radius = pose.body.radius.as_f32();
+        let mirror = if pose.facing_left {
+            vec2(-1.0, 1.0)
+        } else {
+            vec2(1.0, 1.0)
+        };
+        let mut draw = |from: vec2<f32>, to: vec2<f32>, direction: vec2<f32>, part: &SkinPart| {
+            self.draw_bone(
+                from,
+                to,
+                direction,
+                part,
+                pose.facing_left,
+                alpha,
+                framebuffer,
+            );
+        };
+
+        // Torso and head
+        for bone in [&skin.torso, &skin.head] {
+            let from = center + bone.from * mirror * radius;
+            let to = center + bone.to * mirror * radius;
+            draw(from, to, (to - from).normalize_or_zero(), &bone.part);
+        }
+
+        // Arm
+        let [shoulder, elbow, hand] =
+            [pose.shoulder, pose.elbow, pose.hand].map(|joint| joint.position.map(Coord::as_f32));
+        draw(
+            shoulder,
+            elbow,
+            (elbow - shoulder).normalize_or_zero(),
+            &skin.upper_arm,
+        );
+        let forearm = (hand - elbow).normalize_or_zero();
+        draw(elbow, hand, forearm, &skin.forearm);
+        draw(hand, hand, forearm, &skin.hand);
+    }
+
+    /// Draws a capsule between the points, or the sprite stretched over the same area.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_bone(
+        &self,
+        from: vec2<f32>,
+        to: vec2<f32>,
+        direction: vec2<f32>,
+        part: &SkinPart,
+        flip: bool,
+        alpha: f32,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let color = Rgba {
+            a: part.color.a * alpha,
+            ..part.color
+        };
+        let half_width = part.width / 2.0;
+        let texture = part
+            .sprite
+            .as_ref()
+            .and_then(|name| self.assets.sprites.get(name));
+        let Some(texture) = texture else {
+            if from != to {
+                self.geng.draw2d().draw2d(
+                    framebuffer,
+                    &self.camera,
+                    &draw2d::Segment::new(Segment(from, to), part.width, color),
+                );
+            }
+            for end in [from, to] {
+                self.geng.draw2d().draw2d(
+                    framebuffer,
+                    &self.camera,
+                    &draw2d::Ellipse::circle(end, half_width, color),
+                );
+            }
+            return;
+        };
+
+        let direction = if direction == vec2::ZERO {
+            vec2(1.0, 0.0)
+        } else {
+            direction
+        };
+        let start = from - direction * half_width;
+        let end = to + direction * half_width;
+        let normal = direction.rotate_90() * half_width;
+        // Mirroring across the bone keeps the sprite facing the same side as the character
+        let (bottom, top) = if flip { (1.0, 0.0) } else { (0.0, 1.0) };
+        let vertex = |a_pos: vec2<f32>, a_vt: vec2<f32>| draw2d::TexturedVertex {
+            a_pos,
+            a_color: color,
+            a_vt,
+        };
+        self.geng.draw2d().draw2d(
             framebuffer,
+            &self.camera,
+            &draw2d::TexturedPolygon::new(
+                vec![
+                    vertex(start - normal, vec2(0.0, bottom)),
+                    vertex(end - normal, vec2(1.0, bottom)),
+                    vertex(end + normal, vec2(1.0, top)),
+                    vertex(start + normal, vec2(0.0, top)),
+                ],
+                texture,
+            ),
         );
+    }
 
-        // Arm skeleton
-        let [shoulder, elbow, hand] = body.arm.get_skeleton(&body.center);
-        self.draw_point(
-            shoulder.position,
-            shoulder.radius,
-            SHOULDER_COLOR,
+    fn draw_particles(&self, framebuffer: &mut ugli::Framebuffer) {
+        let view = self.view_area(framebuffer);
+        for particle in &self.particles.items {
+            if !view.contains(particle.position) {
+                continue;
+            }
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.camera,
+                &draw2d::Quad::new(
+                    Aabb2::point(particle.position).extend_uniform(particle.size / 2.0),
+                    particle.current_color(),
+                ),
+            );
+        }
+    }
+
+    /// Draws the results of the finished run in the middle of the screen.
+    pub fn draw_summary(
+        &self,
+        model: &Model,
+        results: &LevelResults,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let run = &model.run;
+        let Some(time) = run.recording.finish_time else {
+            return;
+        };
+
+        let size = framebuffer.size().map(|x| x as f32);
+        self.geng.draw2d().draw2d(
+            framebuffer,
+            &geng::PixelPerfectCamera,
+            &draw2d::Quad::new(Aabb2::ZERO.extend_positive(size), OVERLAY_COLOR),
+        );
+
+        let mut lines = vec![
+            "Level complete".to_owned(),
+            format!("Time: {:.2}", time.as_f32()),
+            format!("Deaths: {}", run.deaths),
+        ];
+        if !model.level.collectibles.is_empty() {
+            lines.push(format!(
+                "Collected: {}/{} (best {})",
+                run.collected.len(),
+                model.level.collectibles.len(),
+                results.most_collected
+            ));
+        }
+        if let Some(best) = results.best_time {
+            lines.push(format!("Best time: {:.2}", best.as_f32()));
+        }
+        lines.push("Press jump to retry".to_owned());
+
+        let font = self.geng.default_font();
+        let top = size.y / 2.0 + lines.len() as f32 * SUMMARY_TEXT_SIZE * 0.75;
+        for (i, line) in lines.iter().enumerate() {
+            font.draw(
+                framebuffer,
+                &geng::PixelPerfectCamera,
+                line,
+                vec2::splat(geng::TextAlign::CENTER),
+                mat3::translate(vec2(size.x / 2.0, top - i as f32 * SUMMARY_TEXT_SIZE * 1.5))
+                    * mat3::scale_uniform(SUMMARY_TEXT_SIZE),
+                Rgba::WHITE,
+            );
+        }
+    }
+
+    /// Draws the body fading out and a ring expanding from the place of death.
+    fn draw_death(&self, body: &Body, death: &Death, framebuffer: &mut ugli::Framebuffer) {
+        let t = (death.time.as_f32() / logic::DEATH_DURATION).clamp(0.0, 1.0);
+        self.draw_body(&BodyPose::from_body(body), 1.0 - t, framebuffer);
+
+        let radius = body.center.radius.as_f32() + DEATH_RING_RADIUS * t;
+        self.geng.draw2d().draw2d(
             framebuffer,
+            &self.camera,
+            &draw2d::Ellipse::circle_with_cut(
+                death.position.map(Coord::as_f32),
+                radius * 0.9,
+                radius,
+                Rgba {
+                    a: 1.0 - t,
+                    ..HAZARD_COLOR
+                },
+            ),
         );
-        self.draw_point(elbow.position, elbow.radius, ELBOW_COLOR, framebuffer);
-        self.draw_point(hand.position, hand.radius, HAND_COLOR, framebuffer);
     }
 
-    pub fn draw_level(&self, level: &Level, framebuffer: &mut ugli::Framebuffer) {
-        for surface in &level.surfaces {
+    /// Draws the run timer and the time difference to the best run.
+    pub fn draw_hud(
+        &self,
+        model: &Model,
+        best: Option<&GhostRun>,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let run = &model.run;
+        let size = framebuffer.size().map(|x| x as f32);
+        let font = self.geng.default_font();
+        let mut line = 0;
+        let mut draw_line = |text: &str, color: Rgba<f32>| {
+            let position = vec2(
+                HUD_MARGIN,
+                size.y - HUD_MARGIN - (line as f32 + 1.0) * HUD_TEXT_SIZE,
+            );
+            font.draw(
+                framebuffer,
+                &geng::PixelPerfectCamera,
+                text,
+                vec2::splat(geng::TextAlign::LEFT),
+                mat3::translate(position) * mat3::scale_uniform(HUD_TEXT_SIZE),
+                color,
+            );
+            line += 1;
+        };
+
+        let time = run.recording.finish_time.unwrap_or(run.time);
+        draw_line(&format!("{:.2}", time.as_f32()), Rgba::WHITE);
+        if let Some(best) = best.and_then(|best| best.finish_time) {
+            draw_line(&format!("Best: {:.2}", best.as_f32()), Rgba::GRAY);
+        }
+        if run.deaths > 0 {
+            draw_line(&format!("Deaths: {}", run.deaths), HAZARD_COLOR);
+        }
+        if !model.level.collectibles.is_empty() {
+            draw_line(
+                &format!(
+                    "Collected: {}/{}",
+                    run.collected.len(),
+                    model.level.collectibles.len()
+                ),
+                COLLECTIBLE_COLOR,
+            );
+        }
+
+        // Split delta
+        let split = match run.recording.finish_time {
+            Some(time) => best
+                .and_then(|best| best.finish_time)
+                .map(|best_time| (time, best_time)),
+            None => run
+                .recording
+                .splits
+                .last()
+                .filter(|&&(_, time)| run.time - time < Time::new(SPLIT_DISPLAY_TIME))
+                .and_then(|&(index, time)| {
+                    best.and_then(|best| best.split(index))
+                        .map(|best_time| (time, best_time))
+                }),
+        };
+        if let Some((time, best_time)) = split {
+            let delta = (time - best_time).as_f32();
+            let color = if delta <= 0.0 {
+                SPLIT_AHEAD_COLOR
+            } else {
+                SPLIT_BEHIND_COLOR
+            };
+            draw_line(&format!("{delta:+.2}"), color);
+        }
+    }
+
+    /// Returns the part of the world visible on the framebuffer, extended by a small margin.
+    fn view_area(&self, framebuffer: &ugli::Framebuffer) -> Aabb2<f32> {
+        self.camera
+            .view_area(framebuffer.size().map(|x| x as f32))
+            .bounding_box()
+            .extend_uniform(CULL_MARGIN)
+    }
+
+    /// Draws the level geometry in view, the surfaces are looked up in the grid over them.
+    pub fn draw_level(
+        &self,
+        level: &Level,
+        surface_grid: &SurfaceGrid,
+        time: Time,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let view = self.view_area(framebuffer);
+        let visible = |p1: vec2<Coord>, p2: vec2<Coord>| {
+            Aabb2::points_bounding_box([p1, p2])
+                .unwrap()
+                .map(Coord::as_f32)
+                .intersects(&view)
+        };
+        let default_style = SurfaceStyle::default();
+        // The fills reach past the surfaces, so the ones just outside of the view may show
+        let fill_reach = self
+            .assets
+            .surface_styles
+            .values()
+            .filter_map(|style| style.fill.as_ref())
+            .map(|fill| fill.offset.len())
+            .fold(0.0, f32::max);
+        let area = view.extend_uniform(fill_reach).map(Coord::new);
+        let surfaces: Vec<(&Surface, &SurfaceStyle)> = surface_grid
+            .surfaces_in(&level.surfaces, area)
+            .map(|(_, surface)| surface)
+            .filter(|surface| !surface.flags.disabled)
+            .map(|surface| {
+                let style = surface
+                    .style
+                    .as_ref()
+                    .and_then(|name| self.assets.surface_styles.get(name))
+                    .unwrap_or(&default_style);
+                (surface, style)
+            })
+            .filter(|(surface, style)| {
+                let Segment(p1, p2) = surface.segment_f32();
+                let offset = style.fill.as_ref().map_or(vec2::ZERO, |fill| fill.offset);
+                Aabb2::points_bounding_box([p1, p2, p1 + offset, p2 + offset])
+                    .unwrap()
+                    .intersects(&view)
+            })
+            .collect();
+        // Fills go first, so that they do not cover the neighbouring surfaces
+        for (surface, style) in &surfaces {
+            if let Some(fill) = &style.fill {
+                let Segment(p1, p2) = surface.segment_f32();
+                self.geng.draw2d().draw2d(
+                    framebuffer,
+                    &self.camera,
+                    &draw2d::Polygon::new(
+                        vec![p1, p2, p2 + fill.offset, p1 + fill.offset],
+                        fill.color,
+                    ),
+                );
+            }
+        }
+        for (surface, style) in surfaces {
+            if surface.flags.spikes {
+                self.draw_spikes(surface, framebuffer);
+            } else {
+                self.draw_surface(surface, style, framebuffer);
+            }
+        }
+        for bar in &level.bars {
+            if !visible(bar.p1, bar.p2) {
+                continue;
+            }
             self.geng.draw2d().draw2d(
                 framebuffer,
                 &self.camera,
-                &draw2d::Segment::new(surface.segment_f32(), 0.1, Rgba::GRAY),
+                &draw2d::Segment::new(bar.segment_f32(), 0.15, BAR_COLOR),
             );
         }
+        for hazard in &level.hazards {
+            let area = hazard.area(time).map(Coord::as_f32);
+            if !area.intersects(&view) {
+                continue;
+            }
+            let color = match hazard {
+                Hazard::KillZone(_) => KILL_ZONE_COLOR,
+                Hazard::Crusher(_) => HAZARD_COLOR,
+            };
+            self.geng
+                .draw2d()
+                .draw2d(framebuffer, &self.camera, &draw2d::Quad::new(area, color));
+        }
+        for checkpoint in &level.checkpoints {
+            if !view.contains(checkpoint.position.map(Coord::as_f32)) {
+                continue;
+            }
+            self.draw_point(
+                checkpoint.position,
+                Coord::new(0.5),
+                CHECKPOINT_COLOR,
+                framebuffer,
+            );
+        }
+        if let Some(finish) = level.finish {
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.camera,
+                &draw2d::Quad::new(finish.map(Coord::as_f32), FINISH_COLOR),
+            );
+        }
+    }
+
+    /// Draws the background layers and the decorations behind the gameplay layer.
+    pub fn draw_background(&self, level: &Level, framebuffer: &mut ugli::Framebuffer) {
+        let view = self.view_area(framebuffer);
+        for layer in &level.backgrounds {
+            let area = layer.area_at(self.camera.center);
+            let width = area.width();
+            if width <= 0.0 || area.max.y < view.min.y || area.min.y > view.max.y {
+                continue;
+            }
+            let first = ((view.min.x - area.min.x) / width).floor() as i32;
+            let last = ((view.max.x - area.min.x) / width).ceil() as i32;
+            for i in first..last {
+                let area = area.translate(vec2(i as f32 * width, 0.0));
+                self.draw_sprite(&layer.sprite, area, framebuffer);
+            }
+        }
+        self.draw_decorations(level, false, framebuffer);
+    }
+
+    /// Draws the decorations over the gameplay layer.
+    pub fn draw_foreground(&self, level: &Level, framebuffer: &mut ugli::Framebuffer) {
+        self.draw_decorations(level, true, framebuffer);
+    }
+
+    fn draw_decorations(
+        &self,
+        level: &Level,
+        foreground: bool,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let view = self.view_area(framebuffer);
+        for decoration in &level.decorations {
+            if decoration.foreground != foreground
+                || !decoration.bounds().map(Coord::as_f32).intersects(&view)
+            {
+                continue;
+            }
+            match &decoration.shape {
+                DecorationShape::Sprite { sprite, area } => {
+                    self.draw_sprite(sprite, area.map(Coord::as_f32), framebuffer);
+                }
+                DecorationShape::Polygon { vertices, color } => {
+                    self.geng.draw2d().draw2d(
+                        framebuffer,
+                        &self.camera,
+                        &draw2d::Polygon::new(
+                            vertices.iter().map(|v| v.map(Coord::as_f32)).collect(),
+                            *color,
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn draw_sprite(&self, sprite: &str, area: Aabb2<f32>, framebuffer: &mut ugli::Framebuffer) {
+        match self.assets.sprites.get(sprite) {
+            Some(texture) => self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.camera,
+                &draw2d::TexturedQuad::new(area, texture),
+            ),
+            None => self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.camera,
+                &draw2d::Quad::new(area, MISSING_SPRITE_COLOR),
+            ),
+        }
+    }
+
+    /// Draws the doors and the switches in their current state.
+    pub fn draw_wiring(
+        &self,
+        level: &Level,
+        wiring: &WiringState,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let view = self.view_area(framebuffer);
+        for (door, &open) in level.doors.iter().zip(&wiring.doors) {
+            let area = door.area_at(open).map(Coord::as_f32);
+            if area.intersects(&view) {
+                self.geng.draw2d().draw2d(
+                    framebuffer,
+                    &self.camera,
+                    &draw2d::Quad::new(area, DOOR_COLOR),
+                );
+            }
+        }
+        for (switch, &on) in level.switches.iter().zip(&wiring.switches) {
+            let position = switch.position.map(Coord::as_f32);
+            if !view.contains(position) {
+                continue;
+            }
+            let color = if on {
+                SWITCH_ON_COLOR
+            } else {
+                SWITCH_OFF_COLOR
+            };
+            match switch.kind {
+                SwitchKind::Plate => {
+                    // Pressed plates sink into the ground
+                    let mut area = switch.plate_area().map(Coord::as_f32);
+                    if on {
+                        area.max.y = area.center().y;
+                    }
+                    self.geng.draw2d().draw2d(
+                        framebuffer,
+                        &self.camera,
+                        &draw2d::Quad::new(area, color),
+                    );
+                }
+                SwitchKind::Lever => {
+                    let tilt = if on { -LEVER_TILT } else { LEVER_TILT };
+                    let end = position + vec2(0.0, LEVER_LENGTH).rotate(Angle::from_degrees(tilt));
+                    self.geng.draw2d().draw2d(
+                        framebuffer,
+                        &self.camera,
+                        &draw2d::Segment::new(Segment(position, end), 0.08, color),
+                    );
+                    self.geng.draw2d().draw2d(
+                        framebuffer,
+                        &self.camera,
+                        &draw2d::Ellipse::circle(position, 0.15, DOOR_COLOR),
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn draw_ropes(&self, ropes: &[RopeChain], framebuffer: &mut ugli::Framebuffer) {
+        for rope in ropes {
+            let points = rope
+                .points
+                .iter()
+                .map(|point| point.position.map(Coord::as_f32))
+                .collect();
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.camera,
+                &draw2d::Chain::new(Chain { vertices: points }, 0.1, ROPE_COLOR, 1),
+            );
+        }
+    }
+
+    pub fn draw_props(&self, props: &[PropBody], framebuffer: &mut ugli::Framebuffer) {
+        let view = self.view_area(framebuffer);
+        for prop in props {
+            if !prop.aabb().map(Coord::as_f32).intersects(&view) {
+                continue;
+            }
+            let position = prop.point.position.map(Coord::as_f32);
+            match prop.shape {
+                PropShape::Circle { radius } => {
+                    self.geng.draw2d().draw2d(
+                        framebuffer,
+                        &self.camera,
+                        &draw2d::Ellipse::circle(position, radius.as_f32(), PROP_COLOR),
+                    );
+                }
+                PropShape::Box { .. } => {
+                    self.geng.draw2d().draw2d(
+                        framebuffer,
+                        &self.camera,
+                        &draw2d::Quad::new(prop.aabb().map(Coord::as_f32), PROP_COLOR),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Draws the collectibles that have not been collected yet.
+    pub fn draw_collectibles(
+        &self,
+        level: &Level,
+        collected: &[usize],
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let radius = logic::COLLECTIBLE_RADIUS;
+        let view = self.view_area(framebuffer);
+        for (index, item) in level.collectibles.iter().enumerate() {
+            if collected.contains(&index) || !view.contains(item.position.map(Coord::as_f32)) {
+                continue;
+            }
+            let color = if item.hand_only {
+                HAND_COLLECTIBLE_COLOR
+            } else {
+                COLLECTIBLE_COLOR
+            };
+            let center = item.position.map(Coord::as_f32);
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.camera,
+                &draw2d::Polygon::new(
+                    vec![
+                        center + vec2(radius, 0.0),
+                        center + vec2(0.0, radius),
+                        center - vec2(radius, 0.0),
+                        center - vec2(0.0, radius),
+                    ],
+                    color,
+                ),
+            );
+        }
+    }
+
+    /// Draws the surface line, either solid or with the style texture repeated along it.
+    fn draw_surface(
+        &self,
+        surface: &Surface,
+        style: &SurfaceStyle,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let texture = style
+            .texture
+            .as_ref()
+            .and_then(|name| self.assets.sprites.get(name));
+        let Some(texture) = texture else {
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.camera,
+                &draw2d::Segment::new(surface.segment_f32(), style.thickness, style.color),
+            );
+            return;
+        };
+
+        let Segment(p1, p2) = surface.segment_f32();
+        let len = (p2 - p1).len();
+        let size = texture.size().map(|x| x as f32);
+        let tile = style.thickness * size.x / size.y;
+        if len <= 0.0 || tile <= 0.0 {
+            return;
+        }
+        let direction = (p2 - p1) / len;
+        let normal = direction.rotate_90() * style.thickness / 2.0;
+        let count = (len / tile).ceil() as usize;
+        for i in 0..count {
+            let start = i as f32 * tile;
+            let end = (start + tile).min(len);
+            // The last tile is cut short instead of squeezed
+            let u = (end - start) / tile;
+            let vertex = |t: f32, side: f32, a_vt: vec2<f32>| draw2d::TexturedVertex {
+                a_pos: p1 + direction * t + normal * side,
+                a_color: Rgba::WHITE,
+                a_vt,
+            };
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.camera,
+                &draw2d::TexturedPolygon::new(
+                    vec![
+                        vertex(start, -1.0, vec2(0.0, 0.0)),
+                        vertex(end, -1.0, vec2(u, 0.0)),
+                        vertex(end, 1.0, vec2(u, 1.0)),
+                        vertex(start, 1.0, vec2(0.0, 1.0)),
+                    ],
+                    texture,
+                ),
+            );
+        }
+    }
+
+    /// Draws a row of teeth on both sides of the surface.
+    fn draw_spikes(&self, surface: &Surface, framebuffer: &mut ugli::Framebuffer) {
+        self.geng.draw2d().draw2d(
+            framebuffer,
+            &self.camera,
+            &draw2d::Segment::new(surface.segment_f32(), 0.1, HAZARD_COLOR),
+        );
+
+        let Segment(p1, p2) = surface.segment_f32();
+        let delta = p2 - p1;
+        let len = delta.len();
+        let count = (len / SPIKE_SIZE).floor().max(1.0) as usize;
+        let step = delta / count as f32;
+        let normal = delta.normalize_or_zero().rotate_90() * SPIKE_SIZE;
+        for i in 0..count {
+            let start = p1 + step * i as f32;
+            for side in [normal, -normal] {
+                self.geng.draw2d().draw2d(
+                    framebuffer,
+                    &self.camera,
+                    &draw2d::Polygon::new(
+                        vec![start, start + step, start + step / 2.0 + side],
+                        HAZARD_COLOR,
+                    ),
+                );
+            }
+        }
     }
 
     fn draw_point(
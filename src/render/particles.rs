@@ -0,0 +1,183 @@
+use super::*;
+
+const MAX_PARTICLES: usize = 500;
+/// Particles fall slower than the bodies, so that the dust hangs in the air for a bit.
+const PARTICLE_GRAVITY: vec2<f32> = vec2(0.0, -4.0);
+/// Landings slower than this kick up no dust.
+const LANDING_MIN_IMPACT: f32 = 2.0;
+const TAKEOFF_MIN_IMPACT: f32 = 3.0;
+const DUST_COLOR: Rgba<f32> = Rgba {
+    r: 0.6,
+    g: 0.55,
+    b: 0.5,
+    a: 0.8,
+};
+const GRAB_COLOR: Rgba<f32> = Rgba {
+    r: 1.0,
+    g: 0.9,
+    b: 0.6,
+    a: 1.0,
+};
+const BREAK_COLOR: Rgba<f32> = Rgba {
+    r: 1.0,
+    g: 0.4,
+    b: 0.2,
+    a: 1.0,
+};
+
+#[derive(Debug, Clone)]
+pub struct Particle {
+    pub position: vec2<f32>,
+    pub velocity: vec2<f32>,
+    pub size: f32,
+    pub color: Rgba<f32>,
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+/// A group of particles emitted at once.
+struct Burst {
+    count: usize,
+    /// Main direction of the particles, they are spread around it by the angle.
+    direction: vec2<f32>,
+    spread: Angle<f32>,
+    speed: f32,
+    color: Rgba<f32>,
+    size: f32,
+    lifetime: f32,
+}
+
+/// Short-lived visual effects spawned in response to the gameplay events.
+#[derive(Debug, Clone, Default)]
+pub struct Particles {
+    pub items: Vec<Particle>,
+    rng: Lcg,
+}
+
+impl Particle {
+    /// Returns the color faded out by the age.
+    pub fn current_color(&self) -> Rgba<f32> {
+        Rgba {
+            a: self.color.a * (1.0 - self.age / self.lifetime).max(0.0),
+            ..self.color
+        }
+    }
+}
+
+impl Particles {
+    pub fn spawn(&mut self, event: &Event) {
+        let (position, burst) = match *event {
+            Event::Landed {
+                position,
+                normal,
+                impact,
+            } if impact.as_f32() > LANDING_MIN_IMPACT => {
+                let impact = impact.as_f32();
+                (
+                    position,
+                    Burst {
+                        count: (impact * 2.0).min(24.0) as usize,
+                        direction: normal.map(Coord::as_f32),
+                        spread: Angle::from_degrees(80.0),
+                        speed: impact * 0.4,
+                        color: DUST_COLOR,
+                        size: 0.12,
+                        lifetime: 0.6,
+                    },
+                )
+            }
+            Event::LeftGround {
+                position,
+                normal,
+                impact,
+            } if impact.as_f32() > TAKEOFF_MIN_IMPACT => (
+                position,
+                Burst {
+                    count: 4,
+                    direction: normal.map(Coord::as_f32),
+                    spread: Angle::from_degrees(60.0),
+                    speed: 1.0,
+                    color: DUST_COLOR,
+                    size: 0.1,
+                    lifetime: 0.4,
+                },
+            ),
+            Event::HoldStarted {
+                position, impact, ..
+            } => (
+                position,
+                Burst {
+                    count: 6,
+                    direction: vec2(0.0, 1.0),
+                    spread: Angle::from_degrees(180.0),
+                    speed: 1.5 + impact.as_f32() * 0.2,
+                    color: GRAB_COLOR,
+                    size: 0.06,
+                    lifetime: 0.25,
+                },
+            ),
+            Event::HoldEnded {
+                reason: HoldEndReason::Released,
+                position,
+                ..
+            } => (
+                position,
+                Burst {
+                    count: 3,
+                    direction: vec2(0.0, 1.0),
+                    spread: Angle::from_degrees(180.0),
+                    speed: 1.0,
+                    color: GRAB_COLOR,
+                    size: 0.05,
+                    lifetime: 0.2,
+                },
+            ),
+            Event::HoldEnded {
+                reason: HoldEndReason::Broken,
+                position,
+                impact,
+            } => (
+                position,
+                Burst {
+                    count: 12,
+                    direction: vec2(0.0, 1.0),
+                    spread: Angle::from_degrees(180.0),
+                    speed: 3.0 + impact.as_f32() * 0.3,
+                    color: BREAK_COLOR,
+                    size: 0.08,
+                    lifetime: 0.4,
+                },
+            ),
+            _ => return,
+        };
+        let position = position.map(Coord::as_f32);
+
+        for _ in 0..burst.count {
+            let angle = burst.spread * (self.rng.next() * 2.0 - 1.0);
+            let speed = burst.speed * (0.5 + self.rng.next());
+            let lifetime = burst.lifetime * (0.7 + self.rng.next() * 0.6);
+            self.items.push(Particle {
+                position,
+                velocity: burst.direction.rotate(angle) * speed,
+                size: burst.size,
+                color: burst.color,
+                age: 0.0,
+                lifetime,
+            });
+        }
+        if self.items.len() > MAX_PARTICLES {
+            let excess = self.items.len() - MAX_PARTICLES;
+            self.items.drain(..excess);
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        for particle in &mut self.items {
+            particle.velocity += PARTICLE_GRAVITY * delta_time;
+            particle.position += particle.velocity * delta_time;
+            particle.age += delta_time;
+        }
+        self.items
+            .retain(|particle| particle.age < particle.lifetime);
+    }
+}
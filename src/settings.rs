@@ -0,0 +1,57 @@
+use super::*;
+
+const CAMERA_SMOOTHING: f32 = 0.5;
+const VOLUME: f32 = 0.7;
+
+/// User preferences that are not related to the input.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Time constant in seconds of the camera catching up with its target.
+    pub camera_smoothing: f32,
+    pub screen_shake: bool,
+    /// Master volume, from 0 to 1.
+    pub volume: f32,
+}
+
+impl Settings {
+    pub fn save(&self, path: impl AsRef<std::path::Path>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            serde_json::to_writer_pretty(
+                std::fs::File::create(path).expect("Failed to create a file"),
+                &self,
+            )
+            .expect("Failed to serialize the settings");
+            log::info!("Settings saved");
+        }
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Option<Self> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            None
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let file = std::fs::File::open(path).ok()?;
+            match serde_json::from_reader(file) {
+                Ok(settings) => Some(settings),
+                Err(err) => {
+                    log::error!("Failed to deserialize the settings: {err}");
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            camera_smoothing: CAMERA_SMOOTHING,
+            screen_shake: true,
+            volume: VOLUME,
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use geng::prelude::*;
+use parkour::{
+    control::BodyControl,
+    env::{BatchEnv, Env, Observation, OBSERVATION_SIZE},
+    model::{Coord, Level},
+};
+
+fn level() -> Level {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/levels/reachable.json");
+    Level::load_full(path).unwrap()
+}
+
+/// Runs right, jumping now and then, and swinging the arm.
+fn control(step: usize) -> BodyControl {
+    let time = step as f32 / 60.0;
+    BodyControl {
+        hand_target: vec2((time * 5.0).sin(), -0.5).map(r32),
+        move_speed: Coord::ONE,
+        target_height: Coord::ONE,
+        jump: step % 90 == 45,
+        ..default()
+    }
+}
+
+fn episode(level: &Level, seed: u64) -> Vec<(Observation, f32, bool)> {
+    let mut env = Env::new(level, seed);
+    let mut steps = vec![(env.observe(), 0.0, false)];
+    for step in 0..600 {
+        let result = env.step(control(step));
+        let done = result.2;
+        steps.push(result);
+        if done {
+            break;
+        }
+    }
+    steps
+}
+
+#[test]
+fn same_seed_same_trajectory() {
+    let level = level();
+    let first = episode(&level, 7);
+    assert_eq!(first, episode(&level, 7));
+    // The seed moves the spawn point
+    assert_ne!(first[0].0, episode(&level, 8)[0].0);
+}
+
+#[test]
+fn observation_size() {
+    let level = level();
+    for (observation, _, _) in episode(&level, 1) {
+        assert_eq!(observation.len(), OBSERVATION_SIZE);
+    }
+    let mut batch = BatchEnv::new(&level, 3, 1);
+    let controls: Vec<BodyControl> = (0..3).map(control).collect();
+    for _ in 0..10 {
+        for (observation, _, _) in batch.step(&controls) {
+            assert_eq!(observation.len(), OBSERVATION_SIZE);
+        }
+    }
+}